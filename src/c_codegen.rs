@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+
+use crate::stages::common::*;
+use crate::stages::mir::*;
+use crate::shared::binops::BinaryOperator;
+use crate::shared::typing::*;
+
+
+// Translates the MIR into one C file: a struct per monomorphized newtype, a function per MIR
+// function with its cells as locals, and a label per block. It doesn't go anywhere near the LIR,
+// so it doubles as an independent check on the native backends.
+pub struct CCompiler<'a> {
+    program: &'a MIRProgram,
+    type_names: BTreeMap<ConcreteType, String>,
+    output: String,
+}
+
+impl<'a> CCompiler<'a> {
+
+    pub fn compile(program: &'a MIRProgram) -> String {
+        let mut comp = CCompiler {
+            program,
+            type_names: BTreeMap::new(),
+            output: String::new(),
+        };
+        comp.compile_program();
+        comp.output
+    }
+
+
+    fn compile_program(&mut self) {
+        self.emit("#include <stdint.h>");
+        self.emit("#include <stdio.h>");
+        self.emit("");
+        // Everything the language calls a number is 32-bit; bools and () are ints too so that
+        // they print and compare the same way they do in the native backends
+        self.emit("typedef int32_t yum_int;");
+        self.emit("typedef int32_t yum_bool;");
+        self.emit("typedef int32_t yum_unit;");
+        self.emit("");
+
+        self.compile_types();
+
+        for (id, func) in &self.program.functions {
+            let signature = self.signature(*id, func);
+            self.emit(&format!("static {};", signature));
+        }
+        self.emit("");
+
+        for (id, func) in &self.program.functions {
+            self.compile_function(*id, func);
+        }
+
+        self.emit("int main(void) {");
+        let entry = &self.program.functions[&self.program.entry];
+        match entry.ret_type {
+            // Anything wider has no exit code, same as in compiled code
+            ConcreteType::Prim(_) => {
+                self.emit(&format!("    return {}();", func_name(self.program.entry, entry)));
+            }
+            _ => {
+                self.emit(&format!("    {}();", func_name(self.program.entry, entry)));
+                self.emit("    return 0;");
+            }
+        }
+        self.emit("}");
+    }
+
+    // Dependencies come first in topo order, so each struct is complete before it's used as a field.
+    // References don't count as dependencies, so every struct is declared up front for them to point to.
+    fn compile_types(&mut self) {
+        let mut counters: BTreeMap<NewtypeId, usize> = BTreeMap::new();
+        let mut shapes: Vec<(ConcreteType, String, ConcreteShape)> = Vec::new();
+        for (id, tvars, shape) in self.program.typetable.topo_mono_iter() {
+            // Namespaced variants are `E::V`, which C won't take
+            let base = id.0.replace("::", "__");
+            let name = if tvars.is_empty() {
+                format!("yum_{}", base)
+            } else {
+                let counter = counters.entry(id.clone()).or_default();
+                *counter += 1;
+                format!("yum_{}_{}", base, counter)
+            };
+            let typ = ConcreteType::NewType(id.clone(), tvars);
+            self.type_names.insert(typ.clone(), name.clone());
+            self.emit(&format!("typedef struct {} {};", name, name));
+            shapes.push((typ, name, shape));
+        }
+        self.emit("");
+
+        for (typ, name, shape) in shapes {
+            match shape {
+                ConcreteShape::Struct { fields } => {
+                    self.emit(&format!("// {}", typ));
+                    self.emit(&format!("struct {} {{", name));
+                    for (fname, ftype) in &fields {
+                        self.emit(&format!("    {} {};", self.c_type(ftype), c_field(fname)));
+                    }
+                    self.emit("};");
+                    self.emit("");
+
+                    // C has no == on structs, so they get one compared field by field
+                    self.emit(&format!("static inline yum_bool eq_{}({} a, {} b) {{", name, name, name));
+                    let mut conds: Vec<String> = fields
+                        .iter()
+                        .map(|(fname, ftype)| {
+                            let fname = c_field(fname);
+                            self.equality(ftype, &format!("a.{}", fname), &format!("b.{}", fname))
+                        })
+                        .collect();
+                    if conds.is_empty() {
+                        conds.push("1".to_string());
+                    }
+                    self.emit(&format!("    return {};", conds.join(" && ")));
+                    self.emit("}");
+                    self.emit("");
+                }
+                // A tag saying which variant it is, and a union of them
+                ConcreteShape::Enum { variants } => {
+                    self.emit(&format!("// {}", typ));
+                    self.emit(&format!("struct {} {{", name));
+                    self.emit("    yum_int tag;");
+                    if !variants.is_empty() {
+                        self.emit("    union {");
+                        for (i, vtype) in variants.iter().enumerate() {
+                            self.emit(&format!("        {} v{};", self.c_type(vtype), i));
+                        }
+                        self.emit("    } payload;");
+                    }
+                    self.emit("};");
+                    self.emit("");
+
+                    self.emit(&format!("static inline yum_bool eq_{}({} a, {} b) {{", name, name, name));
+                    self.emit("    if (a.tag != b.tag) return 0;");
+                    for (i, vtype) in variants.iter().enumerate() {
+                        let (l, r) = (format!("a.payload.v{}", i), format!("b.payload.v{}", i));
+                        self.emit(&format!("    if (a.tag == {}) return {};", i, self.equality(vtype, &l, &r)));
+                    }
+                    self.emit("    return 1;");
+                    self.emit("}");
+                    self.emit("");
+                }
+            }
+        }
+    }
+
+
+    fn compile_function(&mut self, id: FuncId, func: &MIRFunction) {
+        let signature = self.signature(id, func);
+        self.emit(&format!("static {} {{", signature));
+        for (cell_id, cell) in &func.cells {
+            if !func.args.contains(cell_id) {
+                self.emit(&format!("    {} {};", self.c_type(&cell.typ), cell_name(*cell_id, cell)));
+            }
+        }
+        self.emit(&format!("    goto block_{};", func.entry.0));
+
+        for (block_id, block) in &func.blocks {
+            self.emit(&format!("block_{}:", block_id.0));
+            for stmt in &block.statements {
+                let line = self.compile_stmt(func, &stmt.stmt);
+                self.emit(&format!("    {}", line));
+            }
+            let line = self.compile_terminator(func, &block.terminator);
+            self.emit(&format!("    {}", line));
+        }
+        self.emit("}");
+        self.emit("");
+    }
+
+    fn compile_stmt(&self, func: &MIRFunction, stmt: &MIRStatementKind) -> String {
+        match stmt {
+            MIRStatementKind::Assign { target, value } => {
+                format!("{} = {};", self.place(func, target), self.value(func, value))
+            }
+            MIRStatementKind::BinOp { target, op, left, right } => {
+                let l = self.value(func, left);
+                let r = self.value(func, right);
+                let result = match op {
+                    // Wrapping, like the native backends; signed overflow would be undefined
+                    BinaryOperator::Add => format!("(yum_int)((uint32_t){} + (uint32_t){})", l, r),
+                    BinaryOperator::Sub => format!("(yum_int)((uint32_t){} - (uint32_t){})", l, r),
+                    BinaryOperator::Mul => format!("(yum_int)((uint32_t){} * (uint32_t){})", l, r),
+                    BinaryOperator::Modulo => format!("{} % {}", l, r),
+                    BinaryOperator::Less => format!("{} < {}", l, r),
+                    BinaryOperator::Equals => self.equality(&left.typ, &l, &r),
+                };
+                format!("{} = {};", self.place(func, target), result)
+            }
+            MIRStatementKind::Call { target, func: callee, args } => {
+                let args: Vec<String> = args.iter().map(|arg| self.value(func, arg)).collect();
+                let callee_name = func_name(*callee, &self.program.functions[callee]);
+                format!("{} = {}({});", self.place(func, target), callee_name, args.join(", "))
+            }
+            MIRStatementKind::Print(value) => {
+                format!(r#"printf("%d\n", {});"#, self.value(func, value))
+            }
+        }
+    }
+
+    fn compile_terminator(&self, func: &MIRFunction, term: &MIRTerminator) -> String {
+        match term {
+            MIRTerminator::Goto(block) => format!("goto block_{};", block.0),
+            MIRTerminator::Branch { condition, then_, else_ } => {
+                format!("if ({}) goto block_{}; else goto block_{};", self.value(func, condition), then_.0, else_.0)
+            }
+            MIRTerminator::Return(Some(value)) => format!("return {};", self.value(func, value)),
+            // Also what functions that fall off their end get, whatever they return
+            MIRTerminator::Return(None) => format!("return ({}){{0}};", self.c_type(&func.ret_type)),
+        }
+    }
+
+    fn value(&self, func: &MIRFunction, value: &MIRValue) -> String {
+        match &value.value {
+            MIRValueKind::Place(place) => self.place(func, place),
+            MIRValueKind::IntLiteral(i32::MIN) => "(-2147483647 - 1)".to_string(),
+            MIRValueKind::IntLiteral(num) => num.to_string(),
+            MIRValueKind::BoolTrue => "1".to_string(),
+            MIRValueKind::BoolFalse => "0".to_string(),
+            MIRValueKind::StructLiteral { typ, fields } => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(fname, fvalue)| format!(".{} = {}", c_field(fname), self.value(func, fvalue)))
+                    .collect();
+                format!("(({}){{ {} }})", self.c_type(typ), fields.join(", "))
+            }
+            MIRValueKind::EnumLiteral { typ, variant, payload } => {
+                format!(
+                    "(({}){{ .tag = {}, .payload.v{} = {} }})",
+                    self.c_type(typ), variant, variant, self.value(func, payload)
+                )
+            }
+            MIRValueKind::Reference(place) => format!("&{}", self.place(func, place)),
+        }
+    }
+
+    fn place(&self, func: &MIRFunction, place: &MIRPlace) -> String {
+        let mut lowered = match place.base {
+            MIRPlaceBase::Cell(cell) => cell_name(cell, &func.cells[&cell]),
+            MIRPlaceBase::Deref(cell) => format!("(*{})", cell_name(cell, &func.cells[&cell])),
+        };
+        for proj in &place.fieldchain {
+            match proj {
+                Projection::Field(field) => lowered.push_str(&format!(".{}", c_field(field))),
+                Projection::Tag => lowered.push_str(".tag"),
+                Projection::Payload(i) => lowered.push_str(&format!(".payload.v{}", i)),
+            }
+        }
+        lowered
+    }
+
+    fn equality(&self, typ: &ConcreteType, l: &str, r: &str) -> String {
+        match typ {
+            ConcreteType::NewType(..) => format!("eq_{}({}, {})", self.type_names[typ], l, r),
+            ConcreteType::Prim(_) | ConcreteType::Reference(_) => format!("{} == {}", l, r),
+        }
+    }
+
+    fn signature(&self, id: FuncId, func: &MIRFunction) -> String {
+        let args: Vec<String> = func.args
+            .iter()
+            .map(|cell| format!("{} {}", self.c_type(&func.cells[cell].typ), cell_name(*cell, &func.cells[cell])))
+            .collect();
+        let args = if args.is_empty() { "void".to_string() } else { args.join(", ") };
+        format!("{} {}({})", self.c_type(&func.ret_type), func_name(id, func), args)
+    }
+
+    fn c_type(&self, typ: &ConcreteType) -> String {
+        match typ {
+            ConcreteType::Prim(PrimType::Integer) => "yum_int".to_string(),
+            ConcreteType::Prim(PrimType::Bool) => "yum_bool".to_string(),
+            ConcreteType::Prim(PrimType::None) => "yum_unit".to_string(),
+            ConcreteType::NewType(..) => self.type_names[typ].clone(),
+            ConcreteType::Reference(inner) => format!("{}*", self.c_type(inner)),
+        }
+    }
+
+    fn emit(&mut self, line: &str) {
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+}
+
+
+// Prefixed with the id, so neither overloads nor C keywords can clash
+fn func_name(id: FuncId, func: &MIRFunction) -> String {
+    format!("func_{}_{}", id.0, func.name)
+}
+
+// Positional fields, of namespaced variants, are numbers; nothing else can start with `_`
+fn c_field(name: &str) -> String {
+    match name.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("_{}", name),
+        false => name.to_string(),
+    }
+}
+
+fn cell_name(id: CellId, cell: &Cell) -> String {
+    match &cell.kind {
+        CellKind::Var { name } => format!("c{}_{}", id.0, name),
+        CellKind::Temp => format!("c{}", id.0),
+    }
+}
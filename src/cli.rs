@@ -0,0 +1,258 @@
+use std::path::PathBuf;
+
+use minicompiler::{Stage, Target};
+
+
+pub const USAGE: &str = "\
+Usage: yumc <command> [options] <file.yum>
+
+Commands:
+    build    Compile the program into an executable (or the artifacts in --emit)
+    check    Run every compiler stage without writing any output
+    run      Compile the program and execute it
+    dump     Print internal stage representations to stdout
+
+Options:
+    --emit=<kind>[=<path>],...   Artifacts to produce: tokens, ast, hir, mir, lir, asm, exe
+                                 (asm is C source with --target=c)
+    -o <path>                    Output path (only when a single artifact is emitted)
+    --target=<arm32|x86_64|c>    What to generate code for (default: the host's architecture)
+    --interp[=hir|mir|lir]       With `run`: interpret the HIR (default), MIR or LIR instead of compiling
+    -h, --help                   Print this message
+";
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Build,
+    Check,
+    Run,
+    Dump,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interp {
+    Hir,
+    Mir,
+    Lir,
+}
+
+impl Interp {
+
+    // The stage whose output it runs
+    pub fn stage(&self) -> Stage {
+        match self {
+            Interp::Hir => Stage::Hir,
+            Interp::Mir => Stage::Mir,
+            Interp::Lir => Stage::Lir,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EmitKind {
+    Tokens,
+    Ast,
+    Hir,
+    Mir,
+    Lir,
+    Asm,
+    Exe,
+}
+
+impl EmitKind {
+
+    fn from_name(name: &str) -> Option<EmitKind> {
+        match name {
+            "tokens" => Some(EmitKind::Tokens),
+            "ast" => Some(EmitKind::Ast),
+            "hir" => Some(EmitKind::Hir),
+            "mir" => Some(EmitKind::Mir),
+            "lir" => Some(EmitKind::Lir),
+            "asm" => Some(EmitKind::Asm),
+            "exe" | "link" => Some(EmitKind::Exe),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmitKind::Tokens => "tokens",
+            EmitKind::Ast => "ast",
+            EmitKind::Hir => "hir",
+            EmitKind::Mir => "mir",
+            EmitKind::Lir => "lir",
+            EmitKind::Asm => "asm",
+            EmitKind::Exe => "exe",
+        }
+    }
+
+    // Compiler stage the artifact comes out of; executables are linked from the assembly
+    pub fn stage(&self) -> Stage {
+        match self {
+            EmitKind::Tokens => Stage::Tokens,
+            EmitKind::Ast => Stage::Ast,
+            EmitKind::Hir => Stage::Hir,
+            EmitKind::Mir => Stage::Mir,
+            EmitKind::Lir => Stage::Lir,
+            EmitKind::Asm | EmitKind::Exe => Stage::Asm,
+        }
+    }
+
+    fn default_path(&self, stem: &str, target: Target) -> PathBuf {
+        match self {
+            EmitKind::Asm => PathBuf::from(format!("{}.{}", stem, target.source_ext())),
+            EmitKind::Exe => PathBuf::from(stem),
+            _ => PathBuf::from(format!("{}.{}.txt", stem, self.name())),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Emit {
+    pub kind: EmitKind,
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub command: Command,
+    pub input: PathBuf,
+    pub emits: Vec<Emit>,
+    pub output: Option<PathBuf>,
+    pub interp: Option<Interp>,
+    pub target: Target,
+}
+
+impl Options {
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut args = args.into_iter();
+        let command = match args.next().as_deref() {
+            Some("build") => Command::Build,
+            Some("check") => Command::Check,
+            Some("run") => Command::Run,
+            Some("dump") => Command::Dump,
+            Some(other) => return Err(format!("unknown command `{}`", other)),
+            None => return Err("no command given".to_string()),
+        };
+
+        let mut input: Option<PathBuf> = None;
+        let mut emits: Vec<Emit> = Vec::new();
+        let mut output: Option<PathBuf> = None;
+        let mut interp: Option<Interp> = None;
+        let mut target = Target::host();
+
+        while let Some(arg) = args.next() {
+            if let Some(spec) = arg.strip_prefix("--emit=") {
+                emits.extend(parse_emit_list(spec)?);
+            } else if arg == "--emit" {
+                let spec = args.next().ok_or("`--emit` expects a value")?;
+                emits.extend(parse_emit_list(&spec)?);
+            } else if arg == "-o" {
+                output = Some(PathBuf::from(args.next().ok_or("`-o` expects a path")?));
+            } else if let Some(name) = arg.strip_prefix("--target=") {
+                target = Target::from_name(name).ok_or_else(|| format!("unknown target `{}`", name))?;
+            } else if arg == "--interp" {
+                interp = Some(Interp::Hir);
+            } else if let Some(level) = arg.strip_prefix("--interp=") {
+                interp = Some(match level {
+                    "hir" => Interp::Hir,
+                    "mir" => Interp::Mir,
+                    "lir" => Interp::Lir,
+                    _ => return Err(format!("unknown interpreter `{}`", level)),
+                });
+            } else if arg.starts_with('-') {
+                return Err(format!("unknown option `{}`", arg));
+            } else if input.is_some() {
+                return Err(format!("unexpected argument `{}`", arg));
+            } else {
+                input = Some(PathBuf::from(arg));
+            }
+        }
+        let input = input.ok_or("no input file given")?;
+
+        if emits.is_empty() {
+            emits = default_emits(command);
+        }
+        if command == Command::Check && !emits.is_empty() {
+            return Err("`check` does not write any artifacts".to_string());
+        }
+        if matches!(command, Command::Run | Command::Dump) && emits.iter().any(|e| e.kind == EmitKind::Exe) {
+            return Err("only `build` can emit an executable".to_string());
+        }
+        if interp.is_some() && command != Command::Run {
+            return Err("`--interp` only works with `run`".to_string());
+        }
+        if command == Command::Run && interp.is_none() && !target.runs_on_host() {
+            return Err(format!("can't run {} code on this host", target.name()));
+        }
+        if output.is_some() && emits.len() > 1 {
+            return Err("`-o` can only be used when a single artifact is emitted".to_string());
+        }
+
+        Ok(Options { command, input, emits, output, interp, target })
+    }
+
+    // Where a given artifact goes: an explicit `--emit=kind=path`, then `-o`, then `<stem>.<ext>`
+    pub fn artifact_path(&self, emit: &Emit) -> PathBuf {
+        if let Some(path) = &emit.path {
+            return path.clone();
+        }
+        if let Some(path) = &self.output {
+            return path.clone();
+        }
+        let stem = self.input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("out");
+        emit.kind.default_path(stem, self.target)
+    }
+
+    pub fn last_stage(&self) -> EmitKind {
+        match self.command {
+            Command::Check => EmitKind::Lir,
+            // Far enough for the interpreter, and for whatever else was asked to be emitted
+            Command::Run => {
+                let interpreted = match self.interp {
+                    Some(Interp::Hir) => EmitKind::Hir,
+                    Some(Interp::Mir) => EmitKind::Mir,
+                    Some(Interp::Lir) => EmitKind::Lir,
+                    None => EmitKind::Exe,
+                };
+                self.emits.iter().map(|e| e.kind).fold(interpreted, EmitKind::max)
+            }
+            Command::Build | Command::Dump => self.emits
+                .iter()
+                .map(|e| e.kind)
+                .max()
+                .unwrap_or(EmitKind::Tokens),
+        }
+    }
+}
+
+
+fn default_emits(command: Command) -> Vec<Emit> {
+    let kinds = match command {
+        Command::Build => vec![EmitKind::Exe],
+        Command::Check | Command::Run => vec![],
+        Command::Dump => vec![EmitKind::Hir],
+    };
+    kinds.into_iter().map(|kind| Emit { kind, path: None }).collect()
+}
+
+fn parse_emit_list(spec: &str) -> Result<Vec<Emit>, String> {
+    spec.split(',')
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (name, path) = match item.split_once('=') {
+                Some((name, path)) => (name, Some(PathBuf::from(path))),
+                None => (item, None),
+            };
+            let kind = EmitKind::from_name(name)
+                .ok_or_else(|| format!("unknown emit kind `{}`", name))?;
+            Ok(Emit { kind, path })
+        })
+        .collect()
+}
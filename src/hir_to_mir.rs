@@ -1,19 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::stages::common::*;
 use crate::stages::hir::*;
 use crate::stages::mir::*;
 use crate::shared::typing::*;
+use crate::shared::span::Span;
+use crate::shared::binops::BinaryOperator;
 
 
 pub struct MIRBuilder {
     var_map: HashMap<VarId, CellId>,
-    current_cells: HashMap<CellId, Cell>,
+    current_cells: BTreeMap<CellId, Cell>,
     cell_counter: usize,
     block_counter: usize,
     loop_start_stack: Vec<BlockId>,
     loop_end_stack: Vec<BlockId>,
-    curr_collected_blocks: HashMap<BlockId, MIRBlock>,
+    curr_collected_blocks: BTreeMap<BlockId, MIRBlock>,
     wip_blocks: HashMap<BlockId, Vec<MIRStatement>>,
     processing_stack: Vec<BlockId>,
 }
@@ -24,12 +26,12 @@ impl MIRBuilder {
     pub fn lower_hir(program: HIRProgram) -> MIRProgram {
         let mut builder = MIRBuilder {
             var_map: HashMap::new(),
-            current_cells: HashMap::new(),
+            current_cells: BTreeMap::new(),
             cell_counter: 0,
             block_counter: 1,
             loop_start_stack: Vec::new(),
             loop_end_stack: Vec::new(),
-            curr_collected_blocks: HashMap::new(),
+            curr_collected_blocks: BTreeMap::new(),
             wip_blocks: HashMap::new(),
             processing_stack: Vec::new()
         };
@@ -45,8 +47,8 @@ impl MIRBuilder {
 
     fn lower_function(&mut self, func: HIRFunction) -> MIRFunction {
         
-        self.curr_collected_blocks = HashMap::new();
-        self.current_cells = HashMap::new();
+        self.curr_collected_blocks = BTreeMap::new();
+        self.current_cells = BTreeMap::new();
 
         for (var_id, var) in func.variables.into_iter() {
             let cell_id = self.add_cell(Cell { typ: var.typ, kind: CellKind::Var { name: var.name}});
@@ -57,7 +59,7 @@ impl MIRBuilder {
         
         let arg_cells: Vec<CellId> = func.args
             .iter()
-            .map(|arg_id| self.var_map[&arg_id].clone())
+            .map(|arg_id| self.var_map[arg_id])
             .collect();
         MIRFunction {
             name: func.name,
@@ -75,14 +77,11 @@ impl MIRBuilder {
         let mut curr_top_id = entry_id;
         self.switch_to_block(entry_id);
 
-        let mut has_unterminated = false;
-
         for stmt in stmts {
             self.switch_to_block(curr_top_id);                 // TODO: this is only for safety, sort it out properly
             match self.lower_stmt(stmt) {
                 LoweredStatement::Statements(low_stmts) => {
                     self.push_to_current_block(low_stmts);
-                    has_unterminated = true;
                 }
                 LoweredStatement::Termination(low_stmts, term) => {
                     self.push_to_current_block(low_stmts);
@@ -91,38 +90,37 @@ impl MIRBuilder {
                 }
                 LoweredStatement::TabulaRasa(next_id) => {
                     curr_top_id = next_id;
-                    has_unterminated = false;
                 }
             }
         }
         
-        if has_unterminated {
-            self.switch_to_block(curr_top_id);
-            self.terminate_current_block(tail_termr);
-        }
+        // Even when empty, since something jumps to it
+        self.switch_to_block(curr_top_id);
+        self.terminate_current_block(tail_termr);
         entry_id
     }
 
     
      
     fn lower_stmt(&mut self, stmt: HIRStatement) -> LoweredStatement {
+        let HIRStatement { stmt, span } = stmt;
         match stmt {
-            HIRStatement::Let { var, value } => {
+            HIRStatementKind::Let { var, value } => {
                 let cell_id = &self.var_map[&var];
                 let target = MIRPlace {
-                    typ: self.current_cells[&cell_id].typ.clone(),
+                    typ: self.current_cells[cell_id].typ.clone(),
                     base: MIRPlaceBase::Cell(*cell_id),
                     fieldchain: Vec::new()
                 };
                 let (mir_val, val_stmts) = self.lower_expr(value);
-                LoweredStatement::Statements([val_stmts, vec![MIRStatement::Assign { target, value: mir_val}]].concat())
+                LoweredStatement::Statements([val_stmts, vec![MIRStatementKind::Assign { target, value: mir_val}.at(span)]].concat())
             },
-            HIRStatement::Assign { target, value } => {
+            HIRStatementKind::Assign { target, value } => {
                 let (mir_val, val_stmts) = self.lower_expr(value);
                 let (mir_target, target_stmts) =  self.lower_place(target);
-                LoweredStatement::Statements([val_stmts, target_stmts, vec![MIRStatement::Assign { target: mir_target, value: mir_val}]].concat())
+                LoweredStatement::Statements([val_stmts, target_stmts, vec![MIRStatementKind::Assign { target: mir_target, value: mir_val}.at(span)]].concat())
             }
-            HIRStatement::If { condition, if_body, else_body } => {
+            HIRStatementKind::If { condition, if_body, else_body } => {
                 let (cond_val, cond_stmts) = self.lower_expr(condition);
                 self.push_to_current_block(cond_stmts);
                 let curr_id = self.get_current_wip_id().unwrap();
@@ -138,7 +136,7 @@ impl MIRBuilder {
                 self.terminate_current_block(MIRTerminator::Branch { condition: cond_val, then_: then_id, else_: else_id});
                 LoweredStatement::TabulaRasa(merge_id)
             }
-            HIRStatement::While { condition, body } => {
+            HIRStatementKind::While { condition, body } => {
                 let head_id = self.add_new_block();
                 self.loop_start_stack.push(head_id);
                 self.terminate_current_block(MIRTerminator::Goto(head_id));
@@ -158,13 +156,13 @@ impl MIRBuilder {
                 self.loop_end_stack.pop();
                 LoweredStatement::TabulaRasa(after_id)
             },
-            HIRStatement::Break => {
+            HIRStatementKind::Break => {
                 LoweredStatement::Termination(vec![], MIRTerminator::Goto(*self.loop_end_stack.last().unwrap())) 
             }
-            HIRStatement::Continue => {
+            HIRStatementKind::Continue => {
                 LoweredStatement::Termination(vec![], MIRTerminator::Goto(*self.loop_start_stack.last().unwrap())) 
             }
-            HIRStatement::Return(ret_val) => { 
+            HIRStatementKind::Return(ret_val) => { 
                 let (mir_retval, ret_stmts) = match ret_val {
                     Some(value) => {
                         let (mir_val, val_stmts) = self.lower_expr(value);
@@ -174,19 +172,135 @@ impl MIRBuilder {
                 };
                 LoweredStatement::Termination(ret_stmts, MIRTerminator::Return(mir_retval))
             }
-            HIRStatement::Print(expr) => {
+            HIRStatementKind::Print(expr) => {
                 let (expr_val, expr_stmts) = self.lower_expr(expr);
-                LoweredStatement::Statements([expr_stmts, vec![MIRStatement::Print(expr_val)]].concat())
+                LoweredStatement::Statements([expr_stmts, vec![MIRStatementKind::Print(expr_val).at(span)]].concat())
+            }
+            HIRStatementKind::Call(expr) => {
+                let (_, expr_stmts) = self.lower_expr(expr);
+                LoweredStatement::Statements(expr_stmts)
+            }
+            // Each arm tests its pattern against the scrutinee and falls through to the next one if
+            // that fails. The last arm needs no tests, since the match is exhaustive.
+            HIRStatementKind::Match { scrutinee, arms } => {
+                let (scrut_val, scrut_stmts) = self.lower_expr(scrutinee);
+                let (scrut_place, spill_stmts) = self.spill_to_place(scrut_val, span);
+                self.push_to_current_block([scrut_stmts, spill_stmts].concat());
+                let curr_id = self.get_current_wip_id().unwrap();
+
+                let merge_id = self.add_new_block();
+                let arm_count = arms.len();
+                let arms: Vec<(HIRPattern, BlockId)> = arms
+                    .into_iter()
+                    .map(|arm| (arm.pattern, self.lower_stmt_block(arm.body, MIRTerminator::Goto(merge_id))))
+                    .collect();
+
+                self.switch_to_block(curr_id);
+                if arm_count == 0 {
+                    self.terminate_current_block(MIRTerminator::Goto(merge_id));
+                }
+                for (i, (pattern, body_id)) in arms.into_iter().enumerate() {
+                    let fail_id = (i + 1 < arm_count).then(|| self.add_new_block());
+                    let mut bindings = Vec::new();
+                    self.lower_pattern_tests(pattern, scrut_place.clone(), fail_id, &mut bindings, span);
+                    let binding_stmts = bindings
+                        .into_iter()
+                        .map(|(var, place)| {
+                            let cell = self.var_map[&var];
+                            let target = MIRPlace { typ: place.typ.clone(), base: MIRPlaceBase::Cell(cell), fieldchain: vec![] };
+                            let value = MIRValue { typ: place.typ.clone(), value: MIRValueKind::Place(place) };
+                            MIRStatementKind::Assign { target, value }.at(span)
+                        })
+                        .collect();
+                    self.push_to_current_block(binding_stmts);
+                    self.terminate_current_block(MIRTerminator::Goto(body_id));
+                    if let Some(fail_id) = fail_id {
+                        self.switch_to_block(fail_id);
+                    }
+                }
+                LoweredStatement::TabulaRasa(merge_id)
             }
         }
     }
     
 
+    // Leaves the current block to `fail` wherever the place doesn't match the pattern (if there's
+    // anywhere to go) and collects what the bindings are bound to
+    fn lower_pattern_tests(
+        &mut self,
+        pattern: HIRPattern,
+        place: MIRPlace,
+        fail: Option<BlockId>,
+        bindings: &mut Vec<(VarId, MIRPlace)>,
+        span: Span,
+    ) {
+        let int_typ = ConcreteType::Prim(PrimType::Integer);
+        let bool_typ = ConcreteType::Prim(PrimType::Bool);
+        match pattern.pat {
+            HIRPatternKind::Wildcard => {}
+            HIRPatternKind::Binding(var) => bindings.push((var, place)),
+            HIRPatternKind::IntLiteral(num) => {
+                self.branch_unless_equal(place, MIRValue { typ: int_typ, value: MIRValueKind::IntLiteral(num) }, fail, span);
+            }
+            HIRPatternKind::BoolTrue => {
+                self.branch_unless_equal(place, MIRValue { typ: bool_typ, value: MIRValueKind::BoolTrue }, fail, span);
+            }
+            HIRPatternKind::BoolFalse => {
+                self.branch_unless_equal(place, MIRValue { typ: bool_typ, value: MIRValueKind::BoolFalse }, fail, span);
+            }
+            HIRPatternKind::Variant { variant, payload } => {
+                let mut tag_chain = place.fieldchain.clone();
+                tag_chain.push(Projection::Tag);
+                let tag_place = MIRPlace { typ: int_typ.clone(), base: place.base.clone(), fieldchain: tag_chain };
+                let variant_val = MIRValue { typ: int_typ, value: MIRValueKind::IntLiteral(variant as i32) };
+                self.branch_unless_equal(tag_place, variant_val, fail, span);
+                let payload_place = MIRPlace {
+                    typ: payload.typ.clone(),
+                    base: place.base,
+                    fieldchain: [place.fieldchain, vec![Projection::Payload(variant)]].concat(),
+                };
+                self.lower_pattern_tests(*payload, payload_place, fail, bindings, span);
+            }
+            HIRPatternKind::Struct { fields } => {
+                for (fname, fpattern) in fields {
+                    let field_place = MIRPlace {
+                        typ: fpattern.typ.clone(),
+                        base: place.base.clone(),
+                        fieldchain: [place.fieldchain.clone(), vec![Projection::Field(fname)]].concat(),
+                    };
+                    self.lower_pattern_tests(fpattern, field_place, fail, bindings, span);
+                }
+            }
+        }
+    }
+
+    fn branch_unless_equal(&mut self, place: MIRPlace, value: MIRValue, fail: Option<BlockId>, span: Span) {
+        let Some(fail) = fail else {
+            return;
+        };
+        let bool_typ = ConcreteType::Prim(PrimType::Bool);
+        let test_cell = self.add_cell(Cell { typ: bool_typ.clone(), kind: CellKind::Temp });
+        let test_place = MIRPlace { typ: bool_typ.clone(), base: MIRPlaceBase::Cell(test_cell), fieldchain: vec![] };
+        self.push_to_current_block(vec![MIRStatementKind::BinOp {
+            target: test_place.clone(),
+            op: BinaryOperator::Equals,
+            left: MIRValue { typ: place.typ.clone(), value: MIRValueKind::Place(place) },
+            right: value,
+        }.at(span)]);
+        let pass_id = self.add_new_block();
+        self.terminate_current_block(MIRTerminator::Branch {
+            condition: MIRValue { typ: bool_typ, value: MIRValueKind::Place(test_place) },
+            then_: pass_id,
+            else_: fail,
+        });
+        self.switch_to_block(pass_id);
+    }
+
     fn lower_place(&mut self, hir_place: Place) -> (MIRPlace, Vec<MIRStatement>) {
        match hir_place.place {
             PlaceKind::Variable(var_id) => (MIRPlace { 
                typ: hir_place.typ, 
-               base: MIRPlaceBase::Cell(self.var_map[&var_id].clone()), 
+               base: MIRPlaceBase::Cell(self.var_map[&var_id]), 
                fieldchain: Vec::new(),
             }, vec![]),
             PlaceKind::StructField { of, field } => {
@@ -194,24 +308,33 @@ impl MIRBuilder {
                 (MIRPlace {
                     typ: hir_place.typ,
                     base,
-                    fieldchain: [fieldchain, vec![field]].concat(),
+                    fieldchain: [fieldchain, vec![Projection::Field(field)]].concat(),
+                }, of_stmts)
+            }
+            PlaceKind::VariantPayload { of, variant } => {
+                let (MIRPlace { typ: _, base, fieldchain }, of_stmts) = self.lower_place(*of);
+                (MIRPlace {
+                    typ: hir_place.typ,
+                    base,
+                    fieldchain: [fieldchain, vec![Projection::Payload(variant)]].concat(),
                 }, of_stmts)
             }
             PlaceKind::Deref(reference) => {
+                let span = reference.span;
                 let (ref_val, ref_stmts) = self.lower_expr(reference); 
 
                 let ref_val_cell = self.add_cell(Cell { 
                     typ:  ref_val.typ.clone(),
                     kind: CellKind::Temp 
                 });
-                let ref_assign_stmt = MIRStatement::Assign { 
+                let ref_assign_stmt = MIRStatementKind::Assign { 
                     target: MIRPlace { 
                         typ: ref_val.typ.clone(), 
                         base: MIRPlaceBase::Cell(ref_val_cell), 
                         fieldchain: vec![], 
                     }, 
                     value: ref_val
-                };
+                }.at(span);
                 (MIRPlace {
                     typ: hir_place.typ,
                     base: MIRPlaceBase::Deref(ref_val_cell),
@@ -222,6 +345,7 @@ impl MIRBuilder {
     }
 
     fn lower_expr(&mut self, expr: HIRExpression) -> (MIRValue, Vec<MIRStatement>) {
+        let span = expr.span;
         match expr.expr {
             HIRExpressionKind::IntLiteral(num) => {
                 (MIRValue{
@@ -234,7 +358,7 @@ impl MIRBuilder {
                     typ: expr.typ.clone(),
                     value: MIRValueKind::Place(MIRPlace { 
                         typ: expr.typ, 
-                        base: MIRPlaceBase::Cell(self.var_map[&var_id].clone()),
+                        base: MIRPlaceBase::Cell(self.var_map[&var_id]),
                         fieldchain: Vec::new(),
                     }),
                 };
@@ -249,12 +373,12 @@ impl MIRBuilder {
                     base: MIRPlaceBase::Cell(resc_id), 
                     fieldchain: Vec::new()
                 }; 
-                let bin_stmt = MIRStatement::BinOp { 
+                let bin_stmt = MIRStatementKind::BinOp { 
                     target: target.clone(),
                     op, 
                     left: l_val, 
                     right: r_val 
-                };
+                }.at(span);
                 (MIRValue{typ: expr.typ, value: MIRValueKind::Place(target)}, [l_stmts, r_stmts, vec![bin_stmt]].concat()) 
             },
             HIRExpressionKind::FuncCall { id, args } => {
@@ -268,7 +392,7 @@ impl MIRBuilder {
                     base: MIRPlaceBase::Cell(resc_id), 
                     fieldchain: Vec::new()
                 }; 
-                let call_stmt = MIRStatement::Call { target: target.clone(), func: id, args: arg_vals};
+                let call_stmt = MIRStatementKind::Call { target: target.clone(), func: id, args: arg_vals}.at(span);
                 (MIRValue{typ: expr.typ, value: MIRValueKind::Place(target)}, [arg_stmt_coll.into_iter().flatten().collect(), vec![call_stmt]].concat())
             },
             HIRExpressionKind::BoolTrue => (MIRValue{typ: ConcreteType::Prim(PrimType::Bool) ,value: MIRValueKind::BoolTrue}, Vec::new()),
@@ -276,33 +400,37 @@ impl MIRBuilder {
             HIRExpressionKind::FieldAccess { expr: base_expr, field } => { 
                 let typ = expr.typ.clone();
                 let (expr_val, expr_stmts) = self.lower_expr(*base_expr);
-                let access_val = self.lower_field_access(expr_val, field, typ);
-                (access_val, expr_stmts)
+                let (base_place, spill_stmts) = self.spill_to_place(expr_val, span);
+                let access_val = self.lower_field_access(base_place, field, typ);
+                (access_val, [expr_stmts, spill_stmts].concat())
             },
+            HIRExpressionKind::VariantPayload { expr: base_expr, variant } => {
+                let (expr_val, expr_stmts) = self.lower_expr(*base_expr);
+                let (base_place, spill_stmts) = self.spill_to_place(expr_val, span);
+                let mut fieldchain = base_place.fieldchain;
+                fieldchain.push(Projection::Payload(variant));
+                let payload_place = MIRPlace { typ: expr.typ.clone(), base: base_place.base, fieldchain };
+                (MIRValue { typ: expr.typ, value: MIRValueKind::Place(payload_place) }, [expr_stmts, spill_stmts].concat())
+            }
             HIRExpressionKind::StructLiteral {fields} => {
                 let mut stmts: Vec<MIRStatement> = Vec::new();
-                let mut mir_fields: HashMap<String, MIRValue> = HashMap::new();
+                let mut mir_fields: Vec<(String, MIRValue)> = Vec::new();
                 for (fname, fexpr) in fields {
                     let (f_val, f_stmts) = self.lower_expr(fexpr);
-                    stmts.extend(f_stmts.into_iter());
-                    mir_fields.insert(fname, f_val);
+                    stmts.extend(f_stmts);
+                    mir_fields.push((fname, f_val));
                 }
                 (MIRValue{typ: expr.typ.clone(), value: MIRValueKind::StructLiteral{ typ: expr.typ,fields: mir_fields}}, stmts)
             },
+            HIRExpressionKind::EnumLiteral { variant, payload } => {
+                let (payload_val, payload_stmts) = self.lower_expr(*payload);
+                let value = MIRValueKind::EnumLiteral { typ: expr.typ.clone(), variant, payload: Box::new(payload_val) };
+                (MIRValue{typ: expr.typ, value}, payload_stmts)
+            }
             HIRExpressionKind::Reference(refd) => {
                 let (mir_refd, refd_stmts) = self.lower_expr(*refd);
-                match mir_refd.value {
-                    MIRValueKind::Place(refd_place) => {
-                        (MIRValue{typ: ConcreteType::Reference(Box::new(mir_refd.typ)), value: MIRValueKind::Reference(refd_place)}, refd_stmts)
-                    }
-                    MIRValueKind::Reference(refd_ref) => {
-                        let tempc = self.add_cell(Cell{typ: mir_refd.typ.clone(), kind: CellKind::Temp});
-                        let temp_place = MIRPlace{typ: mir_refd.typ.clone(), base: MIRPlaceBase::Cell(tempc), fieldchain: vec![]};
-                        let assign_stmt = MIRStatement::Assign { target: temp_place.clone(), value: MIRValue { typ: mir_refd.typ.clone(), value: MIRValueKind::Reference(refd_ref)}};
-                        (MIRValue{typ: expr.typ, value: MIRValueKind::Reference(temp_place)}, [refd_stmts, vec![assign_stmt]].concat())
-                    }
-                    _ => {unreachable!();}
-                }
+                let (refd_place, spill_stmts) = self.spill_to_place(mir_refd, span);
+                (MIRValue{typ: expr.typ, value: MIRValueKind::Reference(refd_place)}, [refd_stmts, spill_stmts].concat())
             }
 
             HIRExpressionKind::Dereference(reference) => {
@@ -312,14 +440,14 @@ impl MIRBuilder {
                     typ:  ref_val.typ.clone(),
                     kind: CellKind::Temp
                 });
-                let ref_assign_stmt = MIRStatement::Assign { 
+                let ref_assign_stmt = MIRStatementKind::Assign { 
                     target: MIRPlace { 
                         typ: ref_val.typ.clone(), 
                         base: MIRPlaceBase::Cell(ref_val_cell), 
                         fieldchain: vec![], 
                     }, 
                     value: ref_val
-                };
+                }.at(span);
                 (MIRValue {
                     typ: expr.typ.clone(), 
                     value: MIRValueKind::Place(MIRPlace { 
@@ -333,23 +461,31 @@ impl MIRBuilder {
         }
     }
 
-    fn lower_field_access(&self, of: MIRValue, field: String, typ: ConcreteType) -> MIRValue {
-        let MIRValueKind::Place(place) = of.value else {
-           unreachable!(); 
-        };
+    fn lower_field_access(&self, place: MIRPlace, field: String, typ: ConcreteType) -> MIRValue {
         let mut fieldchain = place.fieldchain;
-        fieldchain.push(field);
+        fieldchain.push(Projection::Field(field));
         let access_place = MIRPlace {
             typ: typ.clone(),
             base: place.base,
             fieldchain 
         };
-        MIRValue{typ: typ, value: MIRValueKind::Place(access_place)}
+        MIRValue{typ, value: MIRValueKind::Place(access_place)}
+    }
+
+    // Values that aren't places yet (literals, references) get stored into a fresh temp
+    fn spill_to_place(&mut self, value: MIRValue, span: Span) -> (MIRPlace, Vec<MIRStatement>) {
+        if let MIRValueKind::Place(place) = value.value {
+            return (place, Vec::new());
+        }
+        let tempc = self.add_cell(Cell{typ: value.typ.clone(), kind: CellKind::Temp});
+        let temp_place = MIRPlace{typ: value.typ.clone(), base: MIRPlaceBase::Cell(tempc), fieldchain: vec![]};
+        let assign_stmt = MIRStatementKind::Assign { target: temp_place.clone(), value }.at(span);
+        (temp_place, vec![assign_stmt])
     }
     
     fn push_to_current_block(&mut self, stmts: Vec<MIRStatement>) {
         let curr_id = self.get_current_wip_id().unwrap();
-        self.wip_blocks.get_mut(&curr_id).unwrap().extend(stmts.into_iter());
+        self.wip_blocks.get_mut(&curr_id).unwrap().extend(stmts);
     }
 
     fn add_new_block(&mut self) -> BlockId {
@@ -383,14 +519,14 @@ impl MIRBuilder {
         
     fn get_new_blockid(&mut self) -> BlockId {
         let block_id = BlockId(self.block_counter); 
-        self.block_counter = self.block_counter + 1;
+        self.block_counter += 1;
         block_id 
     }
 
     fn add_cell(&mut self, cell: Cell) -> CellId {
         let new_id = CellId(self.cell_counter);
-        self.cell_counter = self.cell_counter + 1;
-        self.current_cells.insert(new_id.clone(), cell);
+        self.cell_counter += 1;
+        self.current_cells.insert(new_id, cell);
         new_id
     }
 }
@@ -0,0 +1,301 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::interp::*;
+use crate::shared::span::Span;
+use crate::stages::common::{FuncId, Projection};
+use crate::stages::hir::*;
+
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+struct Frame {
+    vars: BTreeMap<VarId, Pointer>,
+    // One per expression that gets pointed to without being a place, reused each time it's
+    // evaluated like the stack slot compiled code keeps for it
+    temps: BTreeMap<*const HIRExpression, Pointer>,
+}
+
+
+// Walks the HIR directly; this is what programs are supposed to do
+pub struct HIRInterpreter<'a> {
+    program: &'a HIRProgram,
+    memory: Memory,
+    frames: Vec<Frame>,
+    out: &'a mut (dyn Write + Send),
+}
+
+impl<'a> HIRInterpreter<'a> {
+
+    // Runs main and returns its exit code
+    pub fn run(program: &'a HIRProgram, out: &'a mut (dyn Write + Send)) -> RunResult<i32> {
+        with_interp_stack(move || {
+            let mut interp = HIRInterpreter {
+                program,
+                memory: Memory::default(),
+                frames: Vec::new(),
+                out,
+            };
+            let ret = interp.call(program.entry, Vec::new(), Span::default())?;
+            Ok(ret.exit_code())
+        })
+    }
+
+    fn call(&mut self, id: FuncId, args: Vec<Value>, span: Span) -> RunResult<Value> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::new("stack overflow", span));
+        }
+        let program = self.program;
+        let func = &program.functions[&id];
+        let frame = Frame {
+            vars: func.variables
+                .keys()
+                .map(|var| (*var, self.memory.alloc(None)))
+                .collect(),
+            temps: BTreeMap::new(),
+        };
+        for (arg, value) in func.args.iter().zip(args) {
+            self.memory.store(&frame.vars[arg], value, span)?;
+        }
+        self.frames.push(frame);
+        let flow = self.exec_block(&func.body);
+        let frame = self.frames.pop().unwrap();
+        for ptr in frame.vars.values().chain(frame.temps.values()) {
+            self.memory.free(ptr);
+        }
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Unit),
+        }
+    }
+
+    fn exec_block(&mut self, stmts: &[HIRStatement]) -> RunResult<Flow> {
+        for stmt in stmts {
+            let flow = self.exec_stmt(stmt)?;
+            if !matches!(flow, Flow::Normal) {
+                return Ok(flow);
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn exec_stmt(&mut self, stmt: &HIRStatement) -> RunResult<Flow> {
+        let span = stmt.span;
+        match &stmt.stmt {
+            HIRStatementKind::Let { var, value } => {
+                let value = self.eval(value)?;
+                let ptr = self.var_pointer(*var);
+                self.memory.store(&ptr, value, span)?;
+            }
+            HIRStatementKind::Assign { target, value } => {
+                let value = self.eval(value)?;
+                let ptr = self.eval_place(target)?;
+                self.memory.store(&ptr, value, span)?;
+            }
+            HIRStatementKind::If { condition, if_body, else_body } => {
+                if self.eval_condition(condition)? {
+                    return self.exec_block(if_body);
+                } else if let Some(else_body) = else_body {
+                    return self.exec_block(else_body);
+                }
+            }
+            HIRStatementKind::While { condition, body } => {
+                while self.eval_condition(condition)? {
+                    match self.exec_block(body)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                }
+            }
+            HIRStatementKind::Break => return Ok(Flow::Break),
+            HIRStatementKind::Continue => return Ok(Flow::Continue),
+            HIRStatementKind::Return(value) => {
+                let value = match value {
+                    Some(expr) => self.eval(expr)?,
+                    None => Value::Unit,
+                };
+                return Ok(Flow::Return(value));
+            }
+            HIRStatementKind::Print(expr) => {
+                let value = self.eval(expr)?;
+                let text = format_printed(&value, span)?;
+                writeln!(self.out, "{}", text).map_err(|e| RuntimeError::new(e.to_string(), span))?;
+            }
+            HIRStatementKind::Call(expr) => {
+                self.eval(expr)?;
+            }
+            HIRStatementKind::Match { scrutinee, arms } => {
+                let value = self.eval(scrutinee)?;
+                for arm in arms {
+                    let mut bindings = Vec::new();
+                    if match_pattern(&arm.pattern, &value, &mut bindings) {
+                        for (var, bound) in bindings {
+                            let ptr = self.var_pointer(var);
+                            self.memory.store(&ptr, bound, span)?;
+                        }
+                        return self.exec_block(&arm.body);
+                    }
+                }
+                return Err(RuntimeError::new(format!("no arm matches {:?}", value), span));
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn eval_condition(&mut self, condition: &HIRExpression) -> RunResult<bool> {
+        match self.eval(condition)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(RuntimeError::new(format!("condition evaluated to {:?}", other), condition.span)),
+        }
+    }
+
+    fn eval(&mut self, expr: &HIRExpression) -> RunResult<Value> {
+        let span = expr.span;
+        let value = match &expr.expr {
+            HIRExpressionKind::IntLiteral(num) => Value::Int(*num),
+            HIRExpressionKind::BoolTrue => Value::Bool(true),
+            HIRExpressionKind::BoolFalse => Value::Bool(false),
+            HIRExpressionKind::Variable(var) => {
+                let ptr = self.var_pointer(*var);
+                self.memory.load(&ptr, span)?
+            }
+            HIRExpressionKind::BinOp { op, left, right } => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                eval_binop(op, left, right, span)?
+            }
+            HIRExpressionKind::FuncCall { id, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<RunResult<Vec<Value>>>()?;
+                self.call(*id, args, span)?
+            }
+            HIRExpressionKind::FieldAccess { expr, field } => {
+                let Value::Struct(mut fields) = self.eval(expr)? else {
+                    return Err(RuntimeError::new(format!("field `{}` of a non-struct", field), span));
+                };
+                fields.remove(field).unwrap()
+            }
+            HIRExpressionKind::VariantPayload { expr, variant } => match self.eval(expr)? {
+                Value::Enum { variant: actual, payload } if actual == *variant => *payload,
+                other => {
+                    return Err(RuntimeError::new(format!("payload of variant {} read from {:?}", variant, other), span));
+                }
+            },
+            HIRExpressionKind::StructLiteral { fields } => {
+                let mut values = BTreeMap::new();
+                for (fname, fexpr) in fields {
+                    values.insert(fname.clone(), self.eval(fexpr)?);
+                }
+                Value::Struct(values)
+            }
+            HIRExpressionKind::EnumLiteral { variant, payload } => Value::Enum {
+                variant: *variant,
+                payload: Box::new(self.eval(payload)?),
+            },
+            HIRExpressionKind::Reference(refd) => Value::Ref(self.eval_pointee(refd)?),
+            HIRExpressionKind::Dereference(reference) => {
+                let ptr = self.eval_reference(reference)?;
+                self.memory.load(&ptr, span)?
+            }
+        };
+        Ok(value)
+    }
+
+    // Where `&expr` points: the place itself if expr names one, otherwise a fresh temporary
+    fn eval_pointee(&mut self, expr: &HIRExpression) -> RunResult<Pointer> {
+        match &expr.expr {
+            HIRExpressionKind::Variable(var) => Ok(self.var_pointer(*var)),
+            HIRExpressionKind::Dereference(reference) => self.eval_reference(reference),
+            HIRExpressionKind::FieldAccess { expr: base, field } if is_place_expr(base) => {
+                let mut ptr = self.eval_pointee(base)?;
+                ptr.path.push(Projection::Field(field.clone()));
+                Ok(ptr)
+            }
+            HIRExpressionKind::VariantPayload { expr: base, variant } if is_place_expr(base) => {
+                let mut ptr = self.eval_pointee(base)?;
+                ptr.path.push(Projection::Payload(*variant));
+                Ok(ptr)
+            }
+            _ => {
+                let value = self.eval(expr)?;
+                let temps = &mut self.frames.last_mut().unwrap().temps;
+                match temps.get(&std::ptr::from_ref(expr)) {
+                    Some(ptr) => {
+                        self.memory.store(ptr, value, expr.span)?;
+                        Ok(ptr.clone())
+                    }
+                    None => {
+                        let ptr = self.memory.alloc(Some(value));
+                        temps.insert(std::ptr::from_ref(expr), ptr.clone());
+                        Ok(ptr)
+                    }
+                }
+            }
+        }
+    }
+
+    fn eval_reference(&mut self, expr: &HIRExpression) -> RunResult<Pointer> {
+        match self.eval(expr)? {
+            Value::Ref(ptr) => Ok(ptr),
+            other => Err(RuntimeError::new(format!("dereferenced {:?}", other), expr.span)),
+        }
+    }
+
+    fn eval_place(&mut self, place: &Place) -> RunResult<Pointer> {
+        match &place.place {
+            PlaceKind::Variable(var) => Ok(self.var_pointer(*var)),
+            PlaceKind::StructField { of, field } => {
+                let mut ptr = self.eval_place(of)?;
+                ptr.path.push(Projection::Field(field.clone()));
+                Ok(ptr)
+            }
+            PlaceKind::VariantPayload { of, variant } => {
+                let mut ptr = self.eval_place(of)?;
+                ptr.path.push(Projection::Payload(*variant));
+                Ok(ptr)
+            }
+            PlaceKind::Deref(reference) => self.eval_reference(reference),
+        }
+    }
+
+    fn var_pointer(&self, var: VarId) -> Pointer {
+        self.frames.last().unwrap().vars[&var].clone()
+    }
+}
+
+
+fn match_pattern(pattern: &HIRPattern, value: &Value, bindings: &mut Vec<(VarId, Value)>) -> bool {
+    match (&pattern.pat, value) {
+        (HIRPatternKind::Wildcard, _) => true,
+        (HIRPatternKind::Binding(var), _) => {
+            bindings.push((*var, value.clone()));
+            true
+        }
+        (HIRPatternKind::IntLiteral(num), Value::Int(actual)) => num == actual,
+        (HIRPatternKind::BoolTrue, Value::Bool(actual)) => *actual,
+        (HIRPatternKind::BoolFalse, Value::Bool(actual)) => !*actual,
+        (HIRPatternKind::Variant { variant, payload }, Value::Enum { variant: actual, payload: actual_payload }) => {
+            variant == actual && match_pattern(payload, actual_payload, bindings)
+        }
+        (HIRPatternKind::Struct { fields }, Value::Struct(actual)) => {
+            fields.iter().all(|(fname, fpattern)| match_pattern(fpattern, &actual[fname], bindings))
+        }
+        _ => false,
+    }
+}
+
+fn is_place_expr(expr: &HIRExpression) -> bool {
+    match &expr.expr {
+        HIRExpressionKind::Variable(..) | HIRExpressionKind::Dereference(..) => true,
+        HIRExpressionKind::FieldAccess { expr, .. } | HIRExpressionKind::VariantPayload { expr, .. } => is_place_expr(expr),
+        _ => false,
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::interp::*;
+use crate::shared::binops::BinaryOperator;
+use crate::shared::span::Span;
+use crate::stages::common::*;
+use crate::stages::lir::*;
+
+
+// Nothing lives below this, so a zeroed pointer is caught rather than read through
+const NULL_GUARD: usize = 16;
+// At least as strict as any alignment a layout asks for
+const FRAME_ALIGN: usize = 16;
+
+
+// A byte-addressed stack. Only bytes inside a live chunk can be touched, and only once written,
+// so a wrong offset or size shows up as an error instead of quietly reading a neighbour.
+#[derive(Debug)]
+struct StackMemory {
+    bytes: Vec<u8>,
+    init: Vec<bool>,
+    live_chunks: BTreeMap<usize, usize>,        // Start address -> end address
+}
+
+impl StackMemory {
+
+    fn new() -> Self {
+        StackMemory {
+            bytes: vec![0; NULL_GUARD],
+            init: vec![false; NULL_GUARD],
+            live_chunks: BTreeMap::new(),
+        }
+    }
+
+    // Lays the chunks out on top of the stack, each at its alignment, returning each one's address.
+    // The padding between them isn't part of any chunk.
+    fn push_frame(&mut self, chunks: &BTreeMap<ChunkId, Chunk>) -> BTreeMap<ChunkId, usize> {
+        let mut addrs = BTreeMap::new();
+        for (id, chunk) in chunks {
+            let addr = self.bytes.len().next_multiple_of(chunk.align);
+            self.bytes.resize(addr + chunk.size, 0);
+            self.init.resize(addr + chunk.size, false);
+            if chunk.size > 0 {
+                self.live_chunks.insert(addr, addr + chunk.size);
+            }
+            addrs.insert(*id, addr);
+        }
+        // So the next frame starts aligned for anything, and popping it leaves no padding behind
+        let top = self.bytes.len().next_multiple_of(FRAME_ALIGN);
+        self.bytes.resize(top, 0);
+        self.init.resize(top, false);
+        addrs
+    }
+
+    fn pop_frame(&mut self, addrs: &BTreeMap<ChunkId, usize>) {
+        let Some(base) = addrs.values().min() else { return };
+        let dead: Vec<usize> = self.live_chunks.range(base..).map(|(start, _)| *start).collect();
+        for start in dead {
+            self.live_chunks.remove(&start);
+        }
+        self.bytes.truncate(*base);
+        self.init.truncate(*base);
+    }
+
+    fn check_range(&self, addr: usize, size: usize, what: &str) -> RunResult<()> {
+        let in_chunk = self.live_chunks
+            .range(..=addr)
+            .next_back()
+            .is_some_and(|(_, end)| addr + size <= *end);
+        if !in_chunk {
+            return Err(RuntimeError::new(format!("{} of {} bytes at {:#x} is outside any live chunk", what, size, addr), Span::default()));
+        }
+        Ok(())
+    }
+
+    fn read(&self, addr: usize, size: usize) -> RunResult<Vec<u8>> {
+        self.read_raw(addr, size)?.checked()
+    }
+
+    fn read_raw(&self, addr: usize, size: usize) -> RunResult<RawValue> {
+        self.check_range(addr, size, "read")?;
+        Ok(RawValue {
+            addr: Some(addr),
+            bytes: self.bytes[addr..addr + size].to_vec(),
+            init: self.init[addr..addr + size].to_vec(),
+        })
+    }
+
+    fn write_raw(&mut self, addr: usize, value: &RawValue) -> RunResult<()> {
+        let size = value.bytes.len();
+        self.check_range(addr, size, "write")?;
+        self.bytes[addr..addr + size].copy_from_slice(&value.bytes);
+        self.init[addr..addr + size].copy_from_slice(&value.init);
+        Ok(())
+    }
+}
+
+
+// A value as it sat in memory, padding and all. Moves carry it around without looking inside, so
+// uninitialized bytes are only an error once something uses them as a number or a pointer.
+#[derive(Debug)]
+struct RawValue {
+    addr: Option<usize>,            // Where it was read from, for errors
+    bytes: Vec<u8>,
+    init: Vec<bool>,
+}
+
+impl From<Vec<u8>> for RawValue {
+    fn from(bytes: Vec<u8>) -> Self {
+        let init = vec![true; bytes.len()];
+        RawValue { addr: None, bytes, init }
+    }
+}
+
+impl RawValue {
+    fn checked(self) -> RunResult<Vec<u8>> {
+        if self.init.iter().any(|b| !b) {
+            let at = self.addr.map(|addr| format!(" at {:#x}", addr)).unwrap_or_default();
+            return Err(RuntimeError::new(format!("read of {} bytes{} is uninitialized", self.bytes.len(), at), Span::default()));
+        }
+        Ok(self.bytes)
+    }
+}
+
+
+// Where the interpreter is, for error messages; LIR has no spans left
+#[derive(Clone, Copy)]
+struct Location {
+    func: FuncId,
+    block: BlockId,
+}
+
+impl Location {
+    fn error(&self, message: impl Into<String>) -> RuntimeError {
+        RuntimeError::new(format!("in func_{} block_{}: {}", self.func.0, self.block.0, message.into()), Span::default())
+    }
+}
+
+
+// Where a call's chunks ended up, and what its vregs hold. Vregs aren't in memory at all, the
+// same as registers aren't.
+struct Frame {
+    addrs: BTreeMap<ChunkId, usize>,
+    vregs: BTreeMap<VRegId, Vec<u8>>,
+}
+
+
+// Runs the LIR against simulated memory, with chunks and offsets taken at face value
+pub struct LIRInterpreter<'a> {
+    program: &'a LIRProgram,
+    memory: StackMemory,
+    frames: Vec<Frame>,
+    out: &'a mut (dyn Write + Send),
+}
+
+impl<'a> LIRInterpreter<'a> {
+
+    // Runs main and returns its exit code
+    pub fn run(program: &'a LIRProgram, out: &'a mut (dyn Write + Send)) -> RunResult<i32> {
+        with_interp_stack(move || {
+            let mut interp = LIRInterpreter {
+                program,
+                memory: StackMemory::new(),
+                frames: Vec::new(),
+                out,
+            };
+            let main_loc = Location { func: program.entry, block: BlockId(0) };
+            let args = interp.entry_args().map_err(|e| main_loc.error(e.message))?;
+            let ret = interp.call(program.entry, args, main_loc)?;
+            // Only ints and bools make an exit code, same as in compiled code
+            Ok(match ret {
+                Some(raw) if program.exit_code => {
+                    let bytes = raw.checked().map_err(|e| main_loc.error(format!("exit code: {}", e.message)))?;
+                    program.target.bytes_to_int(&bytes) as i32
+                }
+                _ => 0,
+            })
+        })
+    }
+
+    // main takes no args, other than a pointer to its return slot if it needs one. That gets a
+    // frame of its own under main's.
+    fn entry_args(&mut self) -> RunResult<Vec<RawValue>> {
+        let target = &self.program.target;
+        let ret_size = self.program.functions.get(&self.program.entry).map_or(0, |func| func.ret_size);
+        if !target.returns_in_slot(ret_size) {
+            return Ok(Vec::new());
+        }
+        let chunks = BTreeMap::from([(ChunkId(0), Chunk { size: ret_size, align: FRAME_ALIGN })]);
+        let addrs = self.memory.push_frame(&chunks);
+        let slot_pointer = target.int_to_bytes(addrs[&ChunkId(0)] as i64, target.pointer_size);
+        Ok(vec![RawValue::from(slot_pointer)])
+    }
+
+    fn call(&mut self, id: FuncId, args: Vec<RawValue>, loc: Location) -> RunResult<Option<RawValue>> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(loc.error("stack overflow"));
+        }
+        let program = self.program;
+        let Some(func) = program.functions.get(&id) else {
+            return Err(loc.error(format!("call to missing func_{}", id.0)));
+        };
+        if args.len() != func.params.len() {
+            return Err(loc.error(format!("func_{} takes {} args, got {}", id.0, func.params.len(), args.len())));
+        }
+        let addrs = self.memory.push_frame(&func.chunks);
+        self.frames.push(Frame { addrs, vregs: BTreeMap::new() });
+        let callee = Location { func: id, block: func.entry };
+        let ret = func.params
+            .iter()
+            .zip(args)
+            .try_for_each(|(param, arg)| self.store_raw(param, arg, callee))
+            .and_then(|_| self.exec_function(id, func));
+        let frame = self.frames.pop().unwrap();
+        self.memory.pop_frame(&frame.addrs);
+        ret
+    }
+
+    fn exec_function(&mut self, id: FuncId, func: &LIRFunction) -> RunResult<Option<RawValue>> {
+        let mut loc = Location { func: id, block: func.entry };
+        loop {
+            let Some(block) = func.blocks.get(&loc.block) else {
+                return Err(loc.error("jump to a missing block"));
+            };
+            for stmt in &block.statements {
+                self.exec_stmt(stmt, loc)?;
+            }
+            loc.block = match &block.terminator {
+                LIRTerminator::Goto { dest } => *dest,
+                LIRTerminator::Branch { condition, then_block, else_block } => {
+                    match self.eval_int(condition, loc)? {
+                        1 => *then_block,
+                        0 => *else_block,
+                        other => return Err(loc.error(format!("branch on {}", other))),
+                    }
+                }
+                LIRTerminator::Return(value) => {
+                    return match value {
+                        Some(value) => Ok(Some(self.eval_raw(value, loc)?)),
+                        None => Ok(None),
+                    };
+                }
+            };
+        }
+    }
+
+    fn exec_stmt(&mut self, stmt: &LIRStatement, loc: Location) -> RunResult<()> {
+        match stmt {
+            LIRStatement::Store { dest, value } => {
+                let raw = self.eval_raw(value, loc)?;
+                self.store_raw(dest, raw, loc)?;
+            }
+            LIRStatement::BinOp { dest, op, left, right } => {
+                let result = match op {
+                    // Compared bytewise, which is what equality of any value comes down to
+                    BinaryOperator::Equals => (self.eval(left, loc)? == self.eval(right, loc)?) as i32,
+                    _ => {
+                        let l = self.eval_int(left, loc)?;
+                        let r = self.eval_int(right, loc)?;
+                        eval_int_binop(op, l, r, loc)?
+                    }
+                };
+                self.store(dest, &self.program.target.int_to_bytes(result as i64, dest.size), loc)?;
+            }
+            LIRStatement::Call { dest, func, args } => {
+                let args = args.iter().map(|arg| self.eval_raw(arg, loc)).collect::<RunResult<Vec<_>>>()?;
+                // A bare `return` leaves the dest unwritten, so reading it later is caught
+                if let (Some(dest), Some(raw)) = (dest, self.call(*func, args, loc)?) {
+                    self.store_raw(dest, raw, loc)?;
+                }
+            }
+            LIRStatement::Print(value) => {
+                let num = self.eval_int(value, loc)?;
+                writeln!(self.out, "{}", num).map_err(|e| loc.error(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    // What gets moved: a place's bytes as they are, or the value itself
+    fn eval_raw(&mut self, value: &LIRValue, loc: Location) -> RunResult<RawValue> {
+        match &value.value {
+            LIRValueKind::Place(place) => {
+                if place.size != value.size {
+                    return Err(loc.error(format!("{}-byte value read from {}-byte place", value.size, place.size)));
+                }
+                self.load_raw(place, loc)
+            }
+            _ => Ok(RawValue::from(self.eval(value, loc)?)),
+        }
+    }
+
+    fn eval(&mut self, value: &LIRValue, loc: Location) -> RunResult<Vec<u8>> {
+        let target = self.program.target;
+        let bytes = match &value.value {
+            LIRValueKind::Place(place) => {
+                if place.size != value.size {
+                    return Err(loc.error(format!("{}-byte value read from {}-byte place", value.size, place.size)));
+                }
+                self.load(place, loc)?
+            }
+            LIRValueKind::IntLiteral(num) => target.int_to_bytes(*num as i64, value.size),
+            LIRValueKind::BoolTrue => target.int_to_bytes(1, value.size),
+            LIRValueKind::BoolFalse => target.int_to_bytes(0, value.size),
+            LIRValueKind::Reference(place) => {
+                let addr = self.place_addr(place, loc)?;
+                if value.size != target.pointer_size {
+                    return Err(loc.error(format!("{}-byte reference", value.size)));
+                }
+                target.int_to_bytes(addr as i64, target.pointer_size)
+            }
+        };
+        Ok(bytes)
+    }
+
+    // Values the language treats as numbers are 32-bit, whatever room their place has
+    fn eval_int(&mut self, value: &LIRValue, loc: Location) -> RunResult<i32> {
+        let bytes = self.eval(value, loc)?;
+        if bytes.len() > 8 {
+            return Err(loc.error(format!("{}-byte value used as a number", bytes.len())));
+        }
+        Ok(self.program.target.bytes_to_int(&bytes) as i32)
+    }
+
+    fn load(&self, place: &LIRPlace, loc: Location) -> RunResult<Vec<u8>> {
+        self.load_raw(place, loc)?.checked().map_err(|e| loc.error(e.message))
+    }
+
+    fn load_raw(&self, place: &LIRPlace, loc: Location) -> RunResult<RawValue> {
+        if let LIRPlaceKind::VReg(vreg) = place.place {
+            self.check_vreg(vreg, place.size, loc)?;
+            let frame = self.frames.last().unwrap();
+            return frame.vregs
+                .get(&vreg)
+                .map(|bytes| RawValue::from(bytes.clone()))
+                .ok_or_else(|| loc.error(format!("read of {:?} before it was set", vreg)));
+        }
+        let addr = self.place_addr(place, loc)?;
+        self.memory.read_raw(addr, place.size).map_err(|e| loc.error(e.message))
+    }
+
+    fn store(&mut self, place: &LIRPlace, bytes: &[u8], loc: Location) -> RunResult<()> {
+        self.store_raw(place, RawValue::from(bytes.to_vec()), loc)
+    }
+
+    fn store_raw(&mut self, place: &LIRPlace, raw: RawValue, loc: Location) -> RunResult<()> {
+        if raw.bytes.len() != place.size {
+            return Err(loc.error(format!("{}-byte value stored into {}-byte place", raw.bytes.len(), place.size)));
+        }
+        if let LIRPlaceKind::VReg(vreg) = place.place {
+            // Registers only ever get whole scalars, so there's no padding to let through
+            self.check_vreg(vreg, place.size, loc)?;
+            let bytes = raw.checked().map_err(|e| loc.error(e.message))?;
+            self.frames.last_mut().unwrap().vregs.insert(vreg, bytes);
+            return Ok(());
+        }
+        let addr = self.place_addr(place, loc)?;
+        self.memory.write_raw(addr, &raw).map_err(|e| loc.error(e.message))
+    }
+
+    fn check_vreg(&self, vreg: VRegId, size: usize, loc: Location) -> RunResult<()> {
+        match self.program.functions[&loc.func].vregs.get(&vreg) {
+            Some(chunk) if chunk.size == size => Ok(()),
+            Some(chunk) => Err(loc.error(format!("{}-byte access to {}-byte {:?}", size, chunk.size, vreg))),
+            None => Err(loc.error(format!("{:?} isn't in this function", vreg))),
+        }
+    }
+
+    fn place_addr(&self, place: &LIRPlace, loc: Location) -> RunResult<usize> {
+        let frame = self.frames.last().unwrap();
+        let chunk_addr = |id: &ChunkId| {
+            frame.addrs.get(id).copied().ok_or_else(|| loc.error(format!("chunk {:?} isn't in this frame", id)))
+        };
+        let addr = match &place.place {
+            LIRPlaceKind::Local { base, offset } => {
+                let size = self.program.functions[&loc.func].chunks[base].size;
+                if offset + place.size > size {
+                    return Err(loc.error(format!(
+                        "{} bytes at offset {} overrun {}-byte chunk {:?}", place.size, offset, size, base
+                    )));
+                }
+                chunk_addr(base)? + offset
+            }
+            LIRPlaceKind::Deref { pointer, offset } => {
+                let bytes = self.memory
+                    .read(chunk_addr(pointer)?, self.program.target.pointer_size)
+                    .map_err(|e| loc.error(format!("loading pointer: {}", e.message)))?;
+                let target = self.program.target.bytes_to_int(&bytes) as usize;
+                if target < NULL_GUARD {
+                    return Err(loc.error(format!("dereferenced null pointer {:#x}", target)));
+                }
+                target + offset
+            }
+            LIRPlaceKind::VReg(vreg) => return Err(loc.error(format!("{:?} has no address", vreg))),
+        };
+        Ok(addr)
+    }
+}
+
+
+fn eval_int_binop(op: &BinaryOperator, l: i32, r: i32, loc: Location) -> RunResult<i32> {
+    let result = match op {
+        BinaryOperator::Add => l.wrapping_add(r),
+        BinaryOperator::Sub => l.wrapping_sub(r),
+        BinaryOperator::Mul => l.wrapping_mul(r),
+        BinaryOperator::Modulo if r == 0 => return Err(loc.error("modulo by zero")),
+        BinaryOperator::Modulo => l.wrapping_rem(r),
+        BinaryOperator::Less => (l < r) as i32,
+        BinaryOperator::Equals => (l == r) as i32,
+    };
+    Ok(result)
+}
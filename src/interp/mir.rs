@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::interp::*;
+use crate::shared::span::Span;
+use crate::stages::common::*;
+use crate::stages::mir::*;
+
+
+// Executes the CFG block by block, so any difference from the HIR interpreter is a lowering bug
+pub struct MIRInterpreter<'a> {
+    program: &'a MIRProgram,
+    memory: Memory,
+    frames: Vec<BTreeMap<CellId, Pointer>>,
+    out: &'a mut (dyn Write + Send),
+}
+
+impl<'a> MIRInterpreter<'a> {
+
+    // Runs main and returns its exit code
+    pub fn run(program: &'a MIRProgram, out: &'a mut (dyn Write + Send)) -> RunResult<i32> {
+        with_interp_stack(move || {
+            let mut interp = MIRInterpreter {
+                program,
+                memory: Memory::default(),
+                frames: Vec::new(),
+                out,
+            };
+            let ret = interp.call(program.entry, Vec::new(), Span::default())?;
+            Ok(ret.exit_code())
+        })
+    }
+
+    fn call(&mut self, id: FuncId, args: Vec<Value>, span: Span) -> RunResult<Value> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::new("stack overflow", span));
+        }
+        let program = self.program;
+        let func = &program.functions[&id];
+        let cells: BTreeMap<CellId, Pointer> = func.cells
+            .keys()
+            .map(|cell| (*cell, self.memory.alloc(None)))
+            .collect();
+        for (arg, value) in func.args.iter().zip(args) {
+            self.memory.store(&cells[arg], value, span)?;
+        }
+        self.frames.push(cells);
+        let ret = self.exec_function(func);
+        for ptr in self.frames.pop().unwrap().values() {
+            self.memory.free(ptr);
+        }
+        ret
+    }
+
+    fn exec_function(&mut self, func: &MIRFunction) -> RunResult<Value> {
+        let mut curr = func.entry;
+        // Terminators have no spans, so runtime errors in them point at the last statement run
+        let mut last_span = Span::default();
+        loop {
+            let Some(block) = func.blocks.get(&curr) else {
+                return Err(RuntimeError::new(format!("jump to missing block {:?}", curr), last_span));
+            };
+            for stmt in &block.statements {
+                last_span = stmt.span;
+                self.exec_stmt(stmt)?;
+            }
+            curr = match &block.terminator {
+                MIRTerminator::Goto(next) => *next,
+                MIRTerminator::Branch { condition, then_, else_ } => {
+                    match self.eval(condition, last_span)? {
+                        Value::Bool(true) => *then_,
+                        Value::Bool(false) => *else_,
+                        other => {
+                            return Err(RuntimeError::new(format!("branch on {:?}", other), last_span));
+                        }
+                    }
+                }
+                MIRTerminator::Return(value) => {
+                    return match value {
+                        Some(value) => self.eval(value, last_span),
+                        None => Ok(Value::Unit),
+                    };
+                }
+            };
+        }
+    }
+
+    fn exec_stmt(&mut self, stmt: &MIRStatement) -> RunResult<()> {
+        let span = stmt.span;
+        match &stmt.stmt {
+            MIRStatementKind::Assign { target, value } => {
+                let value = self.eval(value, span)?;
+                let ptr = self.place_pointer(target, span)?;
+                self.memory.store(&ptr, value, span)?;
+            }
+            MIRStatementKind::BinOp { target, op, left, right } => {
+                let left = self.eval(left, span)?;
+                let right = self.eval(right, span)?;
+                let value = eval_binop(op, left, right, span)?;
+                let ptr = self.place_pointer(target, span)?;
+                self.memory.store(&ptr, value, span)?;
+            }
+            MIRStatementKind::Call { target, func, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg, span))
+                    .collect::<RunResult<Vec<Value>>>()?;
+                let value = self.call(*func, args, span)?;
+                let ptr = self.place_pointer(target, span)?;
+                self.memory.store(&ptr, value, span)?;
+            }
+            MIRStatementKind::Print(value) => {
+                let value = self.eval(value, span)?;
+                let text = format_printed(&value, span)?;
+                writeln!(self.out, "{}", text).map_err(|e| RuntimeError::new(e.to_string(), span))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn eval(&mut self, value: &MIRValue, span: Span) -> RunResult<Value> {
+        let value = match &value.value {
+            MIRValueKind::Place(place) => {
+                let ptr = self.place_pointer(place, span)?;
+                self.memory.load(&ptr, span)?
+            }
+            MIRValueKind::IntLiteral(num) => Value::Int(*num),
+            MIRValueKind::BoolTrue => Value::Bool(true),
+            MIRValueKind::BoolFalse => Value::Bool(false),
+            MIRValueKind::StructLiteral { fields, .. } => {
+                let mut values = BTreeMap::new();
+                for (fname, fvalue) in fields {
+                    values.insert(fname.clone(), self.eval(fvalue, span)?);
+                }
+                Value::Struct(values)
+            }
+            MIRValueKind::EnumLiteral { variant, payload, .. } => Value::Enum {
+                variant: *variant,
+                payload: Box::new(self.eval(payload, span)?),
+            },
+            MIRValueKind::Reference(place) => Value::Ref(self.place_pointer(place, span)?),
+        };
+        Ok(value)
+    }
+
+    fn place_pointer(&self, place: &MIRPlace, span: Span) -> RunResult<Pointer> {
+        let mut ptr = match place.base {
+            MIRPlaceBase::Cell(cell) => self.cell_pointer(cell),
+            MIRPlaceBase::Deref(cell) => match self.memory.load(&self.cell_pointer(cell), span)? {
+                Value::Ref(ptr) => ptr,
+                other => return Err(RuntimeError::new(format!("dereferenced {:?}", other), span)),
+            },
+        };
+        ptr.path.extend(place.fieldchain.iter().cloned());
+        Ok(ptr)
+    }
+
+    fn cell_pointer(&self, cell: CellId) -> Pointer {
+        self.frames.last().unwrap()[&cell].clone()
+    }
+}
@@ -0,0 +1,215 @@
+pub mod hir;
+pub mod mir;
+pub mod lir;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::thread;
+
+use crate::shared::binops::BinaryOperator;
+use crate::shared::span::Span;
+use crate::stages::common::Projection;
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Bool(bool),
+    Unit,
+    Struct(BTreeMap<String, Value>),
+    Enum {
+        variant: usize,
+        payload: Box<Value>,
+    },
+    Ref(Pointer),
+}
+
+impl Value {
+
+    // What a program's main returns becomes its exit code, same as in compiled code
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Value::Int(num) => *num,
+            Value::Bool(b) => *b as i32,
+            _ => 0,
+        }
+    }
+}
+
+
+// A memory slot, plus the chain of fields and payloads leading into it. The slot's generation
+// tells it apart from whatever reuses the slot after it's freed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pointer {
+    pub slot: usize,
+    pub generation: usize,
+    pub path: Vec<Projection>,
+}
+
+
+#[derive(Clone, Debug)]
+pub struct RuntimeError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        RuntimeError { message: message.into(), span }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Errors from the LIR have no source position to give
+        if self.span == Span::default() {
+            return write!(f, "runtime error: {}", self.message);
+        }
+        write!(f, "runtime error at {}:{}: {}", self.span.line, self.span.col, self.message)
+    }
+}
+
+pub type RunResult<T> = Result<T, RuntimeError>;
+
+
+// Calls in the interpreted program recurse in the interpreter, so give it a stack that can fit
+// MAX_CALL_DEPTH of them rather than whatever the calling thread happens to have
+pub const MAX_CALL_DEPTH: usize = 10_000;
+const INTERP_STACK_SIZE: usize = 1 << 30;
+
+pub fn with_interp_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .name("interp".to_string())
+            .stack_size(INTERP_STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("couldn't spawn the interpreter thread")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+
+// Every variable and every temporary something points to gets a slot. Slots are freed when
+// their function returns and then reused, but dangling references to them are still caught
+// instead of reading whatever lives there now.
+#[derive(Debug, Default)]
+pub struct Memory {
+    slots: Vec<Slot>,
+    free_slots: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct Slot {
+    value: Option<Value>,
+    generation: usize,
+}
+
+impl Memory {
+
+    pub fn alloc(&mut self, value: Option<Value>) -> Pointer {
+        let slot = match self.free_slots.pop() {
+            Some(slot) => {
+                self.slots[slot].value = value;
+                slot
+            }
+            None => {
+                self.slots.push(Slot { value, generation: 0 });
+                self.slots.len() - 1
+            }
+        };
+        Pointer { slot, generation: self.slots[slot].generation, path: Vec::new() }
+    }
+
+    pub fn free(&mut self, ptr: &Pointer) {
+        let slot = &mut self.slots[ptr.slot];
+        slot.value = None;
+        slot.generation += 1;
+        self.free_slots.push(ptr.slot);
+    }
+
+    pub fn load(&self, ptr: &Pointer, span: Span) -> RunResult<Value> {
+        let slot = &self.slots[ptr.slot];
+        let mut value = slot.value
+            .as_ref()
+            .filter(|_| slot.generation == ptr.generation)
+            .ok_or_else(|| RuntimeError::new("read through a dangling or uninitialized reference", span))?;
+        for proj in &ptr.path {
+            value = match (proj, value) {
+                (Projection::Field(field), Value::Struct(fields)) => &fields[field],
+                (Projection::Payload(i), Value::Enum { variant, payload }) if i == variant => payload,
+                (Projection::Tag, Value::Enum { variant, .. }) => return Ok(Value::Int(*variant as i32)),
+                _ => return Err(projection_error(proj, value, span)),
+            };
+        }
+        Ok(value.clone())
+    }
+
+    pub fn store(&mut self, ptr: &Pointer, new_value: Value, span: Span) -> RunResult<()> {
+        let dangling = || RuntimeError::new("write through a dangling or uninitialized reference", span);
+        let slot = &mut self.slots[ptr.slot];
+        if slot.generation != ptr.generation {
+            return Err(dangling());
+        }
+        if ptr.path.is_empty() {
+            slot.value = Some(new_value);
+            return Ok(());
+        }
+        let mut value = slot.value.as_mut().ok_or_else(dangling)?;
+        for proj in &ptr.path {
+            // Checked up front, since the borrow checker won't give the value back from a failed guard
+            let fits = match (proj, &*value) {
+                (Projection::Field(_), Value::Struct(_)) => true,
+                (Projection::Payload(i), Value::Enum { variant, .. }) => i == variant,
+                _ => false,
+            };
+            if !fits {
+                return Err(projection_error(proj, value, span));
+            }
+            value = match (proj, value) {
+                (Projection::Field(field), Value::Struct(fields)) => fields.get_mut(field).unwrap(),
+                (_, Value::Enum { payload, .. }) => payload,
+                _ => unreachable!(),
+            };
+        }
+        *value = new_value;
+        Ok(())
+    }
+}
+
+fn projection_error(proj: &Projection, value: &Value, span: Span) -> RuntimeError {
+    let message = match proj {
+        Projection::Field(field) => format!("field `{}` of {:?}", field, value),
+        Projection::Tag => format!("tag of {:?}", value),
+        Projection::Payload(i) => format!("payload of variant {} read from {:?}", i, value),
+    };
+    RuntimeError::new(message, span)
+}
+
+
+pub fn eval_binop(op: &BinaryOperator, left: Value, right: Value, span: Span) -> RunResult<Value> {
+    let value = match (op, left, right) {
+        (BinaryOperator::Add, Value::Int(l), Value::Int(r)) => Value::Int(l.wrapping_add(r)),
+        (BinaryOperator::Sub, Value::Int(l), Value::Int(r)) => Value::Int(l.wrapping_sub(r)),
+        (BinaryOperator::Mul, Value::Int(l), Value::Int(r)) => Value::Int(l.wrapping_mul(r)),
+        (BinaryOperator::Modulo, Value::Int(_), Value::Int(0)) => {
+            return Err(RuntimeError::new("modulo by zero", span));
+        }
+        (BinaryOperator::Modulo, Value::Int(l), Value::Int(r)) => Value::Int(l.wrapping_rem(r)),
+        (BinaryOperator::Less, Value::Int(l), Value::Int(r)) => Value::Bool(l < r),
+        (BinaryOperator::Equals, l, r) => Value::Bool(l == r),
+        (op, l, r) => {
+            return Err(RuntimeError::new(format!("can't apply {:?} to {:?} and {:?}", op, l, r), span));
+        }
+    };
+    Ok(value)
+}
+
+// Prints like the compiled code's printf("%d\n")
+pub fn format_printed(value: &Value, span: Span) -> RunResult<String> {
+    match value {
+        Value::Int(num) => Ok(num.to_string()),
+        Value::Bool(b) => Ok((*b as i32).to_string()),
+        _ => Err(RuntimeError::new(format!("can't print {:?}", value), span)),
+    }
+}
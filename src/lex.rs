@@ -1,22 +1,35 @@
 use crate::shared::tokens::*;
+use crate::shared::span::Span;
+use crate::shared::diagnostics::*;
+use std::iter::Peekable;
+use std::str::Chars;
 
 
-pub fn lex(program: &str) -> Vec<Token> {
+pub fn lex(program: &str) -> CompileResult<Vec<Token>> {
     let mut tokens: Vec<Token> = Vec::new();
-    let mut chars = program.chars().peekable();
-    
-    while let Some(&c) = chars.peek() {
+    let mut chars = Cursor::new(program);
+
+    while let Some(c) = chars.peek() {
 
         if c.is_whitespace() {
             chars.next();
             continue;
         }
 
+        let start = chars.position();
+
+        // Line comments
+        if c == '/' && chars.peek_second() == Some('/') {
+            while chars.peek().is_some_and(|ch| ch != '\n') {
+                chars.next();
+            }
+            continue;
+        }
 
         // Alphanumeric strings: keywords or identifiers
         if c.is_ascii_alphabetic() {
             let mut word = String::new();
-            while let Some(&ch) = chars.peek() {
+            while let Some(ch) = chars.peek() {
                 if ch.is_ascii_alphanumeric() || ch == '_' {
                     word.push(ch);
                     chars.next();
@@ -26,29 +39,31 @@ pub fn lex(program: &str) -> Vec<Token> {
             }
 
             let token = match word.as_str() {
-                "if" => Token::If,
-                "else" => Token::Else,
-                "while" => Token::While,
-                "break" => Token::Break,
-                "continue" => Token::Continue,
-                "fun" => Token::Function,
-                "return" => Token::Return,
-                "print" => Token::Print,
-                "let" => Token::Let,
-                "int" => Token::Int,
-                "bool" => Token::Bool,
-                "true" => Token::True,
-                "false" => Token::False,
-                "struct" => Token::Struct,
-                _ => Token::Identifier(word),
-            };  
-            tokens.push(token); 
-        } 
-        
+                "if" => TokenKind::If,
+                "else" => TokenKind::Else,
+                "while" => TokenKind::While,
+                "break" => TokenKind::Break,
+                "continue" => TokenKind::Continue,
+                "fun" => TokenKind::Function,
+                "return" => TokenKind::Return,
+                "print" => TokenKind::Print,
+                "let" => TokenKind::Let,
+                "int" => TokenKind::Int,
+                "bool" => TokenKind::Bool,
+                "true" => TokenKind::True,
+                "false" => TokenKind::False,
+                "struct" => TokenKind::Struct,
+                "enum" => TokenKind::Enum,
+                "match" => TokenKind::Match,
+                _ => TokenKind::Identifier(word),
+            };
+            tokens.push(chars.token(token, start));
+        }
+
         // Numbers
         else if c.is_ascii_digit() {
             let mut num_str = String::new();
-            while let Some(&ch) = chars.peek() {
+            while let Some(ch) = chars.peek() {
                 if ch.is_ascii_digit() {
                     num_str.push(ch);
                     chars.next();
@@ -56,57 +71,135 @@ pub fn lex(program: &str) -> Vec<Token> {
                     break;
                 }
             }
-            let value = num_str.parse::<i32>().unwrap();
-            tokens.push(Token::IntLiteral(value));
+            let value = num_str.parse::<i32>().map_err(|_| Diagnostic::error(
+                ErrorCode::IntLiteralOverflow,
+                "integer literal is too large",
+                chars.span_from(start),
+            ).with_note(format!("the largest `int` is {}", i32::MAX)))?;
+            tokens.push(chars.token(TokenKind::IntLiteral(value), start));
         }
 
         else if c == '=' {
             chars.next();
-            if chars.peek() == Some(&'=') {
+            if chars.peek() == Some('=') {
+                chars.next();
+                tokens.push(chars.token(TokenKind::Equals, start));
+            } else if chars.peek() == Some('>') {
+                chars.next();
+                tokens.push(chars.token(TokenKind::FatArrow, start));
+            } else {
+                tokens.push(chars.token(TokenKind::Assign, start));
+            }
+        }
+
+        else if c == ':' {
+            chars.next();
+            if chars.peek() == Some(':') {
                 chars.next();
-                tokens.push(Token::Equals);
+                tokens.push(chars.token(TokenKind::DoubleColon, start));
             } else {
-                tokens.push(Token::Assign);
+                tokens.push(chars.token(TokenKind::Colon, start));
             }
         }
 
         else if c == '-' {
             chars.next();
-            if chars.peek() == Some(&'>') {
+            if chars.peek() == Some('>') {
                 chars.next();
-                tokens.push(Token::RightArrow);
+                tokens.push(chars.token(TokenKind::RightArrow, start));
             } else {
-                tokens.push(Token::Minus);
+                tokens.push(chars.token(TokenKind::Minus, start));
             }
         }
 
         else {
             // Processing single character stuff
             let token = match c {
-                '+' => Token::Plus,
-                '*' => Token::Multiply,
-                ';' => Token::Semicolon,
-                '(' => Token::LeftParen,
-                ')' => Token::RightParen,
-                '{' => Token::LeftBrace,
-                '}' => Token::RightBrace,
-                '<' => Token::Less,
-                '%' => Token::Modulo,
-                ',' => Token::Comma,
-                ':' => Token::Colon,
-                '.' => Token::Dot,
-                '&' => Token::Ref,
-                '?' => Token::Deref,
-                '[' => Token::LeftSqBracket,
-                ']' => Token::RightSqBracket,
-                _ => {panic!("Unexpected character: {}",c)},
+                '+' => TokenKind::Plus,
+                '*' => TokenKind::Multiply,
+                ';' => TokenKind::Semicolon,
+                '(' => TokenKind::LeftParen,
+                ')' => TokenKind::RightParen,
+                '{' => TokenKind::LeftBrace,
+                '}' => TokenKind::RightBrace,
+                '<' => TokenKind::Less,
+                '%' => TokenKind::Modulo,
+                ',' => TokenKind::Comma,
+                '.' => TokenKind::Dot,
+                '&' => TokenKind::Ref,
+                '_' => TokenKind::Underscore,
+                '?' => TokenKind::Deref,
+                '[' => TokenKind::LeftSqBracket,
+                ']' => TokenKind::RightSqBracket,
+                _ => {
+                    chars.next();
+                    return Err(Diagnostic::error(
+                        ErrorCode::UnexpectedCharacter,
+                        format!("unexpected character `{}`", c),
+                        chars.span_from(start),
+                    ).into());
+                },
             };
             chars.next();
-            tokens.push(token);
+            tokens.push(chars.token(token, start));
         }
-        
-    } 
-    tokens
+
+    }
+    tokens.push(chars.token(TokenKind::Eof, chars.position()));
+    Ok(tokens)
+}
+
+
+// Character iterator that keeps track of the byte offset, line and column it's at
+struct Cursor<'a> {
+    source: &'a str,
+    chars: Peekable<Chars<'a>>,
+    offset: usize,
+    line: usize,
+    col: usize,
 }
 
+impl<'a> Cursor<'a> {
+
+    fn new(program: &'a str) -> Self {
+        Cursor {
+            source: program,
+            chars: program.chars().peekable(),
+            offset: 0,
+            line: 1,
+            col: 1,
+        }
+    }
 
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.source[self.offset..].chars().nth(1)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn position(&self) -> Span {
+        Span::new(self.offset, self.offset, self.line, self.col)
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        Span::new(start.start, self.offset, start.line, start.col)
+    }
+
+    fn token(&self, kind: TokenKind, start: Span) -> Token {
+        Token { kind, span: self.span_from(start) }
+    }
+}
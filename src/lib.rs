@@ -0,0 +1,24 @@
+pub mod shared;
+pub mod stages;
+
+pub mod lex;
+pub mod parse;
+pub mod make_hir;
+pub mod match_check;
+pub mod hir_to_mir;
+pub mod mir_to_lir;
+pub mod lir_codegen;
+pub mod c_codegen;
+pub mod interp;
+
+mod pipeline;
+
+pub use lex::lex;
+pub use parse::Parser;
+pub use make_hir::HIRBuilder;
+pub use hir_to_mir::MIRBuilder;
+pub use mir_to_lir::LIRBuilder;
+pub use lir_codegen::{Backend, LIRCompiler};
+pub use c_codegen::CCompiler;
+pub use shared::diagnostics::{CompileResult, Diagnostic};
+pub use pipeline::*;
@@ -0,0 +1,317 @@
+use crate::stages::common::*;
+use crate::stages::lir::*;
+use crate::shared::binops::BinaryOperator;
+use crate::lir_codegen::*;
+use crate::lir_codegen::regalloc::VRegHome;
+
+
+// Callee-saved; r11 is fp
+const ALLOCATABLE: [&str; 7] = ["r4", "r5", "r6", "r7", "r8", "r9", "r10"];
+
+// Copies of up to this many words get unrolled
+const COPY_UNROLL: usize = 16;
+
+const ARG_REGS: [&str; 4] = ["r0", "r1", "r2", "r3"];
+
+
+// ARM32, GNU syntax, calling functions the AAPCS way so C can call them and be called. r0 is the
+// accumulator, r1 holds binop left operands. Values up to a word are returned in r0; anything
+// bigger goes through a return slot, which the caller passes a pointer to as the first arg.
+pub struct Arm32Backend;
+
+impl Backend for Arm32Backend {
+
+    fn allocatable_regs(&self) -> &'static [&'static str] {
+        &ALLOCATABLE
+    }
+
+    fn preamble(&self, asm: &mut Asm) {
+        asm.emit(".global main");
+        asm.emit(".extern printf");
+        asm.emit(".align 8");
+        asm.emit(".data");
+        asm.emit(r#"fmt: .asciz "%d\n""#);
+        asm.emit(".text");
+    }
+
+    fn runtime_entry(&self, asm: &mut Asm, entry: FuncId, ret_slot: Option<usize>, exit_code: bool) {
+        asm.emit("main:");
+        asm.emit("    push {fp, lr}");
+        asm.emit("    mov fp, sp");
+        if let Some(size) = ret_slot {
+            asm.emit(&format!("    sub sp, sp, #{}", size.next_multiple_of(8)));
+            asm.emit("    mov r0, sp");
+        }
+        asm.emit(&format!("    bl {}", func_label(entry)));
+        if !exit_code {
+            asm.emit("    mov r0, #0");
+        }
+        asm.emit("    mov sp, fp");
+        asm.emit("    pop {fp, lr}");
+        asm.emit("    bx lr");
+    }
+
+    fn prologue(&self, asm: &mut Asm, frame: &StackFrame, params: &[LIRPlace]) {
+        asm.emit("    push {fp, lr}");
+        asm.emit("    mov fp, sp");
+        asm.emit(&format!("    sub sp, sp, #{}", frame.size));
+        for (reg, chunk) in &frame.saved_regs {
+            asm.emit(&format!("    str {}, [fp, #-{}]", ALLOCATABLE[*reg], frame.offsets[chunk]));
+        }
+        let (locations, _) = arg_locations(params.iter().map(|param| param.size));
+        // Registers first, before the copies use them
+        for (param, loc) in params.iter().zip(&locations) {
+            let param_offset = frame.param_offset(param);
+            for (i, reg) in loc.regs.clone().enumerate() {
+                asm.emit(&format!("    str {}, [fp, #-{}]", ARG_REGS[reg], param_offset - 4 * i));
+            }
+        }
+        // The caller's stack args start right above the saved fp and lr
+        for (param, loc) in params.iter().zip(&locations) {
+            if loc.stack_words() > 0 {
+                let param_offset = frame.param_offset(param);
+                asm.emit(&format!("    add r1, fp, #{}", 8 + loc.stack_offset));
+                asm.emit(&format!("    sub r0, fp, #{}", param_offset - 4 * loc.regs.len()));
+                self.copy(asm, 4 * loc.stack_words());
+            }
+        }
+    }
+
+    fn epilogue(&self, asm: &mut Asm, frame: &StackFrame) {
+        for (reg, chunk) in &frame.saved_regs {
+            asm.emit(&format!("    ldr {}, [fp, #-{}]", ALLOCATABLE[*reg], frame.offsets[chunk]));
+        }
+        asm.emit(&format!("    add sp, sp, #{}", frame.size));
+        asm.emit("    pop {fp, lr}");
+        asm.emit("    bx lr");
+    }
+
+    fn load_operand(&self, asm: &mut Asm, operand: &LIRValue, frame: &StackFrame) {
+        match &operand.value {
+            LIRValueKind::Place(place) => {
+                match place.place {
+                    LIRPlaceKind::Local { base, offset } => {
+                        let place_offset = frame.local_offset(base, offset);
+                        load_from(asm, "fp", -(place_offset as isize), place.size);
+                    }
+                    LIRPlaceKind::Deref { pointer, offset } => {
+                        let pointer_st_offs = frame.offsets[&pointer];
+                        asm.emit(&format!("    ldr r0, [fp, #-{}]", pointer_st_offs));
+                        load_from(asm, "r0", offset as isize, place.size);
+                    }
+                    LIRPlaceKind::VReg(vreg) => {
+                        match frame.vregs[&vreg] {
+                            VRegHome::Reg(reg) => asm.emit(&format!("    mov r0, {}", ALLOCATABLE[reg])),
+                            VRegHome::Spill(chunk) => load_from(asm, "fp", -(frame.offsets[&chunk] as isize), place.size),
+                        }
+                    }
+                }
+            }
+            LIRValueKind::IntLiteral(num) => {
+                asm.emit(&format!("    ldr r0, ={}", num));
+            }
+            LIRValueKind::BoolTrue => {
+                asm.emit("    ldr r0, =1");
+            }
+            LIRValueKind::BoolFalse => {
+                asm.emit("    ldr r0, =0");
+            }
+            LIRValueKind::Reference(refd) => {
+                match refd.place {
+                    LIRPlaceKind::Local { base, offset } => {
+                        let place_offset = frame.local_offset(base, offset);
+                        asm.emit(&format!("    sub r0, fp, #{}", place_offset));
+                    }
+                    LIRPlaceKind::Deref { pointer, offset } => {
+                        let pointer_st_offs = frame.offsets[&pointer];
+                        asm.emit(&format!("    ldr r0, [fp, #-{}]", pointer_st_offs));
+                        asm.emit(&format!("    add r0, r0, #{}", offset));
+                    }
+                    LIRPlaceKind::VReg(vreg) => panic!("{:?} has no address", vreg),
+                }
+            }
+        }
+    }
+
+    fn store_place(&self, asm: &mut Asm, place: &LIRPlace, frame: &StackFrame) {
+        match place.place {
+            LIRPlaceKind::Local { base, offset } => {
+                let place_offset = frame.local_offset(base, offset);
+                store_to(asm, "r0", "fp", -(place_offset as isize), place.size);
+            }
+            LIRPlaceKind::Deref { pointer, offset } => {
+                let pointer_st_offs = frame.offsets[&pointer];
+                asm.emit(&format!("    ldr r1, [fp, #-{}]", pointer_st_offs));
+                store_to(asm, "r0", "r1", offset as isize, place.size);
+            }
+            LIRPlaceKind::VReg(vreg) => {
+                match frame.vregs[&vreg] {
+                    VRegHome::Reg(reg) => asm.emit(&format!("    mov {}, r0", ALLOCATABLE[reg])),
+                    VRegHome::Spill(chunk) => store_to(asm, "r0", "fp", -(frame.offsets[&chunk] as isize), place.size),
+                }
+            }
+        }
+    }
+
+    fn save_left(&self, asm: &mut Asm) {
+        asm.emit("    mov r1, r0");
+    }
+
+    fn binop(&self, asm: &mut Asm, op: BinaryOperator) {
+        match op {
+            BinaryOperator::Add => {
+                asm.emit("    add r0, r1, r0");
+            }
+            BinaryOperator::Sub => {
+                asm.emit("    sub r0, r1, r0");
+            }
+            BinaryOperator::Mul => {
+                asm.emit("    mul r0, r1, r0");
+            }
+            BinaryOperator::Equals => {
+                asm.emit("    cmp r1, r0");
+                asm.emit("    mov r0, #0");
+                asm.emit("    moveq r0, #1");
+            }
+            BinaryOperator::Less=> {
+                asm.emit("    cmp r1, r0");
+                asm.emit("    mov r0, #0");
+                asm.emit("    movlt r0, #1");
+            }
+            BinaryOperator::Modulo => {
+                asm.emit("    sdiv r2, r1, r0");
+                asm.emit("    mul r2, r0, r2");
+                asm.emit("    sub r0, r1, r2");
+            }
+        }
+    }
+
+    // r1 and r0 hold the source and destination addresses, r2 carries each piece over
+    fn copy(&self, asm: &mut Asm, size: usize) {
+        let words = size / 4;
+        let mut offset = 0;
+        if words > COPY_UNROLL {
+            // Walks both pointers forward, so the tail starts at offset 0 again
+            let label = asm.fresh_label("copy");
+            asm.emit(&format!("    ldr r3, ={}", words));
+            asm.emit(&format!("{}:", label));
+            asm.emit("    ldr r2, [r1], #4");
+            asm.emit("    str r2, [r0], #4");
+            asm.emit("    subs r3, r3, #1");
+            asm.emit(&format!("    bne {}", label));
+        } else {
+            for _ in 0..words {
+                asm.emit(&format!("    ldr r2, [r1, #{}]", offset));
+                asm.emit(&format!("    str r2, [r0, #{}]", offset));
+                offset += 4;
+            }
+        }
+        for _ in 0..size % 4 {
+            asm.emit(&format!("    ldrb r2, [r1, #{}]", offset));
+            asm.emit(&format!("    strb r2, [r0, #{}]", offset));
+            offset += 1;
+        }
+    }
+
+    fn call(&self, asm: &mut Asm, dest: Option<&LIRPlace>, func: FuncId, args: &[LIRValue], frame: &StackFrame) {
+        let (locations, stack_size) = arg_locations(args.iter().map(|arg| arg.size));
+        if stack_size > 0 {
+            asm.emit(&format!("    sub sp, sp, #{}", stack_size));
+        }
+        // The stack parts first, while r0-r3 are free to use
+        for (arg, loc) in args.iter().zip(&locations) {
+            match (loc.words, loc.stack_words()) {
+                (_, 0) => {}
+                (1, _) => {
+                    self.load_operand(asm, arg, frame);
+                    asm.emit(&format!("    str r0, [sp, #{}]", loc.stack_offset));
+                }
+                (_, stack_words) => {
+                    self.load_operand(asm, &address_of(arg, 4), frame);
+                    asm.emit(&format!("    add r1, r0, #{}", 4 * loc.regs.len()));
+                    asm.emit(&format!("    add r0, sp, #{}", loc.stack_offset));
+                    self.copy(asm, 4 * stack_words);
+                }
+            }
+        }
+        // Then the registers, last first, since getting an arg loaded goes through r0
+        for (arg, loc) in args.iter().zip(&locations).rev() {
+            if loc.regs.is_empty() {
+                continue;
+            }
+            if loc.words == 1 {
+                self.load_operand(asm, arg, frame);
+                if loc.regs.start != 0 {
+                    asm.emit(&format!("    mov {}, r0", ARG_REGS[loc.regs.start]));
+                }
+            } else {
+                self.load_operand(asm, &address_of(arg, 4), frame);
+                for (i, reg) in loc.regs.clone().enumerate().rev() {
+                    asm.emit(&format!("    ldr {}, [r0, #{}]", ARG_REGS[reg], 4 * i));
+                }
+            }
+        }
+        asm.emit(&format!("    bl {}", func_label(func)));
+        if stack_size > 0 {
+            asm.emit(&format!("    add sp, sp, #{}", stack_size));
+        }
+        if let Some(dest) = dest {
+            self.store_place(asm, dest, frame);
+        }
+    }
+
+    fn print(&self, asm: &mut Asm) {
+        asm.emit("    mov r1, r0");
+        asm.emit("    ldr r0, =fmt");
+        asm.emit("    bl printf");
+    }
+
+    fn jump(&self, asm: &mut Asm, label: &str) {
+        asm.emit(&format!("    b {}", label));
+    }
+
+    fn branch_if_true(&self, asm: &mut Asm, label: &str) {
+        asm.emit("    cmp r0, #1");
+        asm.emit(&format!("    beq {}", label));
+    }
+}
+
+
+// Loads and stores pick their width from the size. Nothing wider than a word gets here, that's
+// copied memory to memory.
+fn load_from(asm: &mut Asm, base: &str, disp: isize, size: usize) {
+    match size {
+        0 => asm.emit("    mov r0, #0"),
+        1 => asm.emit(&format!("    ldrb r0, [{}, #{}]", base, disp)),
+        _ => asm.emit(&format!("    ldr r0, [{}, #{}]", base, disp)),
+    }
+}
+
+fn store_to(asm: &mut Asm, reg: &str, base: &str, disp: isize, size: usize) {
+    match size {
+        0 => {}
+        1 => asm.emit(&format!("    strb {}, [{}, #{}]", reg, base, disp)),
+        _ => asm.emit(&format!("    str {}, [{}, #{}]", reg, base, disp)),
+    }
+}
+
+// AAPCS: args take r0-r3 a word at a time, then the stack. One that doesn't fit in the registers
+// left is split between them and the stack, unless something has gone on the stack already.
+// Nothing is aligned to more than a word here, so the doubleword rules never come up.
+fn arg_locations(sizes: impl Iterator<Item = usize>) -> (Vec<ArgLocation>, usize) {
+    let mut locations = Vec::new();
+    let mut next_reg = 0;
+    let mut stack_size = 0;
+    for size in sizes {
+        let words = size.div_ceil(4);
+        let in_regs = if next_reg + words <= ARG_REGS.len() || stack_size == 0 {
+            words.min(ARG_REGS.len() - next_reg)
+        } else {
+            0
+        };
+        locations.push(ArgLocation { regs: next_reg..next_reg + in_regs, stack_offset: stack_size, words });
+        next_reg = if in_regs < words { ARG_REGS.len() } else { next_reg + in_regs };
+        stack_size += 4 * (words - in_regs);
+    }
+    (locations, stack_size.next_multiple_of(8))
+}
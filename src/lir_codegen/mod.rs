@@ -0,0 +1,299 @@
+pub mod arm;
+pub mod x86;
+pub mod regalloc;
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+use crate::stages::common::*;
+use crate::stages::lir::*;
+use crate::shared::binops::BinaryOperator;
+use crate::shared::target::TargetInfo;
+use crate::lir_codegen::regalloc::*;
+
+
+// Chunks sit below fp. A chunk's offset is how far below fp it starts, and its contents go up
+// from there the same as in memory anywhere else, so a field at offset k is k bytes above that.
+// Backends keep fp aligned to the target's stack alignment, so a chunk is aligned as long as
+// its offset is a multiple of its alignment.
+// Spilled vregs get chunks of their own, as does every allocated register, to keep the caller's
+// value in while the function uses it.
+pub struct StackFrame {
+    pub size: usize,
+    pub offsets: HashMap<ChunkId, usize>,
+    pub sizes: HashMap<ChunkId, usize>,
+    pub vregs: HashMap<VRegId, VRegHome>,
+    pub saved_regs: Vec<(usize, ChunkId)>,      // Register index -> where it's saved
+}
+
+impl StackFrame {
+    pub fn make(mut chunks: BTreeMap<ChunkId, Chunk>, allocation: Allocation, target: &TargetInfo) -> StackFrame {
+        let Allocation { homes, spills, used_regs } = allocation;
+        chunks.extend(spills);
+        let mut saved_regs = Vec::new();
+        for reg in used_regs {
+            let id = ChunkId(chunks.keys().next_back().map_or(0, |id| id.0 + 1));
+            chunks.insert(id, Chunk { size: target.word_size, align: target.word_size });
+            saved_regs.push((reg, id));
+        }
+
+        let mut offsets: HashMap<ChunkId, usize> = HashMap::new();
+        let mut sizes: HashMap<ChunkId, usize> = HashMap::new();
+        let mut curr_offset = 0;
+        for (id, chunk) in chunks {
+            curr_offset = (curr_offset + chunk.size).next_multiple_of(chunk.align);
+            offsets.insert(id, curr_offset);
+            sizes.insert(id, chunk.size);
+        }
+        StackFrame {
+            // Keeps sp aligned whenever the frame is all that's on top of fp
+            size: curr_offset.next_multiple_of(target.stack_align),
+            offsets,
+            sizes,
+            vregs: homes,
+            saved_regs,
+        }
+    }
+
+    // How far below fp a local place is
+    pub fn local_offset(&self, base: ChunkId, offset: usize) -> usize {
+        self.offsets[&base] - offset
+    }
+
+    // Params are always in chunks of their own
+    pub fn param_offset(&self, param: &LIRPlace) -> usize {
+        match param.place {
+            LIRPlaceKind::Local { base, offset } => self.local_offset(base, offset),
+            _ => panic!("param {:?} isn't a local", param),
+        }
+    }
+}
+
+
+#[derive(Default)]
+pub struct Asm {
+    output: String,
+    label_counter: usize,
+}
+
+impl Asm {
+    pub fn emit(&mut self, line: &str) {
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    // For backends' own local jumps
+    pub fn fresh_label(&mut self, prefix: &str) -> String {
+        self.label_counter += 1;
+        format!("{}_{}", prefix, self.label_counter)
+    }
+}
+
+
+pub fn func_label(id: FuncId) -> String {
+    format!("func_{}", id.0)
+}
+
+pub fn block_label(id: BlockId) -> String {
+    format!("block_{}", id.0)
+}
+
+pub fn ret_label(id: FuncId) -> String {
+    format!("ret_{}", id.0)
+}
+
+
+// Where an arg goes under the platform's calling convention: its first words in a run of the
+// arg registers, the rest on the stack, starting stack_offset bytes above sp at the call
+#[derive(Clone, Debug)]
+pub struct ArgLocation {
+    pub regs: Range<usize>,
+    pub stack_offset: usize,
+    pub words: usize,
+}
+
+impl ArgLocation {
+    pub fn stack_words(&self) -> usize {
+        self.words - self.regs.len()
+    }
+}
+
+// Args and results that don't fit one register are in memory, so they're moved from there
+pub fn address_of(value: &LIRValue, pointer_size: usize) -> LIRValue {
+    let LIRValueKind::Place(place) = &value.value else {
+        panic!("{}-byte value that isn't in memory", value.size);
+    };
+    LIRValue { size: pointer_size, value: LIRValueKind::Reference(place.clone()) }
+}
+
+
+// What a target has to provide. Code is generated around an accumulator register: operands get
+// loaded into it, results come out of it, and binops take their left operand from a second one.
+pub trait Backend {
+
+    // Registers vregs can go in. Calls mustn't clobber them, so they're callee-saved ones: the
+    // prologue saves the ones a function uses into frame.saved_regs, and the epilogue restores them.
+    fn allocatable_regs(&self) -> &'static [&'static str];
+
+    // Directives and data (the printf format) before any function
+    fn preamble(&self, asm: &mut Asm);
+
+    // The C `main`, which calls the entry function and hands back its result as the exit code, or
+    // 0 if it isn't one. Comes last. ret_slot is the size of the return slot the entry function
+    // needs passed, if it does.
+    fn runtime_entry(&self, asm: &mut Asm, entry: FuncId, ret_slot: Option<usize>, exit_code: bool);
+
+    // After the function's label: sets up the frame, then moves each arg from wherever the calling
+    // convention put it into its param
+    fn prologue(&self, asm: &mut Asm, frame: &StackFrame, params: &[LIRPlace]);
+
+    // After the function's return label, with the return value in the return registers. Values
+    // that go through a return slot have been stored there already.
+    fn epilogue(&self, asm: &mut Asm, frame: &StackFrame);
+
+    fn load_operand(&self, asm: &mut Asm, operand: &LIRValue, frame: &StackFrame);
+
+    fn store_place(&self, asm: &mut Asm, place: &LIRPlace, frame: &StackFrame);
+
+    // Moves the accumulator to where binop expects its left operand
+    fn save_left(&self, asm: &mut Asm);
+
+    fn binop(&self, asm: &mut Asm, op: BinaryOperator);
+
+    // Copies size bytes from the address in binop's left register to the one in the accumulator.
+    // For values that don't fit the accumulator.
+    fn copy(&self, asm: &mut Asm, size: usize);
+
+    // The whole call sequence: getting the args where the calling convention wants them, and the
+    // result into dest if there is one
+    fn call(&self, asm: &mut Asm, dest: Option<&LIRPlace>, func: FuncId, args: &[LIRValue], frame: &StackFrame);
+
+    // Prints the accumulator
+    fn print(&self, asm: &mut Asm);
+
+    fn jump(&self, asm: &mut Asm, label: &str);
+
+    // Jumps if the accumulator holds true, falls through otherwise
+    fn branch_if_true(&self, asm: &mut Asm, label: &str);
+
+    // Gets the return value, if any, into the return registers
+    fn return_value(&self, asm: &mut Asm, value: Option<&LIRValue>, frame: &StackFrame) {
+        if let Some(value) = value {
+            self.load_operand(asm, value, frame);
+        }
+    }
+}
+
+
+// Walks the LIR and has the backend emit each piece
+pub struct LIRCompiler<'a> {
+    backend: &'a dyn Backend,
+    target: TargetInfo,
+    asm: Asm,
+}
+
+impl<'a> LIRCompiler<'a> {
+
+    pub fn compile(lir_program: LIRProgram, backend: &'a dyn Backend) -> String {
+        let mut comp = LIRCompiler {
+            backend,
+            target: lir_program.target,
+            asm: Asm::default(),
+        };
+        comp.compile_program(lir_program)
+    }
+
+
+    fn compile_program(&mut self, program: LIRProgram) -> String {
+        self.backend.preamble(&mut self.asm);
+        let entry_ret_size = program.functions[&program.entry].ret_size;
+        let ret_slot = self.target.returns_in_slot(entry_ret_size).then_some(entry_ret_size);
+        for (f_id, func) in program.functions.into_iter() {
+            self.compile_function(f_id, func);
+        }
+        self.backend.runtime_entry(&mut self.asm, program.entry, ret_slot, program.exit_code);
+        std::mem::take(&mut self.asm.output)
+    }
+
+
+    fn compile_function(&mut self, func_id: FuncId, lir_func: LIRFunction) {
+        let allocation = RegAllocator::allocate(&lir_func, self.backend.allocatable_regs().len());
+        let LIRFunction { blocks, entry, chunks, vregs: _, params, ret_size: _ } = lir_func;
+        let frame = StackFrame::make(chunks, allocation, &self.target);
+
+        self.asm.emit(&format!("{}:", func_label(func_id)));
+        self.backend.prologue(&mut self.asm, &frame, &params);
+        self.backend.jump(&mut self.asm, &block_label(entry));
+
+        for (id, block) in blocks.into_iter() {
+            self.compile_block(id, block, &frame, func_id);
+        }
+
+        self.asm.emit(&format!("{}:", ret_label(func_id)));
+        self.backend.epilogue(&mut self.asm, &frame);
+    }
+
+
+    fn compile_block(&mut self, id: BlockId, block: LIRBlock, frame: &StackFrame, func: FuncId) {
+        self.asm.emit(&format!("{}:", block_label(id)));
+        let LIRBlock {statements, terminator} = block;
+        for stmt in statements {
+            self.compile_stmt(stmt, frame);
+        }
+        self.compile_terminator(terminator, frame, func);
+    }
+
+    fn compile_stmt(&mut self, stmt: LIRStatement, frame: &StackFrame) {
+        let backend = self.backend;
+        let asm = &mut self.asm;
+        match stmt {
+            LIRStatement::Store { dest, value } if !self.target.fits_register(value.size) => {
+                let LIRValueKind::Place(src) = value.value else {
+                    panic!("{}-byte value that isn't in memory", value.size);
+                };
+                let pointer_size = self.target.pointer_size;
+                backend.load_operand(asm, &LIRValue { size: pointer_size, value: LIRValueKind::Reference(src) }, frame);
+                backend.save_left(asm);
+                backend.load_operand(asm, &LIRValue { size: pointer_size, value: LIRValueKind::Reference(dest) }, frame);
+                backend.copy(asm, value.size);
+            }
+            LIRStatement::Store { dest, value } => {
+                backend.load_operand(asm, &value, frame);
+                backend.store_place(asm, &dest, frame);
+            }
+            LIRStatement::BinOp { dest, op, left, right } => {
+                backend.load_operand(asm, &left, frame);
+                backend.save_left(asm);
+                backend.load_operand(asm, &right, frame);
+                backend.binop(asm, op);
+                backend.store_place(asm, &dest, frame);
+            }
+            LIRStatement::Call { dest, func, args } => {
+                backend.call(asm, dest.as_ref(), func, &args, frame);
+            }
+            LIRStatement::Print(operand) => {
+                backend.load_operand(asm, &operand, frame);
+                backend.print(asm);
+            }
+        }
+    }
+
+    fn compile_terminator(&mut self, term: LIRTerminator, frame: &StackFrame, func_id: FuncId) {
+        let backend = self.backend;
+        let asm = &mut self.asm;
+        match term {
+            LIRTerminator::Goto{dest} => {
+                backend.jump(asm, &block_label(dest));
+            }
+            LIRTerminator::Branch { condition, then_block, else_block } => {
+                backend.load_operand(asm, &condition, frame);
+                backend.branch_if_true(asm, &block_label(then_block));
+                backend.jump(asm, &block_label(else_block));
+            }
+            LIRTerminator::Return(operand_opt) => {
+                backend.return_value(asm, operand_opt.as_ref(), frame);
+                backend.jump(asm, &ret_label(func_id));
+            }
+        }
+    }
+}
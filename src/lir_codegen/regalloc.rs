@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::stages::common::*;
+use crate::stages::lir::*;
+
+
+// Where a vreg ended up
+#[derive(Clone, Copy, Debug)]
+pub enum VRegHome {
+    Reg(usize),             // Index into the backend's allocatable registers
+    Spill(ChunkId),
+}
+
+pub struct Allocation {
+    pub homes: HashMap<VRegId, VRegHome>,
+    pub spills: BTreeMap<ChunkId, Chunk>,
+    pub used_regs: BTreeSet<usize>,
+}
+
+
+type LiveSets = BTreeMap<BlockId, BTreeSet<VRegId>>;
+
+// The positions a vreg is live over, inclusive. Every statement and terminator gets two, going
+// through the blocks in the order they're emitted: an even one where it reads its operands, and
+// the odd one after where it writes its destination.
+#[derive(Clone, Copy, Debug)]
+struct Interval {
+    vreg: VRegId,
+    start: usize,
+    end: usize,
+}
+
+
+// Linear scan over live intervals (Poletto & Sarkar). An interval is the whole stretch between
+// the first and last position its vreg is live at, holes and all, so one register per vreg.
+pub struct RegAllocator<'a> {
+    func: &'a LIRFunction,
+    num_regs: usize,
+    next_chunk: usize,
+}
+
+impl<'a> RegAllocator<'a> {
+
+    pub fn allocate(func: &'a LIRFunction, num_regs: usize) -> Allocation {
+        let allocator = RegAllocator {
+            func,
+            num_regs,
+            // Spill chunks only have to be distinct within the frame
+            next_chunk: func.chunks.keys().next_back().map_or(0, |id| id.0 + 1),
+        };
+        allocator.run()
+    }
+
+
+    fn run(mut self) -> Allocation {
+        let mut intervals = self.intervals();
+        intervals.sort_by_key(|iv| (iv.start, iv.vreg));
+
+        let mut homes: HashMap<VRegId, VRegHome> = HashMap::new();
+        let mut spills: BTreeMap<ChunkId, Chunk> = BTreeMap::new();
+        let mut used_regs: BTreeSet<usize> = BTreeSet::new();
+        let mut free: BTreeSet<usize> = (0..self.num_regs).collect();
+        let mut active: Vec<(Interval, usize)> = Vec::new();
+
+        for iv in intervals {
+            // So a statement's destination can share a register with an operand it last reads
+            active.retain(|(other, reg)| {
+                let expired = other.end < iv.start;
+                if expired {
+                    free.insert(*reg);
+                }
+                !expired
+            });
+
+            if let Some(reg) = free.pop_first() {
+                homes.insert(iv.vreg, VRegHome::Reg(reg));
+                used_regs.insert(reg);
+                active.push((iv, reg));
+                continue;
+            }
+
+            // Out of registers: whichever of these lives longest goes to the stack
+            let (victim_idx, (victim, reg)) = active
+                .iter()
+                .copied()
+                .enumerate()
+                .max_by_key(|(_, (other, _))| (other.end, other.vreg))
+                .unwrap();
+            let spilled = if victim.end > iv.end {
+                active.remove(victim_idx);
+                homes.insert(iv.vreg, VRegHome::Reg(reg));
+                active.push((iv, reg));
+                victim.vreg
+            } else {
+                iv.vreg
+            };
+            let chunk_id = ChunkId(self.next_chunk);
+            self.next_chunk += 1;
+            spills.insert(chunk_id, self.func.vregs[&spilled].clone());
+            homes.insert(spilled, VRegHome::Spill(chunk_id));
+        }
+
+        Allocation { homes, spills, used_regs }
+    }
+
+    fn intervals(&self) -> Vec<Interval> {
+        let (live_in, live_out) = self.liveness();
+        let mut ranges: BTreeMap<VRegId, (usize, usize)> = BTreeMap::new();
+        let mut extend = |vreg: VRegId, pos: usize| {
+            let range = ranges.entry(vreg).or_insert((pos, pos));
+            range.0 = range.0.min(pos);
+            range.1 = range.1.max(pos);
+        };
+
+        let mut pos = 0;
+        for (id, block) in &self.func.blocks {
+            let start = 2 * pos;
+            for vreg in &live_in[id] {
+                extend(*vreg, start);
+            }
+            for stmt in &block.statements {
+                let (uses, defs) = stmt_uses_defs(stmt);
+                for vreg in uses {
+                    extend(vreg, 2 * pos);
+                }
+                for vreg in defs {
+                    extend(vreg, 2 * pos + 1);
+                }
+                pos += 1;
+            }
+            for vreg in term_uses(&block.terminator) {
+                extend(vreg, 2 * pos);
+            }
+            for vreg in &live_out[id] {
+                extend(*vreg, 2 * pos);
+            }
+            pos += 1;
+        }
+
+        ranges
+            .into_iter()
+            .map(|(vreg, (start, end))| Interval { vreg, start, end })
+            .collect()
+    }
+
+    // Backwards dataflow to a fixpoint: a vreg is live into a block if the block reads it before
+    // writing it, or if it's live out and the block doesn't write it at all
+    fn liveness(&self) -> (LiveSets, LiveSets) {
+        let mut gen_kill: BTreeMap<BlockId, (BTreeSet<VRegId>, BTreeSet<VRegId>)> = BTreeMap::new();
+        for (id, block) in &self.func.blocks {
+            let mut gen_set = BTreeSet::new();
+            let mut kill = BTreeSet::new();
+            for stmt in &block.statements {
+                let (uses, defs) = stmt_uses_defs(stmt);
+                gen_set.extend(uses.into_iter().filter(|vreg| !kill.contains(vreg)));
+                kill.extend(defs);
+            }
+            gen_set.extend(term_uses(&block.terminator).into_iter().filter(|vreg| !kill.contains(vreg)));
+            gen_kill.insert(*id, (gen_set, kill));
+        }
+
+        let mut live_in: LiveSets =
+            self.func.blocks.keys().map(|id| (*id, BTreeSet::new())).collect();
+        let mut live_out = live_in.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (id, block) in self.func.blocks.iter().rev() {
+                let out: BTreeSet<VRegId> = successors(&block.terminator)
+                    .iter()
+                    .filter_map(|succ| live_in.get(succ))
+                    .flatten()
+                    .copied()
+                    .collect();
+                let (gen_set, kill) = &gen_kill[id];
+                let inn: BTreeSet<VRegId> = gen_set
+                    .iter()
+                    .chain(out.difference(kill))
+                    .copied()
+                    .collect();
+                if inn != live_in[id] || out != live_out[id] {
+                    changed = true;
+                    live_in.insert(*id, inn);
+                    live_out.insert(*id, out);
+                }
+            }
+        }
+        (live_in, live_out)
+    }
+}
+
+
+fn stmt_uses_defs(stmt: &LIRStatement) -> (Vec<VRegId>, Vec<VRegId>) {
+    match stmt {
+        LIRStatement::Store { dest, value } => (value_vreg(value).into_iter().collect(), place_vreg(dest).into_iter().collect()),
+        LIRStatement::BinOp { dest, left, right, .. } => {
+            let uses = value_vreg(left).into_iter().chain(value_vreg(right)).collect();
+            (uses, place_vreg(dest).into_iter().collect())
+        }
+        LIRStatement::Call { dest, args, .. } => (args.iter().filter_map(value_vreg).collect(), dest.iter().filter_map(place_vreg).collect()),
+        LIRStatement::Print(value) => (value_vreg(value).into_iter().collect(), Vec::new()),
+    }
+}
+
+fn term_uses(term: &LIRTerminator) -> Vec<VRegId> {
+    match term {
+        LIRTerminator::Branch { condition, .. } => value_vreg(condition).into_iter().collect(),
+        LIRTerminator::Return(Some(value)) => value_vreg(value).into_iter().collect(),
+        LIRTerminator::Goto { .. } | LIRTerminator::Return(None) => Vec::new(),
+    }
+}
+
+fn successors(term: &LIRTerminator) -> Vec<BlockId> {
+    match term {
+        LIRTerminator::Goto { dest } => vec![*dest],
+        LIRTerminator::Branch { then_block, else_block, .. } => vec![*then_block, *else_block],
+        LIRTerminator::Return(_) => Vec::new(),
+    }
+}
+
+// Vregs are never referenced, so only a plain read of one uses it
+fn value_vreg(value: &LIRValue) -> Option<VRegId> {
+    match &value.value {
+        LIRValueKind::Place(place) => place_vreg(place),
+        _ => None,
+    }
+}
+
+fn place_vreg(place: &LIRPlace) -> Option<VRegId> {
+    match place.place {
+        LIRPlaceKind::VReg(vreg) => Some(vreg),
+        _ => None,
+    }
+}
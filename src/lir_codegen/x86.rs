@@ -0,0 +1,358 @@
+use crate::stages::common::*;
+use crate::stages::lir::*;
+use crate::shared::binops::BinaryOperator;
+use crate::lir_codegen::*;
+use crate::lir_codegen::regalloc::VRegHome;
+
+
+// A register by the names of its 8, 4 and 1-byte parts
+type Reg = [&'static str; 3];
+
+const ACC: Reg = ["%rax", "%eax", "%al"];
+
+// Callee-saved; rbp is the frame pointer
+const ALLOCATABLE: [&str; 5] = ["%rbx", "%r12", "%r13", "%r14", "%r15"];
+
+// Copies of up to this many words get unrolled
+const COPY_UNROLL: usize = 8;
+
+const ARG_REGS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+
+// x86-64 System V, AT&T syntax, calling functions the way C does. Same frame layout as the ARM
+// backend; rax is the accumulator and rcx holds binop left operands. Values up to 16 bytes are
+// returned in rax and rdx, bigger ones through a return slot passed as a hidden first arg.
+pub struct X86Backend;
+
+impl Backend for X86Backend {
+
+    fn allocatable_regs(&self) -> &'static [&'static str] {
+        &ALLOCATABLE
+    }
+
+    fn preamble(&self, asm: &mut Asm) {
+        asm.emit(".globl main");
+        asm.emit(".data");
+        asm.emit(r#"fmt: .asciz "%d\n""#);
+        asm.emit(".text");
+    }
+
+    fn runtime_entry(&self, asm: &mut Asm, entry: FuncId, ret_slot: Option<usize>, exit_code: bool) {
+        asm.emit("main:");
+        asm.emit("    push %rbp");
+        asm.emit("    mov %rsp, %rbp");
+        if let Some(size) = ret_slot {
+            asm.emit(&format!("    sub ${}, %rsp", size.next_multiple_of(16)));
+            asm.emit(&format!("    mov %rsp, {}", ARG_REGS[0]));
+        }
+        asm.emit(&format!("    call {}", func_label(entry)));
+        if !exit_code {
+            asm.emit("    xor %eax, %eax");
+        }
+        asm.emit("    leave");
+        asm.emit("    ret");
+
+        // Otherwise the linker assumes we need an executable stack
+        asm.emit(r#".section .note.GNU-stack,"",@progbits"#);
+    }
+
+    fn prologue(&self, asm: &mut Asm, frame: &StackFrame, params: &[LIRPlace]) {
+        asm.emit("    push %rbp");
+        asm.emit("    mov %rsp, %rbp");
+        asm.emit(&format!("    sub ${}, %rsp", frame.size));
+        for (reg, chunk) in &frame.saved_regs {
+            asm.emit(&format!("    mov {}, -{}(%rbp)", ALLOCATABLE[*reg], frame.offsets[chunk]));
+        }
+        let (locations, _) = arg_locations(params.iter().map(|param| param.size));
+        // Registers first, since copying uses rcx, rdx and r8
+        for (param, loc) in params.iter().zip(&locations) {
+            let param_offset = frame.param_offset(param);
+            for (i, reg) in loc.regs.clone().enumerate() {
+                asm.emit(&format!("    mov {}, -{}(%rbp)", ARG_REGS[reg], param_offset - 8 * i));
+            }
+        }
+        // The caller's stack args start right above the saved rbp and the return address
+        for (param, loc) in params.iter().zip(&locations) {
+            if loc.stack_words() > 0 {
+                asm.emit(&format!("    lea {}(%rbp), %rcx", 16 + loc.stack_offset));
+                asm.emit(&format!("    lea -{}(%rbp), %rax", frame.param_offset(param)));
+                self.copy(asm, 8 * loc.stack_words());
+            }
+        }
+    }
+
+    fn epilogue(&self, asm: &mut Asm, frame: &StackFrame) {
+        for (reg, chunk) in &frame.saved_regs {
+            asm.emit(&format!("    mov -{}(%rbp), {}", frame.offsets[chunk], ALLOCATABLE[*reg]));
+        }
+        asm.emit("    leave");
+        asm.emit("    ret");
+    }
+
+    // Only ever clobbers rax
+    fn load_operand(&self, asm: &mut Asm, operand: &LIRValue, frame: &StackFrame) {
+        match &operand.value {
+            LIRValueKind::Place(place) => {
+                match place.place {
+                    LIRPlaceKind::Local { base, offset } => {
+                        let place_offset = frame.local_offset(base, offset);
+                        load_from(asm, &format!("-{}(%rbp)", place_offset), place.size);
+                    }
+                    LIRPlaceKind::Deref { pointer, offset } => {
+                        let pointer_st_offs = frame.offsets[&pointer];
+                        asm.emit(&format!("    mov -{}(%rbp), %rax", pointer_st_offs));
+                        load_from(asm, &format!("{}(%rax)", offset), place.size);
+                    }
+                    LIRPlaceKind::VReg(vreg) => {
+                        match frame.vregs[&vreg] {
+                            VRegHome::Reg(reg) => asm.emit(&format!("    mov {}, %rax", ALLOCATABLE[reg])),
+                            VRegHome::Spill(chunk) => load_from(asm, &format!("-{}(%rbp)", frame.offsets[&chunk]), place.size),
+                        }
+                    }
+                }
+            }
+            LIRValueKind::IntLiteral(num) => {
+                asm.emit(&format!("    mov ${}, %rax", num));
+            }
+            LIRValueKind::BoolTrue => {
+                asm.emit("    mov $1, %rax");
+            }
+            LIRValueKind::BoolFalse => {
+                asm.emit("    mov $0, %rax");
+            }
+            LIRValueKind::Reference(refd) => {
+                match refd.place {
+                    LIRPlaceKind::Local { base, offset } => {
+                        let place_offset = frame.local_offset(base, offset);
+                        asm.emit(&format!("    lea -{}(%rbp), %rax", place_offset));
+                    }
+                    LIRPlaceKind::Deref { pointer, offset } => {
+                        let pointer_st_offs = frame.offsets[&pointer];
+                        asm.emit(&format!("    mov -{}(%rbp), %rax", pointer_st_offs));
+                        asm.emit(&format!("    lea {}(%rax), %rax", offset));
+                    }
+                    LIRPlaceKind::VReg(vreg) => panic!("{:?} has no address", vreg),
+                }
+            }
+        }
+    }
+
+    fn store_place(&self, asm: &mut Asm, place: &LIRPlace, frame: &StackFrame) {
+        match place.place {
+            LIRPlaceKind::Local { base, offset } => {
+                let place_offset = frame.local_offset(base, offset);
+                store_to(asm, ACC, &format!("-{}(%rbp)", place_offset), place.size);
+            }
+            LIRPlaceKind::Deref { pointer, offset } => {
+                let pointer_st_offs = frame.offsets[&pointer];
+                asm.emit(&format!("    mov -{}(%rbp), %rcx", pointer_st_offs));
+                store_to(asm, ACC, &format!("{}(%rcx)", offset), place.size);
+            }
+            LIRPlaceKind::VReg(vreg) => {
+                match frame.vregs[&vreg] {
+                    VRegHome::Reg(reg) => asm.emit(&format!("    mov %rax, {}", ALLOCATABLE[reg])),
+                    VRegHome::Spill(chunk) => store_to(asm, ACC, &format!("-{}(%rbp)", frame.offsets[&chunk]), place.size),
+                }
+            }
+        }
+    }
+
+    fn save_left(&self, asm: &mut Asm) {
+        asm.emit("    mov %rax, %rcx");
+    }
+
+    // Ints come out of load_operand sign-extended, and results are kept that way
+    fn binop(&self, asm: &mut Asm, op: BinaryOperator) {
+        match op {
+            BinaryOperator::Add => {
+                asm.emit("    add %ecx, %eax");
+                asm.emit("    cltq");
+            }
+            BinaryOperator::Sub => {
+                asm.emit("    sub %eax, %ecx");
+                asm.emit("    mov %ecx, %eax");
+                asm.emit("    cltq");
+            }
+            BinaryOperator::Mul => {
+                asm.emit("    imul %ecx, %eax");
+                asm.emit("    cltq");
+            }
+            BinaryOperator::Equals => {
+                asm.emit("    cmp %rax, %rcx");
+                asm.emit("    sete %al");
+                asm.emit("    movzbq %al, %rax");
+            }
+            BinaryOperator::Less => {
+                asm.emit("    cmp %eax, %ecx");
+                asm.emit("    setl %al");
+                asm.emit("    movzbq %al, %rax");
+            }
+            BinaryOperator::Modulo => {
+                asm.emit("    mov %rax, %r8");
+                asm.emit("    mov %ecx, %eax");
+                asm.emit("    cltd");
+                asm.emit("    idiv %r8d");
+                asm.emit("    mov %edx, %eax");
+                asm.emit("    cltq");
+            }
+        }
+    }
+
+    // rcx and rax hold the source and destination addresses, rdx carries each piece over
+    fn copy(&self, asm: &mut Asm, size: usize) {
+        let words = size / 8;
+        let mut offset = 0;
+        if words > COPY_UNROLL {
+            // Walks both pointers forward, so the tail starts at offset 0 again
+            let label = asm.fresh_label("copy");
+            asm.emit(&format!("    mov ${}, %r8", words));
+            asm.emit(&format!("{}:", label));
+            asm.emit("    mov (%rcx), %rdx");
+            asm.emit("    mov %rdx, (%rax)");
+            asm.emit("    add $8, %rcx");
+            asm.emit("    add $8, %rax");
+            asm.emit("    dec %r8");
+            asm.emit(&format!("    jnz {}", label));
+        } else {
+            for _ in 0..words {
+                asm.emit(&format!("    mov {}(%rcx), %rdx", offset));
+                asm.emit(&format!("    mov %rdx, {}(%rax)", offset));
+                offset += 8;
+            }
+        }
+        let mut rest = size % 8;
+        if rest >= 4 {
+            asm.emit(&format!("    mov {}(%rcx), %edx", offset));
+            asm.emit(&format!("    mov %edx, {}(%rax)", offset));
+            offset += 4;
+            rest -= 4;
+        }
+        for _ in 0..rest {
+            asm.emit(&format!("    mov {}(%rcx), %dl", offset));
+            asm.emit(&format!("    mov %dl, {}(%rax)", offset));
+            offset += 1;
+        }
+    }
+
+    fn call(&self, asm: &mut Asm, dest: Option<&LIRPlace>, func: FuncId, args: &[LIRValue], frame: &StackFrame) {
+        let (locations, stack_size) = arg_locations(args.iter().map(|arg| arg.size));
+        if stack_size > 0 {
+            asm.emit(&format!("    sub ${}, %rsp", stack_size));
+        }
+        // The stack args first, since copying uses arg registers
+        for (arg, loc) in args.iter().zip(&locations) {
+            match (loc.words, loc.stack_words()) {
+                (_, 0) => {}
+                (1, _) => {
+                    self.load_operand(asm, arg, frame);
+                    asm.emit(&format!("    mov %rax, {}(%rsp)", loc.stack_offset));
+                }
+                (_, stack_words) => {
+                    self.load_operand(asm, &address_of(arg, 8), frame);
+                    self.save_left(asm);
+                    asm.emit(&format!("    lea {}(%rsp), %rax", loc.stack_offset));
+                    self.copy(asm, 8 * stack_words);
+                }
+            }
+        }
+        for (arg, loc) in args.iter().zip(&locations) {
+            if loc.regs.is_empty() {
+                continue;
+            }
+            if loc.words == 1 {
+                self.load_operand(asm, arg, frame);
+                asm.emit(&format!("    mov %rax, {}", ARG_REGS[loc.regs.start]));
+            } else {
+                self.load_operand(asm, &address_of(arg, 8), frame);
+                for (i, reg) in loc.regs.clone().enumerate() {
+                    asm.emit(&format!("    mov {}(%rax), {}", 8 * i, ARG_REGS[reg]));
+                }
+            }
+        }
+        asm.emit(&format!("    call {}", func_label(func)));
+        if stack_size > 0 {
+            asm.emit(&format!("    add ${}, %rsp", stack_size));
+        }
+        match dest {
+            Some(dest) if dest.size > 8 => {
+                asm.emit("    mov %rax, %rcx");
+                let dest_value = LIRValue { size: dest.size, value: LIRValueKind::Place(dest.clone()) };
+                self.load_operand(asm, &address_of(&dest_value, 8), frame);
+                asm.emit("    mov %rcx, (%rax)");
+                asm.emit("    mov %rdx, 8(%rax)");
+            }
+            Some(dest) => self.store_place(asm, dest, frame),
+            None => {}
+        }
+    }
+
+    fn print(&self, asm: &mut Asm) {
+        asm.emit("    mov %rax, %rsi");
+        asm.emit("    lea fmt(%rip), %rdi");
+        asm.emit("    xor %eax, %eax");
+        asm.emit("    call printf@PLT");
+    }
+
+    fn jump(&self, asm: &mut Asm, label: &str) {
+        asm.emit(&format!("    jmp {}", label));
+    }
+
+    fn branch_if_true(&self, asm: &mut Asm, label: &str) {
+        asm.emit("    cmp $1, %rax");
+        asm.emit(&format!("    je {}", label));
+    }
+
+    // A bare return still gives a clean exit code if it's main's
+    fn return_value(&self, asm: &mut Asm, value: Option<&LIRValue>, frame: &StackFrame) {
+        match value {
+            Some(value) if value.size > 8 => {
+                self.load_operand(asm, &address_of(value, 8), frame);
+                asm.emit("    mov 8(%rax), %rdx");
+                asm.emit("    mov (%rax), %rax");
+            }
+            Some(value) => self.load_operand(asm, value, frame),
+            None => asm.emit("    xor %eax, %eax"),
+        }
+    }
+}
+
+
+// Loads widen into the whole of rax: ints sign-extended, bools zero-extended. Nothing wider than 8
+// bytes gets here, that's copied memory to memory.
+fn load_from(asm: &mut Asm, address: &str, size: usize) {
+    match size {
+        0 => asm.emit("    xor %eax, %eax"),
+        1 => asm.emit(&format!("    movzbq {}, %rax", address)),
+        4 => asm.emit(&format!("    movslq {}, %rax", address)),
+        _ => asm.emit(&format!("    mov {}, %rax", address)),
+    }
+}
+
+fn store_to(asm: &mut Asm, reg: Reg, address: &str, size: usize) {
+    match size {
+        0 => {}
+        1 => asm.emit(&format!("    mov {}, {}", reg[2], address)),
+        4 => asm.emit(&format!("    mov {}, {}", reg[1], address)),
+        _ => asm.emit(&format!("    mov {}, {}", reg[0], address)),
+    }
+}
+
+// System V: an arg of up to two eightbytes goes in that many of the arg registers if they're
+// left, otherwise all of it goes on the stack, as bigger ones always do. There are no floats, so
+// every eightbyte is of the INTEGER class.
+fn arg_locations(sizes: impl Iterator<Item = usize>) -> (Vec<ArgLocation>, usize) {
+    let mut locations = Vec::new();
+    let mut next_reg = 0;
+    let mut stack_size = 0;
+    for size in sizes {
+        let words = size.div_ceil(8);
+        if words <= 2 && next_reg + words <= ARG_REGS.len() {
+            locations.push(ArgLocation { regs: next_reg..next_reg + words, stack_offset: stack_size, words });
+            next_reg += words;
+        } else {
+            locations.push(ArgLocation { regs: next_reg..next_reg, stack_offset: stack_size, words });
+            stack_size += 8 * words;
+        }
+    }
+    (locations, stack_size.next_multiple_of(16))
+}
@@ -1,58 +1,154 @@
 use std::fs;
+use std::io;
 use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command as Process};
 
-mod shared;
-mod stages;
+use minicompiler::{compile_str, CompileOptions, Stage};
+use minicompiler::interp::hir::HIRInterpreter;
+use minicompiler::interp::mir::MIRInterpreter;
+use minicompiler::interp::lir::LIRInterpreter;
 
-mod lex;
-use lex::*;
+mod cli;
+use cli::*;
 
-mod parse;
-use parse::*;
 
-mod make_hir;
-use make_hir::*;
-
-mod hir_to_mir;
-use hir_to_mir::*;
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        print!("{}", USAGE);
+        return;
+    }
+    let opts = match Options::parse(args) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("error: {}\n\n{}", msg, USAGE);
+            process::exit(2);
+        }
+    };
+    process::exit(drive(&opts));
+}
 
-mod mir_to_lir;
-use mir_to_lir::*;
 
-mod lir_codegen;
-use lir_codegen::*;
+fn drive(opts: &Options) -> i32 {
+    let program_text = match fs::read_to_string(&opts.input) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("error: couldn't read {}: {}", opts.input.display(), err);
+            return 1;
+        }
+    };
+    let filename = opts.input.display().to_string();
+    let compile_opts = CompileOptions {
+        stop_after: opts.last_stage().stage(),
+        keep: opts.emits
+            .iter()
+            .map(|e| e.kind.stage())
+            .chain(opts.interp.map(|interp| interp.stage()))
+            // Linking needs the code even when it isn't written out
+            .chain((opts.last_stage() == EmitKind::Exe).then_some(Stage::Asm))
+            .collect(),
+        target: opts.target,
+    };
+    let artifacts = match compile_str(&program_text, &compile_opts) {
+        Ok(artifacts) => artifacts,
+        Err(diags) => {
+            for diag in diags {
+                eprint!("{}", diag.render(&program_text, &filename));
+            }
+            return 1;
+        }
+    };
+    for emit in opts.emits.iter().filter(|e| e.kind != EmitKind::Exe) {
+        let Some(contents) = artifacts.dump(emit.kind.stage()) else {
+            eprintln!("error: the {} stage wasn't reached, so there's nothing to emit for it", emit.kind.name());
+            return 1;
+        };
+        write_artifact(opts, emit, &contents);
+    }
+    if let Some(interp) = opts.interp {
+        let result = match interp {
+            Interp::Hir => HIRInterpreter::run(artifacts.hir.as_ref().unwrap(), &mut io::stdout()),
+            Interp::Mir => MIRInterpreter::run(artifacts.mir.as_ref().unwrap(), &mut io::stdout()),
+            Interp::Lir => LIRInterpreter::run(artifacts.lir.as_ref().unwrap(), &mut io::stdout()),
+        };
+        return match result {
+            Ok(code) => code,
+            Err(err) => {
+                eprintln!("{}: {}", filename, err);
+                1
+            }
+        };
+    }
+    if opts.last_stage() != EmitKind::Exe {
+        return 0;
+    }
+    let assembly = artifacts.asm.unwrap();
+
+    match opts.command {
+        Command::Run => {
+            let exe_path = temp_path(opts, "");
+            let status = link(opts, &assembly, &exe_path)
+                .and_then(|_| Process::new(&exe_path).status().map_err(|e| e.to_string()));
+            let _ = fs::remove_file(&exe_path);
+            match status {
+                Ok(status) => status.code().unwrap_or(1),
+                Err(msg) => {
+                    eprintln!("error: {}", msg);
+                    1
+                }
+            }
+        }
+        _ => {
+            let emit = opts.emits.iter().find(|e| e.kind == EmitKind::Exe).unwrap();
+            match link(opts, &assembly, &opts.artifact_path(emit)) {
+                Ok(()) => 0,
+                Err(msg) => {
+                    eprintln!("error: {}", msg);
+                    1
+                }
+            }
+        }
+    }
+}
 
 
-fn main() {
-    
-    let args: Vec<String> = env::args().collect();
-    let code_filename = &args[1];
-    let assembly_filename = &args[2];
-    let tokens_filepath = &args[3];
-    let ast_filepath = &args[4];
-    let hir_filepath = &args[5];
-    let mir_filepath = &args[6];
-    let lir_filepath = &args[7];
-
-    let program_text = &fs::read_to_string(code_filename).unwrap();
-    let tokens = lex(program_text);
-    
-    fs::write(tokens_filepath, format!("{:#?}", tokens)).unwrap();
-
-    let ast = Parser::parse_program(tokens);
-    fs::write(ast_filepath, format!("{:#?}", ast)).unwrap();
-
-    let hir = HIRBuilder::lower_ast(ast);
-    fs::write(hir_filepath, format!("{:#?}", hir)).unwrap();
-
-    let mir = MIRBuilder::lower_hir(hir);
-    fs::write(mir_filepath, format!("{:#?}", mir)).unwrap();
-
-    let lir = LIRBuilder::lower_mir(mir);
-    fs::write(lir_filepath, format!("{:#?}", lir)).unwrap();
-
-    let assembly = LIRCompiler::compile(lir);
-    fs::write(assembly_filename, assembly).unwrap();
+fn write_artifact(opts: &Options, emit: &Emit, contents: &str) {
+    if opts.command == Command::Dump && emit.path.is_none() && opts.output.is_none() {
+        if opts.emits.len() > 1 {
+            println!("=== {} ===", emit.kind.name());
+        }
+        println!("{}", contents);
+        return;
+    }
+    let path = opts.artifact_path(emit);
+    if let Err(err) = fs::write(&path, contents) {
+        eprintln!("error: couldn't write {}: {}", path.display(), err);
+        process::exit(1);
+    }
 }
 
+// Assembles and links through the system C compiler, which also pulls in libc for printf
+fn link(opts: &Options, assembly: &str, exe_path: &Path) -> Result<(), String> {
+    let asm_path = temp_path(opts, &format!(".{}", opts.target.source_ext()));
+    fs::write(&asm_path, assembly).map_err(|e| e.to_string())?;
+    let output = Process::new("cc")
+        .arg("-o")
+        .arg(exe_path)
+        .arg(&asm_path)
+        .output()
+        .map_err(|e| format!("couldn't invoke cc: {}", e))?;
+    let _ = fs::remove_file(&asm_path);
+    if !output.status.success() {
+        return Err(format!("linking failed:\n{}", String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(())
+}
 
+fn temp_path(opts: &Options, ext: &str) -> PathBuf {
+    let stem = opts.input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("out");
+    env::temp_dir().join(format!("yumc-{}-{}{}", process::id(), stem, ext))
+}
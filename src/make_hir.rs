@@ -5,226 +5,539 @@ use crate::stages::ast::*;
 use crate::stages::hir::*;
 use crate::shared::binops::binop_typecheck;
 use crate::shared::utils::*;
+use crate::shared::span::Span;
+use crate::shared::diagnostics::*;
+use crate::match_check::MatchChecker;
 
-use std::{collections::HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+
+
+
+// Way more than any program that isn't recursing polymorphically would go
+const MAX_INSTANCE_DEPTH: usize = 64;
 
 
-       
-        
 pub struct HIRBuilder {
     scope_context: ScopeContext,
-    function_map: HashMap<ConcreteFuncSignature, (FuncId, ConcreteType)>,
+    functions: Vec<ASTFunction>,
+    // Each function gets lowered once per distinct list of type args it's called with, and
+    // each of those gets an id of its own. Non-generic functions just have the empty list.
+    instances: BTreeMap<(usize, Vec<ConcreteType>), FuncId>,
+    // With how many instantiations led to each, which only grows without end under polymorphic
+    // recursion (`f[T]` calling `f[Box[T]]`)
+    worklist: VecDeque<(FuncId, usize, Vec<ConcreteType>, usize)>,
+    type_bindings: BTreeMap<String, ConcreteType>,      // Of the instance being lowered
+    depth: usize,                                       // Likewise
     typetable: GenericTypetable
 }
 
 impl HIRBuilder {
-    
-    pub fn lower_ast(ast: ASTProgram) -> HIRProgram {
+
+    pub fn lower_ast(ast: ASTProgram) -> CompileResult<HIRProgram> {
         let ASTProgram{typetable, functions} = ast;
 
-        let function_map: HashMap<ConcreteFuncSignature, (FuncId, ConcreteType)> = functions
-            .iter()
-            .enumerate()
-            .map(|(i, (sgn, func))| (sgn.clone(), (FuncId(i), func.ret_type.clone())))
-            .collect();
-        let entry = function_map
+        let mut function_spans: HashMap<GenericFuncSignature, Span> = HashMap::new();
+        for func in &functions {
+            let sgn = func.get_signature();
+            if let Some(prev_span) = function_spans.get(&sgn) {
+                return Err(Diagnostic::error(
+                    ErrorCode::DuplicateDefinition,
+                    format!("function `{}` is defined more than once with these argument types", func.name),
+                    func.span,
+                )
+                .with_primary_label("redefined here")
+                .with_label(*prev_span, "first defined here").into());
+            }
+            function_spans.insert(sgn, func.span);
+        }
+        let main = functions
             .iter()
-            .find_map(|(sgn, id)| { (sgn.name == "main")
-            .then_some(id)})
-            .unwrap().0;
+            .position(|func| func.name == "main")
+            .ok_or_else(|| Diagnostic::error(
+                ErrorCode::MissingEntryPoint,
+                "no `main` function found",
+                Span::new(0, 0, 1, 1),
+            ).with_note("every program needs a `fun main()` to start from"))?;
+        if !functions[main].type_params.is_empty() {
+            return Err(Diagnostic::error(
+                ErrorCode::MissingEntryPoint,
+                "`main` can't have type parameters",
+                functions[main].span,
+            ).with_note("nothing could pick the types it runs with").into());
+        }
 
         let mut builder = HIRBuilder {
             scope_context: ScopeContext::new(),
-            function_map,
+            functions,
+            instances: BTreeMap::new(),
+            worklist: VecDeque::new(),
+            type_bindings: BTreeMap::new(),
+            depth: 0,
             typetable,
         };
 
-        let mut hir_functions: HashMap<FuncId, HIRFunction> = HashMap::new();
+        // Non-generic functions come first, so their ids follow source order and are stable from
+        // run to run. Generic ones are only lowered for the type args they turn out to be called with.
+        for i in 0..builder.functions.len() {
+            if builder.functions[i].type_params.is_empty() {
+                builder.instantiate(i, Vec::new(), builder.functions[i].span)?;
+            }
+        }
+        let entry = builder.instances[&(main, Vec::new())];
 
-        for (sgn,func) in functions {
-            let hir_func = builder.lower_function(func); 
-            hir_functions.insert(builder.function_map[&sgn].0, hir_func);
+        let mut hir_functions: BTreeMap<FuncId, HIRFunction> = BTreeMap::new();
+        while let Some((id, def, type_args, depth)) = builder.worklist.pop_front() {
+            builder.depth = depth;
+            hir_functions.insert(id, builder.lower_function(def, type_args)?);
         }
 
-        HIRProgram {
-            typetable: builder.typetable, 
+        Ok(HIRProgram {
+            typetable: builder.typetable,
             functions: hir_functions,
-            entry, 
+            entry,
+        })
+    }
+
+    fn instantiate(&mut self, def: usize, type_args: Vec<ConcreteType>, span: Span) -> CompileResult<FuncId> {
+        if let Some(id) = self.instances.get(&(def, type_args.clone())) {
+            return Ok(*id);
+        }
+        if self.depth >= MAX_INSTANCE_DEPTH {
+            return Err(Diagnostic::error(
+                ErrorCode::InfiniteInstantiation,
+                format!("instantiating `{}` never ends", self.functions[def].name),
+                span,
+            ).with_note(format!(
+                "each instance calls one with new type args, already {} deep",
+                MAX_INSTANCE_DEPTH,
+            )).into());
         }
+        let id = FuncId(self.instances.len());
+        self.instances.insert((def, type_args.clone()), id);
+        self.worklist.push_back((id, def, type_args, self.depth + 1));
+        Ok(id)
     }
 
-    fn lower_function(&mut self, func: ASTFunction) -> HIRFunction {
-        let ASTFunction { name, args, body, ret_type } = func;
+    fn lower_function(&mut self, def: usize, type_args: Vec<ConcreteType>) -> CompileResult<HIRFunction> {
+        let ASTFunction { name, type_params, args, body, ret_type, span } = self.functions[def].clone();
+        self.type_bindings = type_params.into_iter().zip(type_args).collect();
+        let ret_type = self.resolve_type(&ret_type, span)?;
         self.scope_context.reset(ret_type.clone());
-        let arg_ids: Vec<VarId>  = args
-            .into_iter()
-            .map(|arg| self.scope_context.add_var(Variable { name: arg.0, typ: arg.1}))
-            .collect();
-        let mut hir_body = self.lower_block(body, false);
+        address_taken(&body, &mut self.scope_context.address_taken);
+        let mut arg_ids: Vec<VarId> = Vec::new();
+        for (arg_name, arg_typ) in args {
+            let arg_typ = self.resolve_type(&arg_typ, span)?;
+            arg_ids.push(self.scope_context.add_var(Variable { name: arg_name, typ: arg_typ}));
+        }
+        let mut hir_body = self.lower_block(body, false)?;
         if ret_type == ConcreteType::Prim(PrimType::None) {
-            hir_body.push(HIRStatement::Return(None));
+            hir_body.push(HIRStatement { stmt: HIRStatementKind::Return(None), span });
         }
-        let hir_func = HIRFunction { 
-            name, 
+        let hir_func = HIRFunction {
+            name,
             args: arg_ids,
             variables: self.scope_context.var_map.clone(),
-            body: hir_body, 
-            ret_type 
-        }; 
+            body: hir_body,
+            ret_type
+        };
         self.scope_context.exit_scope();
-        hir_func
+        Ok(hir_func)
     }
 
-    fn lower_lvalue(&mut self, lvalue: ASTLValue) -> Place {
-        match lvalue {
+    fn lower_lvalue(&mut self, lvalue: ASTLValue, span: Span) -> CompileResult<Place> {
+        let place = match lvalue {
             ASTLValue::Variable(var_name) => {
-                let (id, typ) = self.scope_context.get_var_info(&var_name);
-                Place {
+                let (id, typ) = self.scope_context.get_var_info(&var_name, span)?;
+                let place = Place {
                     typ,
                     place: PlaceKind::Variable(id),
+                };
+                match self.scope_context.narrowing(id) {
+                    Some((variant, typ)) => Place {
+                        typ,
+                        place: PlaceKind::VariantPayload { of: Box::new(place), variant },
+                    },
+                    None => place,
                 }
             }
             ASTLValue::FieldAccess { of, field: fname } => {
-                let hir_of = self.lower_lvalue(*of);
-                let ConcreteType::NewType(id, typvars) = hir_of.typ.clone() else {unreachable!()};
-                let typdef = self.typetable.monomorphize(id, typvars);
-                let ConcreteShape::Struct{fields} = typdef else {
-                    panic!("Expression in field access isn't a struct");
-                };
-                let field_type = fields.get(&fname).expect("Field not found in struct").clone();
+                let hir_of = self.lower_lvalue(*of, span)?;
+                let field_type = self.get_field_type(&hir_of.typ, &fname, span)?;
                 Place {
                     typ: field_type,
-                    place: PlaceKind::StructField { 
-                        of: Box::new(hir_of), 
-                        field: fname 
+                    place: PlaceKind::StructField {
+                        of: Box::new(hir_of),
+                        field: fname
                     }
                 }
             }
             ASTLValue::Deref(reference) => {
-                let hir_ref = self.lower_expression(reference);
-                let ConcreteType::Reference(refd_typ) = hir_ref.typ.clone() else {unreachable!()};
+                let hir_ref = self.lower_expression(reference)?;
+                let ConcreteType::Reference(refd_typ) = hir_ref.typ.clone() else {
+                    return Err(deref_error(&hir_ref).into());
+                };
                 Place {
                     typ: *refd_typ,
                     place: PlaceKind::Deref(hir_ref)
                 }
             }
-        }
+        };
+        Ok(place)
     }
 
-    fn lower_statement(&mut self, statement: ASTStatement) -> HIRStatement {
-        match statement {
-            ASTStatement::Let {var, value} => {
-                let hir_value = self.lower_expression(value);
-                if hir_value.typ != var.typ {
-                    panic!("Variable definition inconsistent with value type");
-                }
+    fn lower_statement(&mut self, statement: ASTStatement) -> CompileResult<HIRStatement> {
+        let ASTStatement { stmt, span } = statement;
+        let hir_stmt = match stmt {
+            ASTStatementKind::Let {var, value} => {
+                let var = Variable { typ: self.resolve_type(&var.typ, span)?, name: var.name };
+                let hir_value = self.lower_expression(value)?;
+                let hir_value = self.coerce(hir_value, &var.typ).map_err(|diag| {
+                    diag.with_label(span, format!("`{}` is declared as `{}` here", var.name, var.typ))
+                })?;
                 let var_id = self.scope_context.add_var(var);
-                HIRStatement::Let {
+                HIRStatementKind::Let {
                     var: var_id,
                     value: hir_value,
                 }
             }
-            ASTStatement::Assign { target, value } => {
-                let hir_target = self.lower_lvalue(target);
-                let hir_value = self.lower_expression(value);
-                if hir_target.typ != hir_value.typ {
-                    panic!("Non-matching types in assignment");
-                }
-                HIRStatement::Assign { target: hir_target, value: hir_value}
+            ASTStatementKind::Assign { target, value } => {
+                let hir_target = self.lower_lvalue(target, span)?;
+                let hir_value = self.lower_expression(value)?;
+                let hir_value = self.coerce(hir_value, &hir_target.typ).map_err(|diag| {
+                    diag.with_note("the assigned value must have the type of the place it's assigned to")
+                })?;
+                HIRStatementKind::Assign { target: hir_target, value: hir_value}
             }
-            ASTStatement::If { condition, if_body, else_body } => {
-                let hir_condition = self.lower_expression(condition);
-                if hir_condition.typ != ConcreteType::Prim(PrimType::Bool) {
-                    panic!("If condition expression not boolean");
-                }
-                HIRStatement::If {
-                    condition: hir_condition, 
-                    if_body: self.lower_block(if_body, false), 
+            ASTStatementKind::If { condition, if_body, else_body } => {
+                let hir_condition = self.lower_condition(condition, "if")?;
+                HIRStatementKind::If {
+                    condition: hir_condition,
+                    if_body: self.lower_block(if_body, false)?,
                     else_body: match else_body {
+                        Some(block) => Some(self.lower_block(block, false)?),
                         None => None,
-                        Some(block) => Some(self.lower_block(block, false)),
                     }
                 }
             }
-            ASTStatement::While { condition, body } => {
-                let hir_condition = self.lower_expression(condition);
-                if hir_condition.typ != ConcreteType::Prim(PrimType::Bool) {
-                    panic!("If condition expression not boolean");
-                }
-                HIRStatement::While { 
+            ASTStatementKind::While { condition, body } => {
+                let hir_condition = self.lower_condition(condition, "while")?;
+                HIRStatementKind::While {
                     condition: hir_condition,
-                    body: self.lower_block(body, true),
+                    body: self.lower_block(body, true)?,
                 }
             }
-            ASTStatement::Break => {
+            ASTStatementKind::Break => {
                 if !self.scope_context.in_loop() {
-                    panic!("Break statement detected out of loop");
+                    return Err(Diagnostic::error(ErrorCode::BreakOutsideLoop, "`break` outside of a loop", span)
+                        .with_primary_label("cannot `break` here").into());
                 }
-                HIRStatement::Break
+                HIRStatementKind::Break
             }
-            ASTStatement::Continue => {
+            ASTStatementKind::Continue => {
                 if !self.scope_context.in_loop() {
-                    panic!("Continue statement detected out of loop");
+                    return Err(Diagnostic::error(ErrorCode::ContinueOutsideLoop, "`continue` outside of a loop", span)
+                        .with_primary_label("cannot `continue` here").into());
                 }
-                HIRStatement::Continue
+                HIRStatementKind::Continue
+            }
+            ASTStatementKind::Return(expr) => {
+                let hir_expr = self.lower_expression(expr)?;
+                let ret_type = self.scope_context.ret_type.clone().unwrap();
+                let hir_expr = self.coerce(hir_expr, &ret_type).map_err(|diag| {
+                    diag.with_note(format!("the function returns `{}`", ret_type))
+                })?;
+                HIRStatementKind::Return(Some(hir_expr))
+            }
+            ASTStatementKind::Print(expr) => {
+                let hir_expr = self.lower_expression(expr)?;
+                HIRStatementKind::Print(hir_expr)    // Subtler later
             }
-            ASTStatement::Return(expr) => {
-                let hir_expr = self.lower_expression(expr);
-                if hir_expr.typ != self.scope_context.ret_type.clone().unwrap() {
-                    panic!("Return statement has unexpected type");
+            ASTStatementKind::Call(expr) => {
+                HIRStatementKind::Call(self.lower_expression(expr)?)
+            }
+            ASTStatementKind::Match { scrutinee, arms } => {
+                // Only a variable stays put long enough to be seen as its variant inside an arm, and
+                // only if nothing can write another variant into it behind the arm's back
+                let (narrowed, pinned) = match &scrutinee.expr {
+                    ASTExpressionKind::Variable(name) if self.scope_context.address_taken.contains(name) => (None, Some(name.clone())),
+                    ASTExpressionKind::Variable(name) => (Some(self.scope_context.get_var_info(name, scrutinee.span)?.0), None),
+                    _ => (None, None),
+                };
+                let hir_scrutinee = self.lower_expression(scrutinee)?;
+
+                let mut hir_arms: Vec<HIRMatchArm> = Vec::new();
+                for arm in arms {
+                    let pattern_span = arm.pattern.span;
+                    self.scope_context.add_scope(false);
+                    let pattern = self.lower_pattern(arm.pattern, &hir_scrutinee.typ, &mut Vec::new());
+                    let body = pattern.and_then(|pattern| {
+                        if let (Some(id), HIRPatternKind::Variant { variant, payload }) = (narrowed, &pattern.pat) {
+                            self.scope_context.narrow(id, *variant, payload.typ.clone());
+                        }
+                        let body = self.lower_block(arm.body, false).map_err(|diag| match (&pinned, &pattern.pat) {
+                            (Some(name), HIRPatternKind::Variant { payload, .. }) if diag.code == ErrorCode::NotAStruct => {
+                                diag.with_note(format!(
+                                    "`{}` isn't seen as `{}` in the arm, since its address is taken in this function",
+                                    name, payload.typ,
+                                ))
+                            }
+                            _ => *diag,
+                        })?;
+                        Ok((pattern, body))
+                    });
+                    self.scope_context.exit_scope();
+                    let (pattern, body) = body?;
+
+                    let checker = MatchChecker::new(&self.typetable);
+                    let earlier: Vec<&HIRPattern> = hir_arms.iter().map(|arm| &arm.pattern).collect();
+                    if !checker.is_useful(&earlier, &pattern) {
+                        return Err(Diagnostic::error(
+                            ErrorCode::UnreachableArm,
+                            format!("unreachable arm: `{}` is already matched", checker.show_pattern(&pattern)),
+                            pattern_span,
+                        ).with_note("the arms before it match everything it does").into());
+                    }
+                    hir_arms.push(HIRMatchArm { pattern, body });
                 }
-                HIRStatement::Return(Some(hir_expr))
+
+                let checker = MatchChecker::new(&self.typetable);
+                let patterns: Vec<&HIRPattern> = hir_arms.iter().map(|arm| &arm.pattern).collect();
+                if let Some(missing) = checker.missing(&patterns, &hir_scrutinee.typ) {
+                    return Err(Diagnostic::error(
+                        ErrorCode::NonExhaustiveMatch,
+                        format!("non-exhaustive match on `{}`", hir_scrutinee.typ),
+                        span,
+                    ).with_note(format!("`{}` isn't matched by any arm", missing)).into());
+                }
+                HIRStatementKind::Match { scrutinee: hir_scrutinee, arms: hir_arms }
             }
-            ASTStatement::Print(expr) => {
-                let hir_expr = self.lower_expression(expr);
-                HIRStatement::Print(hir_expr)    // Subtler later
+        };
+        Ok(HIRStatement { stmt: hir_stmt, span })
+    }
+
+    // Bindings become variables of the current scope, so the arm's body has to be lowered in it
+    fn lower_pattern(&mut self, pattern: ASTPattern, expected: &ConcreteType, bound: &mut Vec<String>) -> CompileResult<HIRPattern> {
+        let ASTPattern { pat, span } = pattern;
+        let literal_typ = match pat {
+            ASTPatternKind::IntLiteral(_) => ConcreteType::Prim(PrimType::Integer),
+            ASTPatternKind::BoolTrue | ASTPatternKind::BoolFalse => ConcreteType::Prim(PrimType::Bool),
+            _ => expected.clone(),
+        };
+        if literal_typ != *expected {
+            return Err(Diagnostic::error(
+                ErrorCode::TypeMismatch,
+                format!("mismatched types: expected `{}`, found `{}`", expected, literal_typ),
+                span,
+            ).with_primary_label(format!("expected `{}`", expected)).into());
+        }
+        let hir_pat = match pat {
+            ASTPatternKind::Wildcard => HIRPatternKind::Wildcard,
+            ASTPatternKind::IntLiteral(num) => HIRPatternKind::IntLiteral(num),
+            ASTPatternKind::BoolTrue => HIRPatternKind::BoolTrue,
+            ASTPatternKind::BoolFalse => HIRPatternKind::BoolFalse,
+            ASTPatternKind::Name(name) => {
+                if self.typetable.defs.contains_key(&NewtypeId(name.clone())) {
+                    let typ = GenericType::NewType(NewtypeId(name), Vec::new());
+                    return self.lower_typed_pattern(typ, None, expected, span, bound);
+                }
+                if self.type_bindings.contains_key(&name) {
+                    return self.lower_typed_pattern(GenericType::TypeVar(name), None, expected, span, bound);
+                }
+                if bound.contains(&name) {
+                    return Err(Diagnostic::error(
+                        ErrorCode::DuplicateDefinition,
+                        format!("`{}` is bound more than once in this pattern", name),
+                        span,
+                    ).into());
+                }
+                bound.push(name.clone());
+                HIRPatternKind::Binding(self.scope_context.add_var(Variable { name, typ: expected.clone() }))
+            }
+            ASTPatternKind::Type(typ) => return self.lower_typed_pattern(typ, None, expected, span, bound),
+            ASTPatternKind::Struct { typ, fields } => {
+                return self.lower_typed_pattern(typ, Some(fields), expected, span, bound);
             }
+            ASTPatternKind::Positional { typ, fields } => {
+                let given = fields.len();
+                let fields = fields.into_iter().enumerate().map(|(i, field)| (i.to_string(), field)).collect();
+                let hir_pattern = self.lower_typed_pattern(typ, Some(fields), expected, span, bound)?;
+                // Unlike named ones, none can be left out
+                let payload = match &hir_pattern.pat {
+                    HIRPatternKind::Variant { payload, .. } => payload,
+                    _ => &hir_pattern,
+                };
+                if let HIRPatternKind::Struct { fields } = &payload.pat && fields.len() != given {
+                    return Err(Diagnostic::error(
+                        ErrorCode::MissingField,
+                        format!("`{}` has {} field(s) but the pattern gives {}", payload.typ, fields.len(), given),
+                        span,
+                    ).into());
+                }
+                return Ok(hir_pattern);
+            }
+        };
+        Ok(HIRPattern { typ: expected.clone(), pat: hir_pat })
+    }
+
+    // A pattern naming its type matches values of that type, or of that variant of an enum
+    fn lower_typed_pattern(
+        &mut self,
+        typ: GenericType,
+        fields: Option<Vec<(String, ASTPattern)>>,
+        expected: &ConcreteType,
+        span: Span,
+        bound: &mut Vec<String>,
+    ) -> CompileResult<HIRPattern> {
+        let typ = self.resolve_type(&typ, span)?;
+        let inner = match fields {
+            None => HIRPattern { typ: typ.clone(), pat: HIRPatternKind::Wildcard },
+            Some(fields) => {
+                let field_types = match &typ {
+                    ConcreteType::NewType(id, tvars) => match self.typetable.get_mono(id.clone(), tvars.clone()) {
+                        ConcreteShape::Struct { fields } => Some(fields),
+                        ConcreteShape::Enum { .. } => None,
+                    },
+                    _ => None,
+                };
+                let Some(field_types) = field_types else {
+                    return Err(Diagnostic::error(ErrorCode::NotAStruct, format!("`{}` is not a struct", typ), span).into());
+                };
+                let mut given: BTreeMap<String, HIRPattern> = BTreeMap::new();
+                for (fname, fpattern) in fields {
+                    let fspan = fpattern.span;
+                    let Some(ftype) = field_types.get(&fname) else {
+                        return Err(Diagnostic::error(
+                            ErrorCode::NoSuchField,
+                            format!("struct `{}` has no field `{}`", typ, fname),
+                            fspan,
+                        ).into());
+                    };
+                    if given.contains_key(&fname) {
+                        return Err(Diagnostic::error(
+                            ErrorCode::DuplicateDefinition,
+                            format!("field `{}` is matched more than once", fname),
+                            fspan,
+                        ).into());
+                    }
+                    let hir_fpattern = self.lower_pattern(fpattern, ftype, bound)?;
+                    given.insert(fname, hir_fpattern);
+                }
+                let fields = field_types
+                    .into_iter()
+                    .map(|(fname, ftype)| {
+                        let fpattern = given.remove(&fname).unwrap_or(HIRPattern { typ: ftype, pat: HIRPatternKind::Wildcard });
+                        (fname, fpattern)
+                    })
+                    .collect();
+                HIRPattern { typ: typ.clone(), pat: HIRPatternKind::Struct { fields } }
+            }
+        };
+        if typ == *expected {
+            return Ok(inner);
+        }
+        if let Some(variant) = self.typetable.variant_index(expected, &typ) {
+            return Ok(HIRPattern {
+                typ: expected.clone(),
+                pat: HIRPatternKind::Variant { variant, payload: Box::new(inner) },
+            });
         }
+        let expected_enum = match expected {
+            ConcreteType::NewType(id, _) => matches!(self.typetable.defs[id].defn, NewtypeShape::Enum { .. }),
+            _ => false,
+        };
+        if expected_enum {
+            return Err(Diagnostic::error(
+                ErrorCode::InvalidVariant,
+                format!("`{}` is not a variant of `{}`", typ, expected),
+                span,
+            ).into());
+        }
+        Err(Diagnostic::error(
+            ErrorCode::NotAnEnum,
+            format!("cannot match a value of type `{}` against `{}`", expected, typ),
+            span,
+        ).with_note(format!("`{}` is not an enum, so only `{}` patterns can match it", expected, expected)).into())
+    }
+
+    fn lower_condition(&mut self, condition: ASTExpression, keyword: &str) -> CompileResult<HIRExpression> {
+        let hir_condition = self.lower_expression(condition)?;
+        if hir_condition.typ != ConcreteType::Prim(PrimType::Bool) {
+            return Err(Diagnostic::error(
+                ErrorCode::NonBoolCondition,
+                format!("`{}` condition must be a `bool`", keyword),
+                hir_condition.span,
+            ).with_primary_label(format!("this has type `{}`", hir_condition.typ)).into());
+        }
+        Ok(hir_condition)
     }
 
-    fn lower_block(&mut self, stmts: Vec<ASTStatement>, loop_block: bool) -> Vec<HIRStatement>{
+    fn lower_block(&mut self, stmts: Vec<ASTStatement>, loop_block: bool) -> CompileResult<Vec<HIRStatement>>{
         self.scope_context.add_scope(loop_block);
-        let stmts: Vec<HIRStatement> = stmts 
+        let stmts = stmts
             .into_iter()
             .map(|stmt| self.lower_statement(stmt))
-            .collect();
+            .collect::<CompileResult<Vec<HIRStatement>>>()?;
         self.scope_context.exit_scope();
-        stmts
+        Ok(stmts)
     }
 
-    fn lower_expression(&mut self, expr: ASTExpression) -> HIRExpression {
-        match expr {
-            ASTExpression::IntLiteral(num) => HIRExpression {
+    fn lower_expression(&mut self, expr: ASTExpression) -> CompileResult<HIRExpression> {
+        let ASTExpression { expr, span } = expr;
+        let hir_expr = match expr {
+            ASTExpressionKind::IntLiteral(num) => HIRExpression {
+                span,
                 typ: ConcreteType::Prim(PrimType::Integer),
                 expr: HIRExpressionKind::IntLiteral(num),
             },
-            ASTExpression::Variable(varname) => {
-                let (id, typ) = self.scope_context.get_var_info(&varname);
-                HIRExpression {
-                    typ: typ,
+            ASTExpressionKind::Variable(varname) => {
+                let (id, typ) = self.scope_context.get_var_info(&varname, span)?;
+                let var_expr = HIRExpression {
+                    span,
+                    typ,
                     expr: HIRExpressionKind::Variable(id)
+                };
+                match self.scope_context.narrowing(id) {
+                    Some((variant, typ)) => HIRExpression {
+                        span,
+                        typ,
+                        expr: HIRExpressionKind::VariantPayload { expr: Box::new(var_expr), variant },
+                    },
+                    None => var_expr,
                 }
             }
-            ASTExpression::BinOp{ op, left, right} => {
-                let left_hir = self.lower_expression(*left);
-                let right_hir = self.lower_expression(*right);
-                let result_type = binop_typecheck(&op, &left_hir.typ, &right_hir.typ)
-                    .expect("Binop typecheck failed");
+            ASTExpressionKind::BinOp{ op, left, right} => {
+                let left_hir = self.lower_expression(*left)?;
+                let right_hir = self.lower_expression(*right)?;
+                let Some(result_type) = binop_typecheck(&op, &left_hir.typ, &right_hir.typ) else {
+                    return Err(Diagnostic::error(
+                        ErrorCode::InvalidOperands,
+                        format!("invalid operand types for `{:?}`", op),
+                        span,
+                    )
+                    .with_label(left_hir.span, format!("`{}`", left_hir.typ))
+                    .with_label(right_hir.span, format!("`{}`", right_hir.typ)).into());
+                };
                 HIRExpression {
+                    span,
                     typ: result_type,
-                    expr: HIRExpressionKind::BinOp{ 
-                        op, 
+                    expr: HIRExpressionKind::BinOp{
+                        op,
                         left: Box::new(left_hir),
                         right: Box::new(right_hir),
                     }
                 }
             }
-            ASTExpression::FuncCall { funcname, args } => {
-                let hir_args: Vec<HIRExpression> = args
+            ASTExpressionKind::FuncCall { funcname, type_args, args } => {
+                let type_args = type_args
+                    .iter()
+                    .map(|typ| self.resolve_type(typ, span))
+                    .collect::<CompileResult<Vec<ConcreteType>>>()?;
+                let hir_args = args
                     .into_iter()
                     .map(|arg| self.lower_expression(arg))
-                    .collect();
-                
+                    .collect::<CompileResult<Vec<HIRExpression>>>()?;
+
                 let func_sgn = FuncSignature {
                     name: funcname,
                     argtypes: hir_args
@@ -232,101 +545,470 @@ impl HIRBuilder {
                         .map(|arg| arg.typ.clone())
                         .collect()
                 };
-                let (func_id, ret_typ) = &self.function_map[&func_sgn];
+                let (def, bindings) = self.resolve_call(&func_sgn, &type_args, span)?;
+                let func = &self.functions[def];
+                let ret_typ = func.ret_type.monomorphize(&bindings);
+                let type_args = func.type_params.iter().map(|param| bindings[param].clone()).collect();
+                let hir_args = hir_args
+                    .into_iter()
+                    .zip(&func.args)
+                    .map(|(arg, (_, typ))| self.coerce(arg, &typ.monomorphize(&bindings)))
+                    .collect::<CompileResult<Vec<HIRExpression>>>()?;
                 HIRExpression {
-                    typ: ret_typ.clone(),
-                    expr: HIRExpressionKind::FuncCall{ 
-                        id: *func_id, 
+                    span,
+                    typ: ret_typ,
+                    expr: HIRExpressionKind::FuncCall{
+                        id: self.instantiate(def, type_args, span)?,
                         args: hir_args
                     }
-                } 
+                }
             }
-            ASTExpression::BoolTrue => HIRExpression {
+            ASTExpressionKind::BoolTrue => HIRExpression {
+                span,
                 typ: ConcreteType::Prim(PrimType::Bool),
                 expr: HIRExpressionKind::BoolTrue,
             },
-            ASTExpression::BoolFalse => HIRExpression {
+            ASTExpressionKind::BoolFalse => HIRExpression {
+                span,
                 typ: ConcreteType::Prim(PrimType::Bool),
                 expr: HIRExpressionKind::BoolFalse,
             },
-            ASTExpression::FieldAccess{expr, field} => {
-                let hir_expr = self.lower_expression(*expr);
-                let ConcreteType::NewType(id, bindings) = hir_expr.typ.clone() else {
-                    panic!("Expression in field access isn't a newtype");
-                };
-                let expr_typ = self.typetable.monomorphize(id, bindings);
-                let ConcreteShape::Struct { fields } = expr_typ else {
-                    panic!("Expression in field access isn't a struct");
-                };
-                let field_type = fields.get(&field)
-                    .expect("Struct in field access doesn't have the requested field")
-                    .clone();
+            ASTExpressionKind::FieldAccess{expr, field} => {
+                let hir_expr = self.lower_expression(*expr)?;
+                let field_type = self.get_field_type(&hir_expr.typ, &field, span)?;
                 HIRExpression {
+                    span,
                     typ: field_type,
-                    expr: HIRExpressionKind::FieldAccess{  
+                    expr: HIRExpressionKind::FieldAccess{
                         expr: Box::new(hir_expr),
-                        field 
+                        field
                      }
                 }
             }
-            ASTExpression::StructLiteral{typ, fields} => {
-                let hir_fields: HashMap<String, HIRExpression> = fields 
-                        .into_iter()
-                        .map(|(fname, fexpr)| (fname, self.lower_expression(fexpr)))
-                        .collect();
-                self.typecheck_struct_literal(typ.clone(), hir_fields.clone());
+            ASTExpressionKind::StructLiteral{typ, fields} => {
+                let typ = self.resolve_type(&typ, span)?;
+                let mut hir_fields: Vec<(String, HIRExpression)> = Vec::new();
+                for (fname, fexpr) in fields {
+                    let hir_fexpr = self.lower_expression(fexpr)?;
+                    if hir_fields.iter().any(|(f, _)| *f == fname) {
+                        return Err(Diagnostic::error(
+                            ErrorCode::DuplicateDefinition,
+                            format!("field `{}` is specified more than once", fname),
+                            hir_fexpr.span,
+                        ).into());
+                    }
+                    hir_fields.push((fname, hir_fexpr));
+                }
+                let hir_fields = self.typecheck_struct_literal(&typ, hir_fields, span)?;
                 HIRExpression {
-                    typ, 
-                    expr: HIRExpressionKind::StructLiteral { 
+                    span,
+                    typ,
+                    expr: HIRExpressionKind::StructLiteral {
                         fields: hir_fields
                     }
                 }
             }
-            ASTExpression::Reference(refd) => {
-                let hir_refd = self.lower_expression(*refd);
-                HIRExpression{
+            ASTExpressionKind::Reference(refd) => {
+                let hir_refd = self.lower_expression(*refd)?;
+                HIRExpression {
+                    span,
                     typ: ConcreteType::Reference(Box::new(hir_refd.typ.clone())),
                     expr: HIRExpressionKind::Reference(Box::new(hir_refd)),
                 }
             }
-            ASTExpression::Dereference(derefd) => {
-                let hir_derefd = self.lower_expression(*derefd);
+            ASTExpressionKind::Dereference(derefd) => {
+                let hir_derefd = self.lower_expression(*derefd)?;
                 let ConcreteType::Reference(deref_typ) = hir_derefd.typ.clone() else {
-                    unreachable!();
+                    return Err(deref_error(&hir_derefd).into());
                 };
-                HIRExpression{
+                HIRExpression {
+                    span,
                     typ: *deref_typ,
                     expr: HIRExpressionKind::Dereference(Box::new(hir_derefd)),
                 }
             }
 
-        }
+        };
+        Ok(hir_expr)
+    }
+
+    fn get_field_type(&mut self, typ: &ConcreteType, field: &String, span: Span) -> CompileResult<ConcreteType> {
+        let not_a_struct = || Diagnostic::error(
+            ErrorCode::NotAStruct,
+            format!("no field `{}` on type `{}`", field, typ),
+            span,
+        ).with_note(format!("`{}` is not a struct", typ));
+        let ConcreteType::NewType(id, bindings) = typ.clone() else {
+            return Err(not_a_struct().into());
+        };
+        let ConcreteShape::Struct { fields } = self.typetable.monomorphize(id, bindings) else {
+            return Err(not_a_struct().into());
+        };
+        fields.get(field).cloned().ok_or_else(|| {
+            let names: Vec<&String> = fields.keys().collect();
+            Diagnostic::error(
+                ErrorCode::NoSuchField,
+                format!("struct `{}` has no field `{}`", typ, field),
+                span,
+            ).with_note(format!("available fields: {:?}", names)).into()
+        })
     }
 
+    // Hands the fields back with their values coerced to the field types
     fn typecheck_struct_literal(
-        &mut self, 
-        typ: ConcreteType, 
-        literal_fields: HashMap<String, HIRExpression>
-    ) {
+        &mut self,
+        typ: &ConcreteType,
+        literal_fields: Vec<(String, HIRExpression)>,
+        span: Span,
+    ) -> CompileResult<Vec<(String, HIRExpression)>> {
         let ConcreteType::NewType(id, typvars) = typ.clone() else {unreachable!()};
         let typdef = self.typetable.monomorphize(id, typvars);
         let ConcreteShape::Struct{fields: expected_fields} = typdef else {
-            panic!("Expression in field access isn't a struct");
+            return Err(Diagnostic::error(ErrorCode::NotAStruct, format!("`{}` is not a struct", typ), span).into());
+        };
+        if let Some(fname) = expected_fields.keys().find(|fname| literal_fields.iter().all(|(f, _)| f != *fname)) {
+            return Err(Diagnostic::error(
+                ErrorCode::MissingField,
+                format!("missing field `{}` in literal of `{}`", fname, typ),
+                span,
+            ).into());
+        }
+        literal_fields
+            .into_iter()
+            .map(|(fname, fvalue)| {
+                let Some(exp_type) = expected_fields.get(&fname) else {
+                    return Err(Diagnostic::error(
+                        ErrorCode::NoSuchField,
+                        format!("struct `{}` has no field `{}`", typ, fname),
+                        fvalue.span,
+                    ).into());
+                };
+                let fvalue = self.coerce(fvalue, exp_type).map_err(|diag| {
+                    diag.with_note(format!("field `{}` of `{}` has type `{}`", fname, typ, exp_type))
+                })?;
+                Ok((fname, fvalue))
+            })
+            .collect()
+    }
+
+    // A value of one of an enum's variants can go wherever the enum is expected, and gets wrapped
+    // into it there. Anything else has to have the expected type exactly.
+    fn coerce(&self, expr: HIRExpression, expected: &ConcreteType) -> CompileResult<HIRExpression> {
+        if expr.typ == *expected {
+            return Ok(expr);
+        }
+        let Some(variant) = self.typetable.variant_index(expected, &expr.typ) else {
+            return Err(mismatch_error(expected, &expr).into());
+        };
+        Ok(HIRExpression {
+            span: expr.span,
+            typ: expected.clone(),
+            expr: HIRExpressionKind::EnumLiteral { variant, payload: Box::new(expr) },
+        })
+    }
+
+    // Makes sure a type written in the source refers to existing newtypes with the right number of parameters
+    fn check_type(&mut self, typ: &ConcreteType, span: Span) -> CompileResult<()> {
+        match typ {
+            ConcreteType::Prim(..) => Ok(()),
+            ConcreteType::Reference(refd) => self.check_type(refd, span),
+            ConcreteType::NewType(id, params) => {
+                let Some(def) = self.typetable.defs.get(id) else {
+                    return Err(Diagnostic::error(
+                        ErrorCode::UnknownType,
+                        format!("cannot find type `{}`", id.0),
+                        span,
+                    ).into());
+                };
+                if def.type_params.len() != params.len() {
+                    return Err(Diagnostic::error(
+                        ErrorCode::WrongTypeArgCount,
+                        format!(
+                            "type `{}` takes {} type parameter(s) but {} were given",
+                            id.0, def.type_params.len(), params.len()
+                        ),
+                        span,
+                    ).into());
+                }
+                for param in params {
+                    self.check_type(param, span)?;
+                }
+                if self.typetable.is_monomorphized(id, params) {
+                    return Ok(());
+                }
+                match self.typetable.monomorphize(id.clone(), params.clone()) {
+                    ConcreteShape::Struct { fields } => {
+                        for ftyp in fields.values() {
+                            self.check_type(ftyp, span)?;
+                        }
+                    }
+                    ConcreteShape::Enum { variants } => {
+                        for (i, variant) in variants.iter().enumerate() {
+                            self.check_variant(typ, variant, span)?;
+                            if variants[..i].contains(variant) {
+                                return Err(Diagnostic::error(
+                                    ErrorCode::InvalidVariant,
+                                    format!("`{}` is a variant of `{}` more than once", variant, typ),
+                                    span,
+                                ).with_note("a value of it couldn't tell which variant it is").into());
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Types written inside a function can use its type vars, which the instance being lowered binds
+    fn resolve_type(&mut self, typ: &GenericType, span: Span) -> CompileResult<ConcreteType> {
+        let typ = typ.monomorphize(&self.type_bindings);
+        self.check_type(&typ, span)?;
+        Ok(typ)
+    }
+
+    // Picks the function a call goes to, along with the bindings of its type vars: the explicit
+    // type args if there are any, otherwise whatever makes its arg types fit. Non-generic functions
+    // take precedence over generic ones that would fit too.
+    fn resolve_call(
+        &self,
+        sgn: &ConcreteFuncSignature,
+        type_args: &[ConcreteType],
+        span: Span,
+    ) -> CompileResult<(usize, BTreeMap<String, ConcreteType>)> {
+        let mut candidates: Vec<(usize, &ASTFunction)> = self.functions
+            .iter()
+            .enumerate()
+            .filter(|(_, func)| func.name == sgn.name)
+            .collect();
+        candidates.sort_by_key(|(_, func)| !func.type_params.is_empty());
+        let mut uninferred: Option<&ASTFunction> = None;
+        // Args that are variants of an enum param only count once nothing fits without that
+        for coercing in [false, true] {
+            for (def, func) in &candidates {
+                if func.args.len() != sgn.argtypes.len() {
+                    continue;
+                }
+                if !type_args.is_empty() && type_args.len() != func.type_params.len() {
+                    continue;
+                }
+                let mut bindings: BTreeMap<String, ConcreteType> = func.type_params
+                    .iter()
+                    .cloned()
+                    .zip(type_args.iter().cloned())
+                    .collect();
+                let fits = func.args
+                    .iter()
+                    .zip(&sgn.argtypes)
+                    .all(|((_, typ), argtyp)| {
+                        let mut attempt = bindings.clone();
+                        if typ.infer_bindings(argtyp, &mut attempt) {
+                            bindings = attempt;
+                            return true;
+                        }
+                        // A namespaced variant has its enum's type args, so that can be inferred from
+                        let mut attempt = bindings.clone();
+                        if coercing
+                            && let ConcreteType::NewType(id, tvars) = argtyp
+                            && let Some(enum_id) = id.enum_of()
+                            && typ.infer_bindings(&ConcreteType::NewType(enum_id, tvars.clone()), &mut attempt)
+                        {
+                            bindings = attempt;
+                            return true;
+                        }
+                        coercing && self.is_variant_of(argtyp, typ, &bindings)
+                    });
+                if !fits {
+                    continue;
+                }
+                if bindings.len() < func.type_params.len() {
+                    uninferred.get_or_insert(func);
+                    continue;
+                }
+                return Ok((*def, bindings));
+            }
+        }
+
+        if let Some(func) = uninferred {
+            let unbound: Vec<String> = func.type_params
+                .iter()
+                .filter(|param| !func.args.iter().any(|(_, typ)| type_vars(typ).contains(param)))
+                .map(|param| format!("`{}`", param))
+                .collect();
+            return Err(Diagnostic::error(
+                ErrorCode::CannotInferTypeArgs,
+                format!("cannot infer the type args of `{}`", sgn.name),
+                span,
+            )
+            .with_note(format!("nothing in the arguments determines {}", unbound.join(", ")))
+            .with_note(format!("give them explicitly, as in `{}[...](...)`", sgn.name)).into());
+        }
+        let arg_counts_fit = candidates.iter().any(|(_, func)| func.args.len() == sgn.argtypes.len());
+        if !type_args.is_empty() && arg_counts_fit && candidates.iter().all(|(_, func)| func.type_params.len() != type_args.len()) {
+            let counts: Vec<String> = candidates.iter().map(|(_, func)| func.type_params.len().to_string()).collect();
+            return Err(Diagnostic::error(
+                ErrorCode::WrongTypeArgCount,
+                format!(
+                    "function `{}` takes {} type parameter(s) but {} were given",
+                    sgn.name, counts.join(" or "), type_args.len()
+                ),
+                span,
+            ).into());
+        }
+        Err(self.unknown_function_error(sgn, span).into())
+    }
+
+    // Only once the param's type vars are all bound: its type isn't inferred from variants
+    fn is_variant_of(&self, typ: &ConcreteType, param: &GenericType, bindings: &BTreeMap<String, ConcreteType>) -> bool {
+        type_vars(param).iter().all(|var| bindings.contains_key(*var))
+            && self.typetable.variant_index(&param.monomorphize(bindings), typ).is_some()
+    }
+
+    // Enums are sums over structs
+    fn check_variant(&mut self, enum_typ: &ConcreteType, variant: &ConcreteType, span: Span) -> CompileResult<()> {
+        self.check_type(variant, span)?;
+        let is_struct = match variant {
+            ConcreteType::NewType(id, _) => matches!(self.typetable.defs[id].defn, NewtypeShape::Struct { .. }),
+            _ => false,
         };
-        for (fname, exp_type) in expected_fields {
-            if exp_type != literal_fields.get(&fname).expect("Field not found").typ {
-                panic!("Field type doesn't match expected type");
+        if !is_struct {
+            return Err(Diagnostic::error(
+                ErrorCode::InvalidVariant,
+                format!("variant `{}` of `{}` is not a struct", variant, enum_typ),
+                span,
+            ).with_note("enum variants have to be struct types").into());
+        }
+        Ok(())
+    }
+
+    fn unknown_function_error(&self, sgn: &ConcreteFuncSignature, span: Span) -> Diagnostic {
+        let argtypes: Vec<String> = sgn.argtypes.iter().map(|t| format!("`{}`", t)).collect();
+        let candidates: Vec<String> = self.functions
+            .iter()
+            .filter(|cand| cand.name == sgn.name)
+            .map(|cand| {
+                let args: Vec<String> = cand.args.iter().map(|(_, t)| t.to_string()).collect();
+                let params = match cand.type_params.is_empty() {
+                    true => String::new(),
+                    false => format!("[{}]", cand.type_params.join(", ")),
+                };
+                format!("`{}{}({})`", cand.name, params, args.join(", "))
+            })
+            .collect();
+        let diag = Diagnostic::error(
+            ErrorCode::UnknownFunction,
+            format!("no function `{}` taking ({})", sgn.name, argtypes.join(", ")),
+            span,
+        );
+        if candidates.is_empty() {
+            diag
+        } else {
+            diag.with_note(format!("candidates are: {}", candidates.join(", ")))
+        }
+    }
+}
+
+
+fn mismatch_error(expected: &ConcreteType, found: &HIRExpression) -> Diagnostic {
+    Diagnostic::error(
+        ErrorCode::TypeMismatch,
+        format!("mismatched types: expected `{}`, found `{}`", expected, found.typ),
+        found.span,
+    ).with_primary_label(format!("expected `{}`", expected))
+}
+
+fn type_vars(typ: &GenericType) -> Vec<&String> {
+    match typ {
+        GenericType::Prim(..) => vec![],
+        GenericType::NewType(_, params) => params.iter().flat_map(type_vars).collect(),
+        GenericType::Reference(refd) => type_vars(refd),
+        GenericType::TypeVar(name) => vec![name],
+    }
+}
+
+// Goes by name, so a shadowed variable counts for all of them
+fn address_taken(body: &[ASTStatement], names: &mut HashSet<String>) {
+    for stmt in body {
+        match &stmt.stmt {
+            ASTStatementKind::Let { value, .. } => address_taken_expr(value, names),
+            ASTStatementKind::Assign { target, value } => {
+                let mut target = target;
+                while let ASTLValue::FieldAccess { of, .. } = target {
+                    target = of;
+                }
+                if let ASTLValue::Deref(reference) = target {
+                    address_taken_expr(reference, names);
+                }
+                address_taken_expr(value, names);
+            }
+            ASTStatementKind::If { condition, if_body, else_body } => {
+                address_taken_expr(condition, names);
+                address_taken(if_body, names);
+                if let Some(else_body) = else_body {
+                    address_taken(else_body, names);
+                }
+            }
+            ASTStatementKind::While { condition, body } => {
+                address_taken_expr(condition, names);
+                address_taken(body, names);
+            }
+            ASTStatementKind::Match { scrutinee, arms } => {
+                address_taken_expr(scrutinee, names);
+                for arm in arms {
+                    address_taken(&arm.body, names);
+                }
             }
+            ASTStatementKind::Return(expr) | ASTStatementKind::Print(expr) | ASTStatementKind::Call(expr) => {
+                address_taken_expr(expr, names);
+            }
+            ASTStatementKind::Break | ASTStatementKind::Continue => {}
         }
     }
 }
 
+fn address_taken_expr(expr: &ASTExpression, names: &mut HashSet<String>) {
+    match &expr.expr {
+        ASTExpressionKind::Reference(refd) => {
+            // A reference to a field is into the variable too
+            let mut root = &**refd;
+            while let ASTExpressionKind::FieldAccess { expr, .. } = &root.expr {
+                root = expr;
+            }
+            if let ASTExpressionKind::Variable(name) = &root.expr {
+                names.insert(name.clone());
+            }
+            address_taken_expr(refd, names);
+        }
+        ASTExpressionKind::BinOp { left, right, .. } => {
+            address_taken_expr(left, names);
+            address_taken_expr(right, names);
+        }
+        ASTExpressionKind::FuncCall { args, .. } => args.iter().for_each(|arg| address_taken_expr(arg, names)),
+        ASTExpressionKind::StructLiteral { fields, .. } => fields.iter().for_each(|(_, fexpr)| address_taken_expr(fexpr, names)),
+        ASTExpressionKind::FieldAccess { expr, .. } | ASTExpressionKind::Dereference(expr) => address_taken_expr(expr, names),
+        ASTExpressionKind::IntLiteral(_) | ASTExpressionKind::Variable(_) | ASTExpressionKind::BoolTrue | ASTExpressionKind::BoolFalse => {}
+    }
+}
+
+fn deref_error(expr: &HIRExpression) -> Diagnostic {
+    Diagnostic::error(
+        ErrorCode::DerefNonReference,
+        format!("cannot dereference a value of type `{}`", expr.typ),
+        expr.span,
+    ).with_primary_label("not a reference")
+}
+
 
 
 struct ScopeContext {
     var_scope_stack: Vec<HashMap<String, VarId>>,
+    // Enum variables seen as one of their variants, inside match arms on them
+    narrowings: Vec<HashMap<VarId, (usize, ConcreteType)>>,
+    // Names with a reference taken to them anywhere in the function, which never get narrowed:
+    // a write through the reference could change the variant from under the arm
+    address_taken: HashSet<String>,
     loop_entrances: Vec<bool>,
-    var_map: HashMap<VarId, ConcreteVariable>,
+    var_map: BTreeMap<VarId, ConcreteVariable>,
     var_counter: usize,
     ret_type: Option<ConcreteType>,
 }
@@ -338,8 +1020,10 @@ impl ScopeContext {
     fn new() -> Self {
         ScopeContext {
             var_scope_stack: vec![HashMap::new()],
+            narrowings: vec![HashMap::new()],
+            address_taken: HashSet::new(),
             loop_entrances: vec![false],
-            var_map: HashMap::new(),
+            var_map: BTreeMap::new(),
             var_counter: 0,
             ret_type: None,
         }
@@ -347,13 +1031,16 @@ impl ScopeContext {
 
     fn reset(&mut self, new_ret_type: ConcreteType) {
         self.var_scope_stack = vec![HashMap::new()];
+        self.narrowings = vec![HashMap::new()];
+        self.address_taken = HashSet::new();
         self.loop_entrances = vec![false];
-        self.var_map = HashMap::new();
+        self.var_map = BTreeMap::new();
         self.ret_type = Some(new_ret_type);
     }
 
     fn add_scope(&mut self, loop_entry: bool) {
         self.var_scope_stack.push(HashMap::new());
+        self.narrowings.push(HashMap::new());
         self.loop_entrances.push(loop_entry);
     }
 
@@ -363,25 +1050,37 @@ impl ScopeContext {
 
     fn exit_scope(&mut self) {
         self.var_scope_stack.pop();
+        self.narrowings.pop();
         self.loop_entrances.pop();
     }
-    
+
+    // Lasts until the current scope is exited
+    fn narrow(&mut self, id: VarId, variant: usize, typ: ConcreteType) {
+        self.narrowings.last_mut().unwrap().insert(id, (variant, typ));
+    }
+
+    fn narrowing(&self, id: VarId) -> Option<(usize, ConcreteType)> {
+        self.narrowings.iter().rev().find_map(|scope| scope.get(&id)).cloned()
+    }
+
     fn add_var(&mut self, var: ConcreteVariable) -> VarId {
         let id = VarId(self.var_counter);
-        self.var_counter = self.var_counter + 1;
+        self.var_counter += 1;
         self.var_scope_stack.last_mut().unwrap().insert(var.name.clone(), id);
         self.var_map.insert(id, var);
         id
     }
 
-    fn get_var_info(&self, name: &String) -> (VarId, ConcreteType) {
-        let id = **self.var_scope_stack
+    fn get_var_info(&self, name: &String, span: Span) -> CompileResult<(VarId, ConcreteType)> {
+        let id = self.var_scope_stack
             .iter()
-            .flatten()
-            .collect::<HashMap<&String, &VarId>>()
-            .get(name)
-            .expect("Variable name not found in scope");
-        (id, self.var_map[&id].typ.clone())
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .ok_or_else(|| Diagnostic::error(
+                ErrorCode::UnknownVariable,
+                format!("cannot find variable `{}` in this scope", name),
+                span,
+            ))?;
+        Ok((*id, self.var_map[id].typ.clone()))
     }
 }
- 
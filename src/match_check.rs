@@ -0,0 +1,190 @@
+use crate::stages::hir::*;
+use crate::shared::typing::*;
+use crate::shared::tables::*;
+
+
+// Exhaustiveness and overlap of match arms, by asking whether a pattern matches anything the ones
+// before it don't: the usefulness algorithm from Maranget's "Warnings for pattern matching".
+// Patterns become rows of constructors applied to subpatterns, one column per value still to look at.
+pub struct MatchChecker<'a> {
+    typetable: &'a GenericTypetable,
+}
+
+// Bindings match anything, so they're wildcards here
+#[derive(Clone, Debug, PartialEq)]
+enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Ctor {
+    Variant(usize),     // Its one subpattern is the payload's
+    Struct,             // One subpattern per field, in the type's order
+    Int(i32),
+    Bool(bool),
+}
+
+impl<'a> MatchChecker<'a> {
+
+    pub fn new(typetable: &'a GenericTypetable) -> Self {
+        MatchChecker { typetable }
+    }
+
+    // Whether the pattern matches anything none of the earlier ones do
+    pub fn is_useful(&self, earlier: &[&HIRPattern], pattern: &HIRPattern) -> bool {
+        let rows: Vec<Vec<Pat>> = earlier.iter().map(|pat| vec![lower(pat)]).collect();
+        self.find_useful(&rows, &[lower(pattern)], std::slice::from_ref(&pattern.typ)).is_some()
+    }
+
+    // A value of the type that none of the arms match, shown as a pattern
+    pub fn missing(&self, arms: &[&HIRPattern], typ: &ConcreteType) -> Option<String> {
+        let rows: Vec<Vec<Pat>> = arms.iter().map(|pat| vec![lower(pat)]).collect();
+        let witness = self.find_useful(&rows, &[Pat::Wild], std::slice::from_ref(typ))?;
+        Some(self.show(&witness[0], typ))
+    }
+
+    pub fn show_pattern(&self, pattern: &HIRPattern) -> String {
+        self.show(&lower(pattern), &pattern.typ)
+    }
+
+    // The values of `row` no earlier row matches, if any, as a row of patterns matching one of them
+    fn find_useful(&self, rows: &[Vec<Pat>], row: &[Pat], types: &[ConcreteType]) -> Option<Vec<Pat>> {
+        let Some((head, rest)) = row.split_first() else {
+            return rows.is_empty().then(Vec::new);
+        };
+        let typ = &types[0];
+        match head {
+            Pat::Ctor(ctor, subpats) => {
+                let specialized = [subpats.as_slice(), rest].concat();
+                self.find_useful_under(rows, ctor, &specialized, types)
+            }
+            Pat::Wild => {
+                let mut seen: Vec<&Ctor> = Vec::new();
+                for other in rows {
+                    if let Pat::Ctor(ctor, _) = &other[0] && !seen.contains(&ctor) {
+                        seen.push(ctor);
+                    }
+                }
+                let all = self.constructors(typ);
+                // Seeing every constructor, the wildcard only matters if it does for one of them
+                if let Some(all) = &all && all.iter().all(|ctor| seen.contains(&ctor)) {
+                    return all.iter().find_map(|ctor| {
+                        let wilds = vec![Pat::Wild; self.arity(ctor, typ)];
+                        self.find_useful_under(rows, ctor, &[wilds.as_slice(), rest].concat(), types)
+                    });
+                }
+                // Otherwise it matches a constructor none of them start with, if only the rest does
+                let defaults: Vec<Vec<Pat>> = rows
+                    .iter()
+                    .filter(|other| other[0] == Pat::Wild)
+                    .map(|other| other[1..].to_vec())
+                    .collect();
+                let witness = self.find_useful(&defaults, rest, &types[1..])?;
+                let unseen = all
+                    .filter(|_| !seen.is_empty())
+                    .and_then(|all| all.into_iter().find(|ctor| !seen.contains(&ctor)));
+                let head = match unseen {
+                    Some(ctor) => Pat::Ctor(ctor.clone(), vec![Pat::Wild; self.arity(&ctor, typ)]),
+                    None => Pat::Wild,
+                };
+                Some([vec![head], witness].concat())
+            }
+        }
+    }
+
+    // Only the rows starting with `ctor` (or a wildcard) can match where `row` does, once its
+    // subpatterns replace it at the front
+    fn find_useful_under(&self, rows: &[Vec<Pat>], ctor: &Ctor, row: &[Pat], types: &[ConcreteType]) -> Option<Vec<Pat>> {
+        let arity = self.arity(ctor, &types[0]);
+        let specialized: Vec<Vec<Pat>> = rows
+            .iter()
+            .filter_map(|other| match &other[0] {
+                Pat::Ctor(other_ctor, subpats) if other_ctor == ctor => Some([subpats.as_slice(), &other[1..]].concat()),
+                Pat::Ctor(..) => None,
+                Pat::Wild => Some([vec![Pat::Wild; arity], other[1..].to_vec()].concat()),
+            })
+            .collect();
+        let types = [self.subtypes(ctor, &types[0]), types[1..].to_vec()].concat();
+        let mut witness = self.find_useful(&specialized, row, &types)?;
+        let rest = witness.split_off(arity);
+        Some([vec![Pat::Ctor(ctor.clone(), witness)], rest].concat())
+    }
+
+    // All a value of the type can start with, unless there are too many to list
+    fn constructors(&self, typ: &ConcreteType) -> Option<Vec<Ctor>> {
+        match typ {
+            ConcreteType::Prim(PrimType::Bool) => Some(vec![Ctor::Bool(false), Ctor::Bool(true)]),
+            ConcreteType::NewType(id, tvars) => match self.typetable.get_mono(id.clone(), tvars.clone()) {
+                ConcreteShape::Enum { variants } => Some((0..variants.len()).map(Ctor::Variant).collect()),
+                ConcreteShape::Struct { .. } => Some(vec![Ctor::Struct]),
+            },
+            ConcreteType::Prim(_) | ConcreteType::Reference(_) => None,
+        }
+    }
+
+    fn arity(&self, ctor: &Ctor, typ: &ConcreteType) -> usize {
+        self.subtypes(ctor, typ).len()
+    }
+
+    fn subtypes(&self, ctor: &Ctor, typ: &ConcreteType) -> Vec<ConcreteType> {
+        match (ctor, typ) {
+            (Ctor::Variant(i), ConcreteType::NewType(id, tvars)) => match self.typetable.get_mono(id.clone(), tvars.clone()) {
+                ConcreteShape::Enum { variants } => vec![variants[*i].clone()],
+                ConcreteShape::Struct { .. } => unreachable!(),
+            },
+            (Ctor::Struct, ConcreteType::NewType(id, tvars)) => match self.typetable.get_mono(id.clone(), tvars.clone()) {
+                ConcreteShape::Struct { fields } => fields.into_values().collect(),
+                ConcreteShape::Enum { .. } => unreachable!(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    // In the syntax it'd be written in, with a bare type for a struct of all wildcards
+    fn show(&self, pat: &Pat, typ: &ConcreteType) -> String {
+        match pat {
+            Pat::Wild => "_".to_string(),
+            Pat::Ctor(Ctor::Int(num), _) => num.to_string(),
+            Pat::Ctor(Ctor::Bool(b), _) => b.to_string(),
+            Pat::Ctor(Ctor::Variant(i), payload) => {
+                let variant = &self.subtypes(&Ctor::Variant(*i), typ)[0];
+                match &payload[0] {
+                    Pat::Wild => variant.to_string(),
+                    payload => self.show(payload, variant),
+                }
+            }
+            Pat::Ctor(Ctor::Struct, subpats) => {
+                if subpats.iter().all(|sub| *sub == Pat::Wild) {
+                    return typ.to_string();
+                }
+                let ConcreteType::NewType(id, tvars) = typ else { unreachable!() };
+                let ConcreteShape::Struct { fields } = self.typetable.get_mono(id.clone(), tvars.clone()) else {
+                    unreachable!()
+                };
+                if id.enum_of().is_some() {
+                    let shown: Vec<String> = subpats.iter().zip(fields.values()).map(|(sub, ftyp)| self.show(sub, ftyp)).collect();
+                    return format!("{}({})", typ, shown.join(", "));
+                }
+                let shown: Vec<String> = subpats
+                    .iter()
+                    .zip(&fields)
+                    .map(|(sub, (fname, ftyp))| format!("{}: {}", fname, self.show(sub, ftyp)))
+                    .collect();
+                format!("{} {{ {} }}", typ, shown.join(", "))
+            }
+        }
+    }
+}
+
+
+fn lower(pattern: &HIRPattern) -> Pat {
+    match &pattern.pat {
+        HIRPatternKind::Wildcard | HIRPatternKind::Binding(_) => Pat::Wild,
+        HIRPatternKind::IntLiteral(num) => Pat::Ctor(Ctor::Int(*num), Vec::new()),
+        HIRPatternKind::BoolTrue => Pat::Ctor(Ctor::Bool(true), Vec::new()),
+        HIRPatternKind::BoolFalse => Pat::Ctor(Ctor::Bool(false), Vec::new()),
+        HIRPatternKind::Variant { variant, payload } => Pat::Ctor(Ctor::Variant(*variant), vec![lower(payload)]),
+        HIRPatternKind::Struct { fields } => Pat::Ctor(Ctor::Struct, fields.iter().map(|(_, sub)| lower(sub)).collect()),
+    }
+}
@@ -1,129 +1,237 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use crate::stages::common::*;
 use crate::stages::lir::*;
 use crate::stages::mir::*;
 use crate::shared::typing::*;
 use crate::shared::tables::*;
+use crate::shared::span::Span;
+use crate::shared::diagnostics::*;
+use crate::shared::target::TargetInfo;
+
+
+// Where a cell's value is kept
+#[derive(Clone, Copy, Debug)]
+enum CellHome {
+    Chunk(ChunkId),
+    VReg(VRegId),
+}
 
 
 pub struct LIRBuilder {
-    cell_chunk_map: HashMap<CellId, (ChunkId, ConcreteType)>,
-    curr_chunks: HashMap<ChunkId, Chunk>,
-    curr_vregs: Vec<VRegId>,
+    cell_chunk_map: HashMap<CellId, (CellHome, ConcreteType)>,
+    curr_chunks: BTreeMap<ChunkId, Chunk>,
+    curr_vregs: BTreeMap<VRegId, Chunk>,
+    curr_ret_slot: Option<ChunkId>,     // Holds the pointer to the caller's return slot, if it passes one
     layouts: LayoutTable,
     chunk_counter: usize,
+    block_counter: usize,
     typetable: GenericTypetable,
 }
 
 impl LIRBuilder {
     
-    pub fn lower_mir(program: MIRProgram) -> LIRProgram {
-        let layouts = LayoutTable::make(program.typetable.clone());
+    pub fn lower_mir(program: MIRProgram, target: TargetInfo) -> CompileResult<LIRProgram> {
+        let layouts = LayoutTable::make(program.typetable.clone(), target);
+        // Labels are global, so new blocks have to come after every MIR one
+        let block_counter = program.functions
+            .values()
+            .filter_map(|func| func.blocks.keys().next_back())
+            .map(|id| id.0 + 1)
+            .max()
+            .unwrap_or(0);
         let mut builder = LIRBuilder {
             cell_chunk_map: HashMap::new(),
-            curr_chunks: HashMap::new(),
-            curr_vregs: Vec::new(),
+            curr_chunks: BTreeMap::new(),
+            curr_vregs: BTreeMap::new(),
+            curr_ret_slot: None,
             layouts,
             chunk_counter: 0,
+            block_counter,
             typetable: program.typetable
         };
-        LIRProgram {
-            functions: program.functions
-.into_iter()
-                .map(|(id, func)| (id, builder.lower_function(func)))
-                .collect(),
-            entry: program.entry
+        let exit_code = program.functions
+            .get(&program.entry)
+            .is_some_and(|func| matches!(func.ret_type, ConcreteType::Prim(PrimType::Integer | PrimType::Bool)));
+        let mut functions = BTreeMap::new();
+        for (id, func) in program.functions {
+            functions.insert(id, builder.lower_function(func)?);
         }
+        Ok(LIRProgram {
+            functions,
+            entry: program.entry,
+            target,
+            exit_code,
+        })
     }
 
-    fn lower_function(&mut self, func: MIRFunction) -> LIRFunction {
+    fn lower_function(&mut self, func: MIRFunction) -> CompileResult<LIRFunction> {
         self.cell_chunk_map = HashMap::new();
-        self.curr_vregs = Vec::new();
+        self.curr_chunks = BTreeMap::new();
+        self.curr_vregs = BTreeMap::new();
+        let in_memory = cells_in_memory(&func);
+        let ret_size = self.layouts.get_layout(func.ret_type).size();
+        let pointer_chunk = self.layouts.pointer_chunk();
+        let mut params = Vec::new();
+        // The return slot pointer comes in ahead of the args
+        self.curr_ret_slot = self.layouts.target.returns_in_slot(ret_size).then(|| self.add_chunk(pointer_chunk.clone()));
+        if let Some(ret_slot) = self.curr_ret_slot {
+            params.push(LIRPlace { size: pointer_chunk.size, place: LIRPlaceKind::Local { base: ret_slot, offset: 0 } });
+        }
         for (id, cell) in func.cells {
-            self.lower_cell(id, cell);
+            if func.args.contains(&id) && (in_memory.contains(&id) || !is_scalar(&cell.typ)) {
+                // The prologue can put it straight into its cell
+                let layout = self.layouts.get_layout(cell.typ.clone());
+                let chunk = self.add_chunk(self.word_chunk(&layout));
+                self.cell_chunk_map.insert(id, (CellHome::Chunk(chunk), cell.typ));
+            } else {
+                let in_memory = in_memory.contains(&id);
+                self.lower_cell(id, cell, in_memory);
+            }
+        }
+        let mut unpacked = Vec::new();
+        for arg in &func.args {
+            let (home, typ) = self.cell_chunk_map[arg].clone();
+            let layout = self.layouts.get_layout(typ);
+            let param = match home {
+                CellHome::Chunk(chunk) => chunk,
+                // Vregs only get their registers in the body, so the arg is copied over there
+                CellHome::VReg(_) => {
+                    let chunk = self.add_chunk(self.word_chunk(&layout));
+                    unpacked.push((*arg, chunk));
+                    chunk
+                }
+            };
+            params.push(LIRPlace { size: layout.size(), place: LIRPlaceKind::Local { base: param, offset: 0 } });
         }
-        LIRFunction {
-            blocks: func.blocks
-                .into_iter()
-                .map(|(id, block)| (id, self.lower_block(block)))
-                .collect(),
-            entry: func.entry,
-            chunks: self.curr_chunks.clone(), 
-            args: func.args
-                .into_iter()
-                .map(|cell_id| self.cell_chunk_map[&cell_id].0.clone())
-                .collect()
+
+        let mut blocks = BTreeMap::new();
+        for (id, block) in func.blocks {
+            blocks.insert(id, self.lower_block(block)?);
+        }
+        let entry = BlockId(self.block_counter);
+        self.block_counter += 1;
+        blocks.insert(entry, self.lower_arg_unpacking(unpacked, func.entry)?);
+
+        Ok(LIRFunction {
+            blocks,
+            entry,
+            chunks: std::mem::take(&mut self.curr_chunks), 
+            vregs: std::mem::take(&mut self.curr_vregs),
+            params,
+            ret_size,
+        })
+    }
+
+    // The block a function starts with: it copies the args kept in vregs out of their params
+    fn lower_arg_unpacking(&self, unpacked: Vec<(CellId, ChunkId)>, body: BlockId) -> CompileResult<LIRBlock> {
+        let mut statements = Vec::new();
+        for (cell_id, param) in unpacked {
+            let typ = self.cell_chunk_map[&cell_id].1.clone();
+            let size = self.layouts.get_layout(typ.clone()).size();
+            let cell_place = self.lower_place(MIRPlace { typ, base: MIRPlaceBase::Cell(cell_id), fieldchain: Vec::new() }, Span::default())?;
+            statements.push(LIRStatement::Store {
+                dest: cell_place,
+                value: LIRValue {
+                    size,
+                    value: LIRValueKind::Place(LIRPlace { size, place: LIRPlaceKind::Local { base: param, offset: 0 } }),
+                },
+            });
         }
+        Ok(LIRBlock { statements, terminator: LIRTerminator::Goto { dest: body } })
     }
 
-    fn lower_block(&mut self, block: MIRBlock) -> LIRBlock {
+    fn lower_block(&mut self, block: MIRBlock) -> CompileResult<LIRBlock> {
         let mut statements: Vec<LIRStatement> = Vec::new();
+        // Terminators carry no span of their own, so errors there point at the block's last statement
+        let mut last_span = Span::default();
         for stmt in block.statements {
-            let lowered = self.lower_stmt(stmt);
-            statements.extend(lowered.into_iter());
+            last_span = stmt.span;
+            let lowered = self.lower_stmt(stmt)?;
+            statements.extend(lowered);
         }
-        let (terminator, term_stmts) = self.lower_terminator(block.terminator);
-        statements.extend(term_stmts.into_iter());
-        LIRBlock { statements, terminator }
+        let (terminator, term_stmts) = self.lower_terminator(block.terminator, last_span)?;
+        statements.extend(term_stmts);
+        Ok(LIRBlock { statements, terminator })
     }
 
-    fn lower_stmt(&mut self, stmt: MIRStatement) -> Vec<LIRStatement> {
-        match stmt {
-            MIRStatement::Assign { target, value } => {
-                let lir_target = self.lower_place(target);
-                self.lower_value_into_place(value, lir_target)
+    fn lower_stmt(&mut self, stmt: MIRStatement) -> CompileResult<Vec<LIRStatement>> {
+        let span = stmt.span;
+        let lowered = match stmt.stmt {
+            MIRStatementKind::Assign { target, value } => {
+                let lir_target = self.lower_place(target, span)?;
+                self.lower_value_into_place(value, lir_target, span)?
             }
-            MIRStatement::BinOp { target, op, left, right } => {
-                let lir_target = self.lower_place(target);
-                let (left_opnd, left_stmts) = self.lower_value_into_operand(left);
-                let (right_opnd, right_stmts) = self.lower_value_into_operand(right);
+            MIRStatementKind::BinOp { target, op, left, right } => {
+                let lir_target = self.lower_place(target, span)?;
+                let (left_opnd, left_stmts) = self.lower_value_into_operand(left, span)?;
+                let (right_opnd, right_stmts) = self.lower_value_into_operand(right, span)?;
                 let bin_stmt = LIRStatement::BinOp { 
                     dest: lir_target, 
-                    op: op, 
+                    op, 
                     left: left_opnd, 
                     right: right_opnd 
                 };
                 [left_stmts, right_stmts, vec![bin_stmt]].concat()
             }
-            MIRStatement::Call { target, func, args } => {
-                let lir_target = self.lower_place(target);
-                let mut arg_places: Vec<LIRPlace> = Vec::new();
-                let mut arg_stmts_coll: Vec<LIRStatement> = Vec::new();
+            MIRStatementKind::Call { target, func, args } => {
+                let lir_target = self.lower_place(target.clone(), span)?;
+                let pointer_size = self.layouts.pointer_chunk().size;
+                let ret_layout = self.layouts.get_layout(target.typ.clone());
+                let mut stmts: Vec<LIRStatement> = Vec::new();
+                let mut lir_args: Vec<LIRValue> = Vec::new();
+
+                // The callee writes into a slot of our own rather than straight into the target,
+                // which it might still be reading through a reference while it builds the value
+                let ret_slot = self.layouts.target.returns_in_slot(ret_layout.size()).then(|| LIRPlace {
+                    size: ret_layout.size(),
+                    place: LIRPlaceKind::Local { base: self.add_chunk(ret_layout.chunk()), offset: 0 },
+                });
+                if let Some(slot) = &ret_slot {
+                    lir_args.push(LIRValue { size: pointer_size, value: LIRValueKind::Reference(slot.clone()) });
+                }
 
                 for arg in args {
-                    let arg_size = self.layouts.get_layout(arg.typ.clone()).size();
-                    let arg_chunk = self.add_chunk(Chunk { size: arg_size});
-                    let arg_place = LIRPlace {
-                        size: arg_size, 
-                        place: LIRPlaceKind::Local { 
-                            base: arg_chunk, 
-                            offset: 0, 
-                        }
-                    };
-                    arg_places.push(arg_place.clone());
-                    let arg_stmts = self.lower_value_into_place(arg, arg_place);
-                    arg_stmts_coll.extend(arg_stmts.into_iter());
+                    if is_scalar(&arg.typ) {
+                        let (arg_opnd, arg_stmts) = self.lower_value_into_operand(arg, span)?;
+                        stmts.extend(arg_stmts);
+                        lir_args.push(arg_opnd);
+                    } else {
+                        let temp = self.add_word_temp(arg.typ.clone());
+                        stmts.extend(self.lower_value_into_place(arg, temp.clone(), span)?);
+                        lir_args.push(LIRValue { size: temp.size, value: LIRValueKind::Place(temp) });
+                    }
                 }
-                let lir_call = LIRStatement::Call { 
-                    dest: lir_target, 
-                    func, 
-                    args: arg_places
+
+                // Anything that doesn't come straight into the target gets copied there after
+                let (dest, result) = match ret_slot {
+                    Some(slot) => (None, Some(slot)),
+                    None if is_scalar(&target.typ) => (Some(lir_target.clone()), None),
+                    None => {
+                        let temp = self.add_word_temp(target.typ);
+                        (Some(temp.clone()), Some(temp))
+                    }
                 };
-                [arg_stmts_coll, vec![lir_call]].concat()
+                stmts.push(LIRStatement::Call { dest, func, args: lir_args });
+                if let Some(result) = result {
+                    let size = result.size;
+                    stmts.push(LIRStatement::Store { dest: lir_target, value: LIRValue { size, value: LIRValueKind::Place(result) } });
+                }
+                stmts
             }
-            MIRStatement::Print(value) => {
-                let (opnd, stmts) = self.lower_value_into_operand(value);
+            MIRStatementKind::Print(value) => {
+                let (opnd, stmts) = self.lower_value_into_operand(value, span)?;
                 [stmts, vec![LIRStatement::Print(opnd)]].concat()
             }
-        }
+        };
+        Ok(lowered)
     }
 
-    fn lower_terminator(&mut self, term: MIRTerminator) -> (LIRTerminator, Vec<LIRStatement>) {
-        match term {
+    fn lower_terminator(&mut self, term: MIRTerminator, span: Span) -> CompileResult<(LIRTerminator, Vec<LIRStatement>)> {
+        let lowered = match term {
             MIRTerminator::Goto(block_id) => (LIRTerminator::Goto { dest: block_id }, Vec::new()),
             MIRTerminator::Branch { condition, then_, else_ } => {
-                let (cond_op, cond_stmts) = self.lower_value_into_operand(condition);
+                let (cond_op, cond_stmts) = self.lower_value_into_operand(condition, span)?;
                 let term = LIRTerminator::Branch { 
                     condition: cond_op, 
                     then_block: then_, 
@@ -134,20 +242,38 @@ impl LIRBuilder {
             MIRTerminator::Return(ret_val) => {
                 match ret_val {
                     None => (LIRTerminator::Return(None), Vec::new()),
-                    Some(value) => {
-                        let (retval_op, retval_stmts) = self.lower_value_into_operand(value);
-                        (LIRTerminator::Return(Some(retval_op)), retval_stmts)
-                    }
+                    Some(value) => match self.curr_ret_slot {
+                        Some(ret_slot) => {
+                            let size = self.layouts.get_layout(value.typ.clone()).size();
+                            let slot_place = LIRPlace { size, place: LIRPlaceKind::Deref { pointer: ret_slot, offset: 0 } };
+                            let stmts = self.lower_value_into_place(value, slot_place, span)?;
+                            // Handing the slot pointer back is what System V callers expect
+                            let pointer_size = self.layouts.pointer_chunk().size;
+                            let pointer = LIRPlace { size: pointer_size, place: LIRPlaceKind::Local { base: ret_slot, offset: 0 } };
+                            (LIRTerminator::Return(Some(LIRValue { size: pointer_size, value: LIRValueKind::Place(pointer) })), stmts)
+                        }
+                        None if is_scalar(&value.typ) => {
+                            let (retval_op, retval_stmts) = self.lower_value_into_operand(value, span)?;
+                            (LIRTerminator::Return(Some(retval_op)), retval_stmts)
+                        }
+                        None => {
+                            let temp = self.add_word_temp(value.typ.clone());
+                            let stmts = self.lower_value_into_place(value, temp.clone(), span)?;
+                            let size = temp.size;
+                            (LIRTerminator::Return(Some(LIRValue { size, value: LIRValueKind::Place(temp) })), stmts)
+                        }
+                    },
                 }
             }
-        }
+        };
+        Ok(lowered)
     }
 
-    fn lower_value_into_operand(&mut self, value: MIRValue) -> (LIRValue, Vec<LIRStatement>) {
+    fn lower_value_into_operand(&mut self, value: MIRValue, span: Span) -> CompileResult<(LIRValue, Vec<LIRStatement>)> {
         let size = self.layouts.get_layout(value.typ.clone()).size();
-        match value.value {
+        let lowered = match value.value {
             MIRValueKind::Place(val_place) => {
-                let lir_val_place = self.lower_place(val_place);
+                let lir_val_place = self.lower_place(val_place, span)?;
                 (LIRValue {size, value: LIRValueKind::Place(lir_val_place)}, Vec::new())
             },
             MIRValueKind::IntLiteral(num) => {
@@ -159,32 +285,31 @@ impl LIRBuilder {
             MIRValueKind::BoolFalse => {
                 (LIRValue {size, value: LIRValueKind::BoolFalse}, Vec::new())
             }
-            MIRValueKind::StructLiteral {..} => {
-                let temp_chunk = Chunk {
-                    size: self.layouts.get_layout(value.typ.clone()).size(),
-                };
+            MIRValueKind::StructLiteral {..} | MIRValueKind::EnumLiteral {..} => {
+                let temp_chunk = self.layouts.get_layout(value.typ.clone()).chunk();
                 let temp_id = self.add_chunk(temp_chunk);
                 let temp_place = LIRPlace {
-                    size: size,
+                    size,
                     place: LIRPlaceKind::Local { base: temp_id, offset: 0}
                 };
 
                 // Mehh. Maybe add type info back to MIRV?
-                let stmts = self.lower_value_into_place(value, temp_place.clone());
+                let stmts = self.lower_value_into_place(value, temp_place.clone(), span)?;
                 (LIRValue{ size, value: LIRValueKind::Place(temp_place)}, stmts)
             }
             MIRValueKind::Reference(refd) => {
-                let refd_place = self.lower_place(refd);
+                let refd_place = self.lower_place(refd, span)?;
                 (LIRValue {size, value: LIRValueKind::Reference(refd_place)}, vec![]) 
             }
-        }
+        };
+        Ok(lowered)
     }
 
-    fn lower_value_into_place(&self, value: MIRValue, target: LIRPlace) -> Vec<LIRStatement> {
+    fn lower_value_into_place(&self, value: MIRValue, target: LIRPlace, span: Span) -> CompileResult<Vec<LIRStatement>> {
         let size = self.layouts.get_layout(value.typ.clone()).size();
-        match value.value {
+        let lowered = match value.value {
             MIRValueKind::Place(val_place) => {
-                let lir_val_place = self.lower_place(val_place);
+                let lir_val_place = self.lower_place(val_place, span)?;
                 vec![LIRStatement::Store{dest: target, value: LIRValue { size, value: LIRValueKind::Place(lir_val_place)}}] 
             },
             MIRValueKind::IntLiteral(num) => {
@@ -197,8 +322,8 @@ impl LIRBuilder {
                 vec![LIRStatement::Store{dest: target, value: LIRValue { size, value: LIRValueKind::BoolFalse}}]
             }
             MIRValueKind::StructLiteral { typ, fields } => {
-                let LayoutInfo::Struct { size: _, field_offsets } = self.layouts.get_layout(typ) else {
-                    unreachable!();
+                let LayoutInfo::Struct { field_offsets, .. } = self.layouts.get_layout(typ.clone()) else {
+                    return Err(Diagnostic::bug(format!("struct literal of non-struct type `{}`", typ), span).into());
                 };
                 let mut stmts: Vec<LIRStatement> = Vec::new();
                 for (fname, fexpr) in fields {
@@ -207,85 +332,162 @@ impl LIRBuilder {
                         size: fsize,
                         place: increment_place_offset(target.place.clone(), field_offsets[&fname]),
                     };
-                    stmts.extend(self.lower_value_into_place(fexpr, f_target));
+                    stmts.extend(self.lower_value_into_place(fexpr, f_target, span)?);
                 }
                 stmts
             }
+            MIRValueKind::EnumLiteral { typ, variant, payload } => {
+                let LayoutInfo::Enum { payload_offset, .. } = self.layouts.get_layout(typ.clone()) else {
+                    return Err(Diagnostic::bug(format!("enum literal of non-enum type `{}`", typ), span).into());
+                };
+                let tag_size = self.layouts.get_layout(ConcreteType::Prim(PrimType::Integer)).size();
+                let tag = LIRStatement::Store {
+                    dest: LIRPlace { size: tag_size, place: target.place.clone() },
+                    value: LIRValue { size: tag_size, value: LIRValueKind::IntLiteral(variant as i32) },
+                };
+                let payload_target = LIRPlace {
+                    size: self.layouts.get_layout(payload.typ.clone()).size(),
+                    place: increment_place_offset(target.place, payload_offset),
+                };
+                [vec![tag], self.lower_value_into_place(*payload, payload_target, span)?].concat()
+            }
             MIRValueKind::Reference(refd) => {
-                let refd_place = self.lower_place(refd);
+                let refd_place = self.lower_place(refd, span)?;
                 let stmt = LIRStatement::Store { dest: target, value: LIRValue { size, value: LIRValueKind::Reference(refd_place)}}; 
                 vec![stmt]
             }
-        }
+        };
+        Ok(lowered)
     }
 
-    fn lower_place(&self, place: MIRPlace) -> LIRPlace {
+    fn lower_place(&self, place: MIRPlace, span: Span) -> CompileResult<LIRPlace> {
         // TODO: weird solution, change it
         let size = self.layouts.get_layout(place.typ).size();
-        match place.base {
+        let lowered = match place.base {
             MIRPlaceBase::Cell(c_id) => {
 
-                let base_type = self.cell_chunk_map[&c_id].1.clone();
-                let (final_offset, _) = self.lower_fieldchain(base_type, place.fieldchain);
-                LIRPlace {
-                    size,
-                    place: LIRPlaceKind::Local{
-                        base: self.cell_chunk_map[&c_id].0, 
-                        offset: final_offset 
-                    }
+                let (home, base_type) = self.cell_chunk_map[&c_id].clone();
+                let (final_offset, _) = self.lower_fieldchain(base_type, place.fieldchain, span)?;
+                match home {
+                    CellHome::Chunk(chunk) => LIRPlace {
+                        size,
+                        place: LIRPlaceKind::Local{
+                            base: chunk, 
+                            offset: final_offset 
+                        }
+                    },
+                    CellHome::VReg(vreg) => LIRPlace {
+                        size,
+                        place: LIRPlaceKind::VReg(vreg),
+                    },
                 }
             },
             MIRPlaceBase::Deref(c_id) => {
-                let ref_type = self.cell_chunk_map[&c_id].1.clone();
-                let ConcreteType::Reference(deref_type) = ref_type else {unreachable!()};
-                let (final_offset, _) = self.lower_fieldchain(*deref_type, place.fieldchain);
+                let (home, ref_type) = self.cell_chunk_map[&c_id].clone();
+                let CellHome::Chunk(pointer) = home else {
+                    return Err(Diagnostic::bug(format!("dereferenced cell {:?} isn't kept in a chunk", c_id), span).into());
+                };
+                let ConcreteType::Reference(deref_type) = ref_type else {
+                    return Err(Diagnostic::bug(format!("dereferenced cell has non-reference type `{}`", ref_type), span).into());
+                };
+                let (final_offset, _) = self.lower_fieldchain(*deref_type, place.fieldchain, span)?;
                 LIRPlace {
                     size,
                     place: LIRPlaceKind::Deref { 
-                        pointer: self.cell_chunk_map[&c_id].0, 
+                        pointer, 
                         offset: final_offset,
                     }
                 }
             }
-        }
+        };
+        Ok(lowered)
     }
 
     fn lower_fieldchain(
         &self, 
         base_type: ConcreteType, 
-        chain: Vec<String>
-    ) -> (usize, ConcreteType) {
+        chain: Vec<Projection>,
+        span: Span,
+    ) -> CompileResult<(usize, ConcreteType)> {
         let mut curr_typ = base_type;
         let mut curr_offset = 0;
         
-        for field in chain {
+        for proj in chain {
             let curr_typ_layout = self.layouts.get_layout(curr_typ.clone());
 
-            match curr_typ_layout {
-                LayoutInfo::Struct { size, field_offsets } => {
-                    let ConcreteType::NewType(id, tvars) = curr_typ else {panic!("Type in field chain not struct");};
-                    let ConcreteShape::Struct { fields } = self.typetable.get_mono(id, tvars) else {unreachable!()};
-                    curr_typ = fields[&field].clone();
-                    curr_offset = curr_offset + field_offsets[&field];
+            match (proj, curr_typ_layout) {
+                // The tag is at the start of the enum
+                (Projection::Tag, LayoutInfo::Enum { .. }) => {
+                    curr_typ = ConcreteType::Prim(PrimType::Integer);
+                }
+                (Projection::Payload(i), LayoutInfo::Enum { payload_offset, .. }) => {
+                    let ConcreteType::NewType(id, tvars) = curr_typ.clone() else {
+                        return Err(Diagnostic::bug(format!("enum layout for non-newtype `{}`", curr_typ), span).into());
+                    };
+                    let ConcreteShape::Enum { variants } = self.typetable.get_mono(id, tvars) else {
+                        return Err(Diagnostic::bug(format!("`{}` has an enum layout but isn't an enum", curr_typ), span).into());
+                    };
+                    let Some(vtyp) = variants.get(i) else {
+                        return Err(Diagnostic::bug(format!("variant {} missing from `{}`", i, curr_typ), span).into());
+                    };
+                    curr_typ = vtyp.clone();
+                    curr_offset += payload_offset;
+                }
+                (Projection::Field(field), LayoutInfo::Struct { field_offsets, .. }) => {
+                    let ConcreteType::NewType(id, tvars) = curr_typ.clone() else {
+                        return Err(Diagnostic::bug(format!("struct layout for non-newtype `{}`", curr_typ), span).into());
+                    };
+                    let ConcreteShape::Struct { fields } = self.typetable.get_mono(id, tvars) else {
+                        return Err(Diagnostic::bug(format!("`{}` has a struct layout but isn't a struct", curr_typ), span).into());
+                    };
+                    let (Some(ftyp), Some(foffset)) = (fields.get(&field), field_offsets.get(&field)) else {
+                        return Err(Diagnostic::bug(format!("field `{}` missing from `{}`", field, curr_typ), span).into());
+                    };
+                    curr_typ = ftyp.clone();
+                    curr_offset += foffset;
                 } 
-                LayoutInfo::Primitive(..) => {
-                    panic!("This is primitive, shouldn't have a field");
+                (proj, _) => {
+                    return Err(Diagnostic::bug(format!("{:?} of `{}`", proj, curr_typ), span).into());
                 }
             }
         }
-        (curr_offset, curr_typ)
+        Ok((curr_offset, curr_typ))
     }
     
-    fn lower_cell(&mut self, id: CellId, cell: Cell) {
+    fn lower_cell(&mut self, id: CellId, cell: Cell, in_memory: bool) {
         // TODO: This should lower into LIRPlace. I think?
-        let chunk = Chunk {size: self.layouts.get_layout(cell.typ.clone()).size()};
-        let chunk_id = self.add_chunk(chunk);
-        self.cell_chunk_map.insert(id, (chunk_id, cell.typ));
+        let chunk = self.layouts.get_layout(cell.typ.clone()).chunk();
+        let home = if in_memory || !is_scalar(&cell.typ) {
+            CellHome::Chunk(self.add_chunk(chunk))
+        } else {
+            CellHome::VReg(self.add_vreg(chunk))
+        };
+        self.cell_chunk_map.insert(id, (home, cell.typ));
+    }
+
+    // Rounded up to whole words, since calls move these a word at a time
+    fn word_chunk(&self, layout: &LayoutInfo) -> Chunk {
+        let word = self.layouts.target.word_size;
+        Chunk { size: layout.size().next_multiple_of(word), align: layout.align().max(word) }
+    }
+
+    fn add_word_temp(&mut self, typ: ConcreteType) -> LIRPlace {
+        let layout = self.layouts.get_layout(typ);
+        LIRPlace {
+            size: layout.size(),
+            place: LIRPlaceKind::Local { base: self.add_chunk(self.word_chunk(&layout)), offset: 0 },
+        }
+    }
+
+    fn add_vreg(&mut self, chunk: Chunk) -> VRegId {
+        let vreg_id = VRegId(self.curr_vregs.len());
+        self.curr_vregs.insert(vreg_id, chunk);
+        vreg_id
     }
 
     fn add_chunk(&mut self, chunk: Chunk) -> ChunkId {
         let chunk_id = ChunkId(self.chunk_counter);
-        self.chunk_counter = self.chunk_counter + 1;
+        self.chunk_counter += 1;
         self.curr_chunks.insert(chunk_id, chunk);
         chunk_id
     }
@@ -297,38 +499,125 @@ fn increment_place_offset(place: LIRPlaceKind, increment: usize) -> LIRPlaceKind
     match place {
         LIRPlaceKind::Local { base, offset } => LIRPlaceKind::Local { base, offset: offset + increment },
         LIRPlaceKind::Deref { pointer, offset } => LIRPlaceKind::Deref { pointer, offset: offset + increment},
+        LIRPlaceKind::VReg(_) => unreachable!("vregs only hold scalars"),
+    }
+}
+
+// Whatever fits in a register
+fn is_scalar(typ: &ConcreteType) -> bool {
+    matches!(typ, ConcreteType::Prim(_) | ConcreteType::Reference(_))
+}
+
+// Cells that have to stay in memory: ones something takes a reference to, and pointers that get
+// dereferenced (Deref places are based on a chunk)
+fn cells_in_memory(func: &MIRFunction) -> HashSet<CellId> {
+    let mut cells = HashSet::new();
+    for block in func.blocks.values() {
+        for stmt in &block.statements {
+            match &stmt.stmt {
+                MIRStatementKind::Assign { target, value } => {
+                    place_cells_in_memory(target, &mut cells);
+                    value_cells_in_memory(value, &mut cells);
+                }
+                MIRStatementKind::BinOp { target, left, right, .. } => {
+                    place_cells_in_memory(target, &mut cells);
+                    value_cells_in_memory(left, &mut cells);
+                    value_cells_in_memory(right, &mut cells);
+                }
+                MIRStatementKind::Call { target, args, .. } => {
+                    place_cells_in_memory(target, &mut cells);
+                    for arg in args {
+                        value_cells_in_memory(arg, &mut cells);
+                    }
+                }
+                MIRStatementKind::Print(value) => value_cells_in_memory(value, &mut cells),
+            }
+        }
+        match &block.terminator {
+            MIRTerminator::Branch { condition, .. } => value_cells_in_memory(condition, &mut cells),
+            MIRTerminator::Return(Some(value)) => value_cells_in_memory(value, &mut cells),
+            MIRTerminator::Goto(_) | MIRTerminator::Return(None) => {}
+        }
+    }
+    cells
+}
+
+fn value_cells_in_memory(value: &MIRValue, cells: &mut HashSet<CellId>) {
+    match &value.value {
+        MIRValueKind::Place(place) => place_cells_in_memory(place, cells),
+        MIRValueKind::Reference(place) => {
+            match place.base {
+                MIRPlaceBase::Cell(cell) | MIRPlaceBase::Deref(cell) => cells.insert(cell),
+            };
+        }
+        MIRValueKind::StructLiteral { fields, .. } => {
+            for (_, fvalue) in fields {
+                value_cells_in_memory(fvalue, cells);
+            }
+        }
+        MIRValueKind::EnumLiteral { payload, .. } => value_cells_in_memory(payload, cells),
+        MIRValueKind::IntLiteral(_) | MIRValueKind::BoolTrue | MIRValueKind::BoolFalse => {}
+    }
+}
+
+fn place_cells_in_memory(place: &MIRPlace, cells: &mut HashSet<CellId>) {
+    if let MIRPlaceBase::Deref(cell) = place.base {
+        cells.insert(cell);
     }
 }
 
 
 #[derive(Clone, Debug)]
 pub enum LayoutInfo {
-    Primitive(usize),               // Variable size
-    Struct {
+    Primitive {
         size: usize,
+        align: usize,
+    },
+    Struct {
+        size: usize,                // Includes the tail padding, so it's a multiple of align
+        align: usize,
         field_offsets: HashMap<String, usize>
-    }
+    },
+    Enum {
+        size: usize,
+        align: usize,
+        payload_offset: usize,      // The tag, an int, is at 0
+    },
 }
 
 impl LayoutInfo {
     pub fn size(&self) -> usize {
-        match self {
-            &LayoutInfo::Primitive(size) => size,
-            &LayoutInfo::Struct{size, ..} => size,
+        match *self {
+            LayoutInfo::Primitive{size, ..} => size,
+            LayoutInfo::Struct{size, ..} => size,
+            LayoutInfo::Enum{size, ..} => size,
+        }
+    }
+
+    pub fn align(&self) -> usize {
+        match *self {
+            LayoutInfo::Primitive{align, ..} => align,
+            LayoutInfo::Struct{align, ..} => align,
+            LayoutInfo::Enum{align, ..} => align,
         }
     }
+
+    pub fn chunk(&self) -> Chunk {
+        Chunk { size: self.size(), align: self.align() }
+    }
 }
 
 
 #[derive(Clone, Debug)]
 pub struct LayoutTable {
-    newtype_layouts: HashMap<ConcreteType, LayoutInfo>
+    newtype_layouts: HashMap<ConcreteType, LayoutInfo>,
+    target: TargetInfo,
 }
 
 impl LayoutTable {
 
-    pub fn make(typetable: GenericTypetable) -> LayoutTable {
-        let mut table = LayoutTable{newtype_layouts: HashMap::new()};
+    pub fn make(typetable: GenericTypetable, target: TargetInfo) -> LayoutTable {
+        let mut table = LayoutTable{newtype_layouts: HashMap::new(), target};
         for (id, tvars, shape) in typetable.topo_mono_iter() {
             table.newtype_layouts
                 .insert(ConcreteType::NewType(id, tvars), table.lay_out_newtype(shape));
@@ -336,36 +625,65 @@ impl LayoutTable {
         table
     }   
 
+    pub fn pointer_chunk(&self) -> Chunk {
+        Chunk { size: self.target.pointer_size, align: self.target.pointer_align }
+    }
+
     pub fn get_layout(&self, typ: ConcreteType) -> LayoutInfo {
         match typ {
             ConcreteType::Prim(prim_tp) => self.get_primitive_layout(prim_tp),
             ConcreteType::NewType(..) => self.newtype_layouts[&typ].clone(),
-            ConcreteType::Reference(..) => LayoutInfo::Primitive(8)
+            ConcreteType::Reference(..) => LayoutInfo::Primitive {
+                size: self.target.pointer_size,
+                align: self.target.pointer_align,
+            },
         }
     }
 
-    fn get_primitive_layout(&self, _prim_tp: PrimType) -> LayoutInfo {
-        LayoutInfo::Primitive(8)        // Temporarily so; update later
+    fn get_primitive_layout(&self, prim_tp: PrimType) -> LayoutInfo {
+        match prim_tp {
+            PrimType::Integer => LayoutInfo::Primitive { size: self.target.int_size, align: self.target.int_align },
+            PrimType::Bool => LayoutInfo::Primitive { size: self.target.bool_size, align: self.target.bool_align },
+            PrimType::None => LayoutInfo::Primitive { size: 0, align: 1 },
+        }
     }
     
     fn lay_out_newtype(&self, shape: ConcreteShape) -> LayoutInfo {
         
         match shape {
+            // Same as a C compiler would do it: fields in declaration (here name) order, each at the
+            // next offset its alignment allows, and the whole thing as aligned as its strictest field
             ConcreteShape::Struct { fields } => {
                 let mut f_offsets: HashMap<String, usize> = HashMap::new();
-                let mut curr_offset = 0;
+                let mut curr_offset: usize = 0;
+                let mut align = 1;
                 for (fname, ftype) in fields {
+                    let flayout = self.get_layout(ftype);
+                    curr_offset = curr_offset.next_multiple_of(flayout.align());
                     f_offsets.insert(fname, curr_offset);
-                    let fsize = self.get_layout(ftype).size(); 
-                    curr_offset = curr_offset + fsize;
+                    curr_offset += flayout.size();
+                    align = align.max(flayout.align());
                 }
                 LayoutInfo::Struct { 
-                    size: curr_offset, 
+                    size: curr_offset.next_multiple_of(align), 
+                    align,
                     field_offsets: f_offsets 
                 }
             }
-            ConcreteShape::Enum { variants: _} => {
-                unimplemented!();
+            // Like a C struct of an int tag and a union of the variants: the payload goes where
+            // the strictest variant can go, and is as big as the biggest one
+            ConcreteShape::Enum { variants } => {
+                let tag = self.get_primitive_layout(PrimType::Integer);
+                let variant_layouts: Vec<LayoutInfo> = variants.into_iter().map(|v| self.get_layout(v)).collect();
+                let payload_align = variant_layouts.iter().map(LayoutInfo::align).max().unwrap_or(1);
+                let payload_size = variant_layouts.iter().map(LayoutInfo::size).max().unwrap_or(0);
+                let payload_offset = tag.size().next_multiple_of(payload_align);
+                let align = tag.align().max(payload_align);
+                LayoutInfo::Enum {
+                    size: (payload_offset + payload_size).next_multiple_of(align),
+                    align,
+                    payload_offset,
+                }
             }
         }
     }
@@ -7,461 +7,783 @@ use crate::shared::typing::*;
 use crate::stages::ast::*;
 use crate::shared::tables::*;
 use crate::shared::utils::*;
+use crate::shared::span::Span;
+use crate::shared::diagnostics::*;
 
 pub struct Parser {
-    tokens: Peekable<std::vec::IntoIter<Token>>, 
-    new_types: HashMap<NewtypeId, GenericTypeDef>,
-    functions: HashMap<ConcreteFuncSignature, ASTFunction>,
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+    prev_span: Span,
+    new_types: BTreeMap<NewtypeId, GenericTypeDef>,
+    newtype_spans: HashMap<NewtypeId, Span>,
+    functions: Vec<ASTFunction>,
+    fun_type_vars: Vec<String>,     // Those of the function being parsed
+    errors: Vec<Diagnostic>,
 }
 
 
 impl Parser {
 
-    pub fn parse_program(tokens: Vec<Token>) -> ASTProgram {
+    // Always yields a program; items and statements that failed to parse are left out of it
+    pub fn parse_program(tokens: Vec<Token>) -> (ASTProgram, Vec<Diagnostic>) {
         let mut parser = Parser {
             tokens: tokens.into_iter().peekable(),
-            new_types: HashMap::new(),
-            functions: HashMap::new(),              
+            prev_span: Span::default(),
+            new_types: BTreeMap::new(),
+            newtype_spans: HashMap::new(),
+            functions: Vec::new(),
+            fun_type_vars: Vec::new(),
+            errors: Vec::new(),
         };
-        while !parser.tokens.peek().is_none() {
-            match parser.tokens.peek().unwrap() {
-                &Token::Struct => {
-                    parser.process_struct_typedef(); 
-                }
-                &Token::Function => {
-                    parser.process_function_definition();
-                }
-                _ => {panic!("Invalid token, expected struct or func def");}
+        loop {
+            let result = match *parser.peek() {
+                TokenKind::Struct => parser.process_struct_typedef(),
+                TokenKind::Enum => parser.process_enum_typedef(),
+                TokenKind::Function => parser.process_function_definition(),
+                TokenKind::Eof => break,
+                _ => Err(parser.unexpected("`struct`, `enum` or `fun`").into()),
+            };
+            if let Err(diag) = result {
+                parser.errors.push(*diag);
+                parser.synchronize_item();
             }
         }
-        ASTProgram { 
-            typetable: GenericTypetable::new(parser.new_types),
+        let typetable = parser.make_typetable();
+        let program = ASTProgram {
+            typetable,
             functions: parser.functions,
-        } 
-    }
-    
-    fn process_struct_typedef(&mut self) {
-        self.expect_unparametric_token(Token::Struct);
-        let struct_identifier = NewtypeId(self.expect_identifier());
-        let type_params = self.collect_type_vars();
-        self.expect_unparametric_token(Token::LeftBrace);
+        };
+        (program, parser.errors)
+    }
+
+    fn make_typetable(&mut self) -> GenericTypetable {
+        loop {
+            match GenericTypetable::new(self.new_types.clone()) {
+                Ok(typetable) => return typetable,
+                Err(cycle) => {
+                    let names: Vec<String> = cycle.iter().map(|id| format!("`{}`", id.0)).collect();
+                    self.errors.push(Diagnostic::error(
+                        ErrorCode::CyclicTypeDefinition,
+                        "type definitions contain themselves",
+                        self.newtype_spans[&cycle[0]],
+                    )
+                    .with_note(format!("the cycle involves {}", names.join(", ")))
+                    .with_note("use a reference (`&T`) to break the cycle"));
+                    // Drop the offenders so the rest of the program can still be checked
+                    for id in cycle {
+                        self.new_types.remove(&id);
+                    }
+                }
+            }
+        }
+    }
+
+    fn process_struct_typedef(&mut self) -> CompileResult<()> {
+        self.expect_unparametric_token(TokenKind::Struct)?;
+        let name_span = self.peek_span();
+        let struct_identifier = NewtypeId(self.expect_identifier()?);
+        let type_params = self.collect_type_vars()?;
+        self.expect_unparametric_token(TokenKind::LeftBrace)?;
         let mut fields = BTreeMap::new();
-        while self.tokens.peek() != Some(&Token::RightBrace) { // TODO: update this to get rid of trail commas
-            let field_name = self.expect_identifier();
-            self.expect_unparametric_token(Token::Colon);
-            let field_type = self.expect_generic_type_annotation(&type_params);
-            self.expect_unparametric_token(Token::Comma);
+        while self.peek() != &TokenKind::RightBrace { // TODO: update this to get rid of trail commas
+            let field_name = self.expect_identifier()?;
+            self.expect_unparametric_token(TokenKind::Colon)?;
+            let field_type = self.expect_generic_type_annotation(&type_params)?;
+            self.expect_unparametric_token(TokenKind::Comma)?;
             fields.insert(field_name, field_type);
         }
-        self.expect_unparametric_token(Token::RightBrace);
+        self.expect_unparametric_token(TokenKind::RightBrace)?;
         let typedef = GenericTypeDef {
             type_params,
             defn: GenericShape::Struct { fields},
         };
-        self.new_types.insert(struct_identifier, typedef); 
+        self.newtype_spans.insert(struct_identifier.clone(), name_span);
+        self.new_types.insert(struct_identifier, typedef);
+        Ok(())
+    }
+
+
+    // Variants are types, separated by commas, with the trailing one optional. `::D(A, B)` is a
+    // namespaced one instead, with a payload type of its own; `enum N:: {...}` namespaces them all.
+    fn process_enum_typedef(&mut self) -> CompileResult<()> {
+        self.expect_unparametric_token(TokenKind::Enum)?;
+        let name_span = self.peek_span();
+        let enum_identifier = NewtypeId(self.expect_identifier()?);
+        let type_params = self.collect_type_vars()?;
+        let all_namespaced = self.peek() == &TokenKind::DoubleColon;
+        if all_namespaced {
+            self.advance();
+        }
+        self.expect_unparametric_token(TokenKind::LeftBrace)?;
+        let mut variants = Vec::new();
+        while self.peek() != &TokenKind::RightBrace {
+            if !all_namespaced && self.peek() != &TokenKind::DoubleColon {
+                variants.push(self.expect_generic_type_annotation(&type_params)?);
+            } else {
+                if !all_namespaced {
+                    self.advance();
+                }
+                variants.push(self.process_namespaced_variant(&enum_identifier, &type_params)?);
+            }
+            if self.peek() != &TokenKind::Comma {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_unparametric_token(TokenKind::RightBrace)?;
+        let typedef = GenericTypeDef {
+            type_params,
+            defn: GenericShape::Enum { variants },
+        };
+        self.newtype_spans.insert(enum_identifier.clone(), name_span);
+        self.new_types.insert(enum_identifier, typedef);
+        Ok(())
     }
 
+    // Its payload is an anonymous struct with a field per listed type, named by position: `0`, `1`, ...
+    fn process_namespaced_variant(&mut self, enum_id: &NewtypeId, type_params: &Vec<String>) -> CompileResult<GenericType> {
+        let name_span = self.peek_span();
+        let variant_id = NewtypeId::variant(enum_id, &self.expect_identifier()?);
+        let mut fields = BTreeMap::new();
+        if self.peek() == &TokenKind::LeftParen {
+            self.advance();
+            while self.peek() != &TokenKind::RightParen {
+                fields.insert(fields.len().to_string(), self.expect_generic_type_annotation(type_params)?);
+                if self.peek() != &TokenKind::Comma {
+                    break;
+                }
+                self.advance();
+            }
+            self.expect_unparametric_token(TokenKind::RightParen)?;
+        }
+        let typedef = GenericTypeDef {
+            type_params: type_params.clone(),
+            defn: GenericShape::Struct { fields },
+        };
+        self.newtype_spans.insert(variant_id.clone(), name_span);
+        self.new_types.insert(variant_id.clone(), typedef);
+        let params = type_params.iter().map(|param| GenericType::TypeVar(param.clone())).collect();
+        Ok(GenericType::NewType(variant_id, params))
+    }
 
-    fn collect_type_vars(&mut self) -> Vec<String> {
-        if self.tokens.peek().unwrap() != &Token::LeftSqBracket {
-            return vec![]
+    fn collect_type_vars(&mut self) -> CompileResult<Vec<String>> {
+        if self.peek() != &TokenKind::LeftSqBracket {
+            return Ok(vec![])
         } else {
-            self.tokens.next();
+            self.advance();
         }
         let mut type_params: Vec<String> = Vec::new();
-        type_params.push(self.expect_identifier());
-        while self.tokens.peek().unwrap() == &Token::Comma {
-            self.tokens.next();
-            type_params.push(self.expect_identifier());
+        type_params.push(self.expect_identifier()?);
+        while self.peek() == &TokenKind::Comma {
+            self.advance();
+            type_params.push(self.expect_identifier()?);
         }
-        self.expect_unparametric_token(Token::RightSqBracket);
-        type_params
-    }
-
-       
-    fn process_function_definition(&mut self) {
-        self.expect_unparametric_token(Token::Function);
-        let funcname = self.expect_identifier(); 
-
-        self.expect_unparametric_token(Token::LeftParen);
-        let args:HashMap<String, ConcreteType> = match self.tokens.peek().unwrap() {
-            &Token::RightParen => {
-                HashMap::new()
-            }
-            &Token::Identifier(_) => {
-                let name1 = self.expect_identifier();
-                self.expect_unparametric_token(Token::Colon);
-                let typ1 = self.expect_concrete_type_annotation();
-                let mut args = HashMap::new();
-                args.insert(name1, typ1);
-
-                while self.tokens.peek().unwrap() == &Token::Comma {
-                    self.tokens.next();
-                    let arg_name = self.expect_identifier();
-                    self.expect_unparametric_token(Token::Colon);
-                    let arg_type = self.expect_concrete_type_annotation();
-                    args.insert(arg_name, arg_type);
+        self.expect_unparametric_token(TokenKind::RightSqBracket)?;
+        Ok(type_params)
+    }
+
+
+    fn process_function_definition(&mut self) -> CompileResult<()> {
+        let start = self.peek_span();
+        self.expect_unparametric_token(TokenKind::Function)?;
+        let funcname = self.expect_identifier()?;
+        let type_params = self.collect_type_vars()?;
+        self.fun_type_vars = type_params.clone();
+
+        self.expect_unparametric_token(TokenKind::LeftParen)?;
+        let args: Vec<(String, GenericType)> = match *self.peek() {
+            TokenKind::RightParen => {
+                Vec::new()
+            }
+            TokenKind::Identifier(_) => {
+                let name1 = self.expect_identifier()?;
+                self.expect_unparametric_token(TokenKind::Colon)?;
+                let typ1 = self.expect_type_annotation()?;
+                let mut args = vec![(name1, typ1)];
+
+                while self.peek() == &TokenKind::Comma {
+                    self.advance();
+                    let arg_name = self.expect_identifier()?;
+                    self.expect_unparametric_token(TokenKind::Colon)?;
+                    let arg_type = self.expect_type_annotation()?;
+                    args.push((arg_name, arg_type));
                 }
                 args
             }
             _ => {
-                panic!("Unexpected token encountered during function arg parsing");
+                return Err(self.unexpected("an argument name or `)`").into());
             }
         };
-        self.expect_unparametric_token(Token::RightParen);
+        self.expect_unparametric_token(TokenKind::RightParen)?;
 
-        let ret_type_id = match self.tokens.peek().unwrap() {
-            Token::RightArrow => {
-                self.tokens.next();
-                self.expect_concrete_type_annotation()
+        let ret_type_id = match self.peek() {
+            TokenKind::RightArrow => {
+                self.advance();
+                self.expect_type_annotation()?
             },
             _ => {
-                ConcreteType::Prim(PrimType::None)
+                GenericType::Prim(PrimType::None)
             }
         };
-        let body = self.parse_statement_block();
-        let func = ASTFunction {name: funcname, args: args, body: body, ret_type: ret_type_id};
-        let sgn = func.get_signature();
-        self.functions.insert(sgn, func);
+        let span = self.span_from(start);
+        let body = self.parse_statement_block()?;
+        self.functions.push(ASTFunction {name: funcname, type_params, args, body, ret_type: ret_type_id, span});
+        Ok(())
     }
-    
-    fn parse_statement_block(&mut self) -> Vec<ASTStatement> {
+
+    fn parse_statement_block(&mut self) -> CompileResult<Vec<ASTStatement>> {
         let mut statements = Vec::new();
-        self.expect_unparametric_token(Token::LeftBrace);
-        while !matches!(self.tokens.peek(), Some(Token::RightBrace)){
-            statements.push(self.parse_statement());
+        self.expect_unparametric_token(TokenKind::LeftBrace)?;
+        let errors_before = self.errors.len();
+        while !matches!(self.peek(), TokenKind::RightBrace | TokenKind::Eof | TokenKind::Function | TokenKind::Struct | TokenKind::Enum) {
+            match self.parse_statement() {
+                Ok(stmt) => statements.push(stmt),
+                Err(diag) => {
+                    self.errors.push(*diag);
+                    self.synchronize_statement();
+                }
+            }
+        }
+        // A missing brace after an already reported error is most likely fallout from it
+        if self.peek() != &TokenKind::RightBrace && self.errors.len() > errors_before {
+            return Ok(statements);
         }
-        self.expect_unparametric_token(Token::RightBrace);
-        statements
-    }
-    
-    fn parse_statement(&mut self) -> ASTStatement {
-        match self.tokens.peek().unwrap() {           
-            &Token::Let => {
-                self.tokens.next();
-                let var_name = self.expect_identifier();         
-                self.expect_unparametric_token(Token::Colon);
-                let var_type = self.expect_concrete_type_annotation();
-                let var = ConcreteVariable {
-                    name: var_name, 
+        self.expect_unparametric_token(TokenKind::RightBrace)?;
+        Ok(statements)
+    }
+
+    fn parse_statement(&mut self) -> CompileResult<ASTStatement> {
+        let start = self.peek_span();
+        let stmt = match *self.peek() {
+            TokenKind::Let => {
+                self.advance();
+                let var_name = self.expect_identifier()?;
+                self.expect_unparametric_token(TokenKind::Colon)?;
+                let var_type = self.expect_type_annotation()?;
+                let var = GenTypeVariable {
+                    name: var_name,
                     typ: var_type
                 };
-                self.expect_unparametric_token(Token::Assign);
-                let value = self.parse_expression();
-                self.expect_unparametric_token(Token::Semicolon);
-                ASTStatement::Let{var, value}
-            }
-            &Token::If => {
-                self.tokens.next();
-                let condition = self.parse_expression();
-                let if_body = self.parse_statement_block();
-                let else_body =  if matches!(self.tokens.peek(), Some(Token::Else)) {
-                    self.tokens.next();
-                    Some(self.parse_statement_block())
+                self.expect_unparametric_token(TokenKind::Assign)?;
+                let value = self.parse_expression()?;
+                self.expect_semicolon()?;
+                ASTStatementKind::Let{var, value}
+            }
+            TokenKind::If => {
+                self.advance();
+                let condition = self.parse_expression()?;
+                let if_body = self.parse_statement_block()?;
+                let else_body =  if self.peek() == &TokenKind::Else {
+                    self.advance();
+                    Some(self.parse_statement_block()?)
                 } else {None};
-                ASTStatement::If {condition, if_body, else_body}
-            }
-            &Token::While => {
-                self.tokens.next();
-                let cond = self.parse_expression();
-                let body = self.parse_statement_block();
-                ASTStatement::While { 
-                    condition: cond, 
-                    body: body,
+                ASTStatementKind::If {condition, if_body, else_body}
+            }
+            TokenKind::While => {
+                self.advance();
+                let cond = self.parse_expression()?;
+                let body = self.parse_statement_block()?;
+                ASTStatementKind::While {
+                    condition: cond,
+                    body,
                 }
             }
-            &Token::Break => {         
-                self.tokens.next();
-                self.expect_unparametric_token(Token::Semicolon);
-                ASTStatement::Break
-            }
-            &Token::Continue => {         
-                self.tokens.next();
-                self.expect_unparametric_token(Token::Semicolon);
-                ASTStatement::Continue
-            }
-            &Token::Return => {
-                self.tokens.next();
-                let return_expr = self.parse_expression();
-                self.expect_unparametric_token(Token::Semicolon);
-                ASTStatement::Return(return_expr)
-            }
-            &Token::Print => {
-                self.tokens.next();
-                self.expect_unparametric_token(Token::LeftParen);
-                let expr = self.parse_expression();
-                self.expect_unparametric_token(Token::RightParen);
-                self.expect_unparametric_token(Token::Semicolon);        
-                ASTStatement::Print(expr)
+            TokenKind::Match => {
+                self.advance();
+                let scrutinee = self.parse_expression()?;
+                self.expect_unparametric_token(TokenKind::LeftBrace)?;
+                let mut arms = Vec::new();
+                while !matches!(self.peek(), TokenKind::RightBrace | TokenKind::Eof) {
+                    let pattern = self.parse_pattern()?;
+                    self.expect_unparametric_token(TokenKind::FatArrow)?;
+                    let body = self.parse_statement_block()?;
+                    arms.push(ASTMatchArm { pattern, body });
+                }
+                self.expect_unparametric_token(TokenKind::RightBrace)?;
+                ASTStatementKind::Match { scrutinee, arms }
+            }
+            TokenKind::Break => {
+                self.advance();
+                self.expect_semicolon()?;
+                ASTStatementKind::Break
+            }
+            TokenKind::Continue => {
+                self.advance();
+                self.expect_semicolon()?;
+                ASTStatementKind::Continue
+            }
+            TokenKind::Return => {
+                self.advance();
+                let return_expr = self.parse_expression()?;
+                self.expect_semicolon()?;
+                ASTStatementKind::Return(return_expr)
+            }
+            TokenKind::Print => {
+                self.advance();
+                self.expect_unparametric_token(TokenKind::LeftParen)?;
+                let expr = self.parse_expression()?;
+                self.expect_unparametric_token(TokenKind::RightParen)?;
+                self.expect_semicolon()?;
+                ASTStatementKind::Print(expr)
             },
-            _ => {                                      
-                // We assume it's an assignment 
-                // TODO: this is not actually correct, it might be a funccall
-                let target = self.parse_lvalue();
-                self.expect_unparametric_token(Token::Assign);
-                let assign_value = self.parse_expression();
-                self.expect_unparametric_token(Token::Semicolon);
-                ASTStatement::Assign { 
+            _ => {
+                // An assignment, unless it's a bare name followed by args
+                let target = self.parse_lvalue()?;
+                if let ASTLValue::Variable(funcname) = &target
+                    && matches!(self.peek(), TokenKind::LeftParen | TokenKind::LeftSqBracket)
+                {
+                    let type_args = self.expect_type_bindings()?;
+                    let args = self.parse_call_args()?;
+                    let call = ASTExpressionKind::FuncCall { funcname: funcname.clone(), type_args, args };
+                    self.expect_semicolon()?;
+                    let expr = ASTExpression { expr: call, span: self.span_from(start) };
+                    return Ok(ASTStatement { stmt: ASTStatementKind::Call(expr), span: self.span_from(start) });
+                }
+                self.expect_unparametric_token(TokenKind::Assign)?;
+                let assign_value = self.parse_expression()?;
+                self.expect_semicolon()?;
+                ASTStatementKind::Assign {
                     target,
                     value: assign_value
                 }
             }
+        };
+        Ok(ASTStatement { stmt, span: self.span_from(start) })
+    }
+
+    fn parse_pattern(&mut self) -> CompileResult<ASTPattern> {
+        let start = self.peek_span();
+        let pat = match self.peek().clone() {
+            TokenKind::Underscore => {
+                self.advance();
+                ASTPatternKind::Wildcard
+            }
+            TokenKind::IntLiteral(int) => {
+                self.advance();
+                ASTPatternKind::IntLiteral(int)
+            }
+            TokenKind::True => {
+                self.advance();
+                ASTPatternKind::BoolTrue
+            }
+            TokenKind::False => {
+                self.advance();
+                ASTPatternKind::BoolFalse
+            }
+            TokenKind::Identifier(name) => {
+                self.advance();
+                // Whether a bare name is a type or a binding is only known once all types are
+                if !matches!(self.peek(), TokenKind::LeftSqBracket | TokenKind::DoubleColon | TokenKind::LeftBrace | TokenKind::LeftParen) {
+                    return Ok(ASTPattern { pat: ASTPatternKind::Name(name), span: self.span_from(start) });
+                }
+                let type_vars = self.fun_type_vars.clone();
+                let typ = self.expect_named_type(name, &type_vars)?;
+                match self.peek() {
+                    TokenKind::LeftBrace => ASTPatternKind::Struct { typ, fields: self.parse_field_patterns()? },
+                    TokenKind::LeftParen => ASTPatternKind::Positional { typ, fields: self.parse_positional_patterns()? },
+                    _ => ASTPatternKind::Type(typ),
+                }
+            }
+            _ => return Err(self.unexpected("a pattern").into()),
+        };
+        Ok(ASTPattern { pat, span: self.span_from(start) })
+    }
+
+    // `{a, b: p}`, where `a` is short for `a: a`. Commas separate, with the trailing one optional.
+    fn parse_field_patterns(&mut self) -> CompileResult<Vec<(String, ASTPattern)>> {
+        self.expect_unparametric_token(TokenKind::LeftBrace)?;
+        let mut fields = Vec::new();
+        while !matches!(self.peek(), TokenKind::RightBrace | TokenKind::Eof) {
+            let start = self.peek_span();
+            let field = self.expect_identifier()?;
+            let pattern = if self.peek() == &TokenKind::Colon {
+                self.advance();
+                self.parse_pattern()?
+            } else {
+                ASTPattern { pat: ASTPatternKind::Name(field.clone()), span: self.span_from(start) }
+            };
+            fields.push((field, pattern));
+            if self.peek() != &TokenKind::Comma {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_unparametric_token(TokenKind::RightBrace)?;
+        Ok(fields)
+    }
+
+    fn parse_positional_patterns(&mut self) -> CompileResult<Vec<ASTPattern>> {
+        self.expect_unparametric_token(TokenKind::LeftParen)?;
+        let mut fields = Vec::new();
+        while !matches!(self.peek(), TokenKind::RightParen | TokenKind::Eof) {
+            fields.push(self.parse_pattern()?);
+            if self.peek() != &TokenKind::Comma {
+                break;
+            }
+            self.advance();
         }
-    } 
+        self.expect_unparametric_token(TokenKind::RightParen)?;
+        Ok(fields)
+    }
 
-    fn parse_lvalue(&mut self) -> ASTLValue {
-        let mut curr_lvalue = match self.tokens.peek().unwrap() {
-            &Token::Deref => {
-                self.tokens.next();
-                ASTLValue::Deref(self.parse_expression())
+    fn parse_lvalue(&mut self) -> CompileResult<ASTLValue> {
+        let mut curr_lvalue = match self.peek() {
+            TokenKind::Deref => {
+                self.advance();
+                ASTLValue::Deref(self.parse_expression()?)
             },
-            _ => {
-                let root_var = self.expect_identifier();
+            TokenKind::Identifier(_) => {
+                let root_var = self.expect_identifier()?;
                 ASTLValue::Variable(root_var)
             }
+            _ => return Err(self.unexpected("a statement").into()),
         };
-        while self.tokens.peek().unwrap() == &Token::Dot {
-            self.tokens.next();
-            let curr_field = self.expect_identifier();
-            curr_lvalue = ASTLValue::FieldAccess { 
-                of: Box::new(curr_lvalue), 
-                field: curr_field 
+        while self.peek() == &TokenKind::Dot {
+            self.advance();
+            let curr_field = self.expect_field_name()?;
+            curr_lvalue = ASTLValue::FieldAccess {
+                of: Box::new(curr_lvalue),
+                field: curr_field
             };
         }
-        curr_lvalue
+        Ok(curr_lvalue)
 
     }
 
-    fn parse_expression(&mut self) -> ASTExpression {
+    fn parse_expression(&mut self) -> CompileResult<ASTExpression> {
         self.parse_expression_with_precedence(0)
     }
-              
-    fn parse_expression_with_precedence(&mut self, current_level: usize) -> ASTExpression {
-        let mut current_expr = self.parse_unary();
+
+    fn parse_expression_with_precedence(&mut self, current_level: usize) -> CompileResult<ASTExpression> {
+        let start = self.peek_span();
+        let mut current_expr = self.parse_unary()?;
         loop {
-            let token = self.tokens.peek().unwrap();
+            let token = self.peek();
             let prec = match token {
-                Token::Plus | Token::Minus | Token::Multiply | Token::Equals | Token::Less | Token::Modulo  => {
-                    get_connector_precedence(self.tokens.peek().unwrap())
+                TokenKind::Plus | TokenKind::Minus | TokenKind::Multiply | TokenKind::Equals | TokenKind::Less | TokenKind::Modulo  => {
+                    get_connector_precedence(token)
                 }
                 _ => break,
             };
             if prec < current_level {
                 break;
             }
-            let conn_token = self.tokens.next().unwrap();  
-            current_expr = match conn_token {
-                Token::Dot => {
-                    let field = self.expect_identifier();
-                    ASTExpression::FieldAccess { 
-                        expr: Box::new(current_expr), 
-                        field 
-                    }
-                }
-                _ => {
-                    let op = map_binop_token(&conn_token);
-                    let next_expr = self.parse_expression_with_precedence(prec + 1);
-                    ASTExpression::BinOp { op, left: Box::new(current_expr), right: Box::new(next_expr) }
-                }
-            };
+            let op = map_binop_token(&self.advance().kind);
+            let next_expr = self.parse_expression_with_precedence(prec + 1)?;
+            let expr = ASTExpressionKind::BinOp { op, left: Box::new(current_expr), right: Box::new(next_expr) };
+            current_expr = ASTExpression { expr, span: self.span_from(start) };
         }
-        current_expr
+        Ok(current_expr)
     }
 
-    fn parse_unary(&mut self) -> ASTExpression {
-        match self.tokens.peek().unwrap() {
-            &Token::Ref => {
-                self.tokens.next();
-                let refd = self.parse_unary();
-                ASTExpression::Reference(Box::new(refd))
+    fn parse_unary(&mut self) -> CompileResult<ASTExpression> {
+        let start = self.peek_span();
+        let expr = match self.peek() {
+            TokenKind::Ref => {
+                self.advance();
+                let refd = self.parse_unary()?;
+                ASTExpressionKind::Reference(Box::new(refd))
             }
-            &Token::Deref => {
-                self.tokens.next();
-                let derefd = self.parse_unary();
-                ASTExpression::Dereference(Box::new(derefd))
+            TokenKind::Deref => {
+                self.advance();
+                let derefd = self.parse_unary()?;
+                ASTExpressionKind::Dereference(Box::new(derefd))
             }
-            _ => self.parse_postfix(),
-        }
+            _ => return self.parse_postfix(),
+        };
+        Ok(ASTExpression { expr, span: self.span_from(start) })
     }
 
-    fn parse_postfix(&mut self) -> ASTExpression {
-        let mut curr_expr = self.parse_expression_atom();
-        while self.tokens.peek().unwrap() == &Token::Dot {
-            self.tokens.next();
-            let field = self.expect_identifier();
-            curr_expr = ASTExpression::FieldAccess { 
-                expr: Box::new(curr_expr), 
-                field 
+    fn parse_postfix(&mut self) -> CompileResult<ASTExpression> {
+        let start = self.peek_span();
+        let mut curr_expr = self.parse_expression_atom()?;
+        while self.peek() == &TokenKind::Dot {
+            self.advance();
+            let field = self.expect_field_name()?;
+            curr_expr = ASTExpression {
+                expr: ASTExpressionKind::FieldAccess {
+                    expr: Box::new(curr_expr),
+                    field
+                },
+                span: self.span_from(start),
             };
         }
-        curr_expr
-    }
-    
-    fn parse_expression_atom(&mut self) -> ASTExpression {
-        let token = self.tokens.next().unwrap();  
-        match token {
-            Token::IntLiteral(int) => ASTExpression::IntLiteral(int),
-            Token::Identifier(name) => {
-                let bindings = self.expect_concrete_bindings(); // TODO: add later for funccall case, reject properly for variables
-                match self.tokens.peek().unwrap() {
-                    &Token::LeftParen => {                                                      // FuncCall
-                        self.tokens.next();
-                        let args: Vec<ASTExpression> = match self.tokens.peek().unwrap() {
-                            &Token::RightParen => Vec::new(),
-                            _ => {
-                                let mut collected_args: Vec<ASTExpression> = Vec::new();
-                                collected_args.push(self.parse_expression());
-                                while self.tokens.peek().unwrap() == &Token::Comma {
-                                    self.tokens.next();
-                                    collected_args.push(self.parse_expression());
-                                }
-                                collected_args
-                            }
+        Ok(curr_expr)
+    }
+
+    fn parse_expression_atom(&mut self) -> CompileResult<ASTExpression> {
+        let start = self.peek_span();
+        let expr = match self.peek().clone() {
+            TokenKind::IntLiteral(int) => {
+                self.advance();
+                ASTExpressionKind::IntLiteral(int)
+            }
+            TokenKind::Identifier(name) => {
+                self.advance();
+                let bindings = self.expect_type_bindings()?; // TODO: reject properly for variables
+                match *self.peek() {
+                    TokenKind::DoubleColon => {                                                    // Namespaced variant
+                        self.advance();
+                        let variant_id = NewtypeId::variant(&NewtypeId(name), &self.expect_identifier()?);
+                        let args = match self.peek() {
+                            TokenKind::LeftParen => self.parse_call_args()?,
+                            _ => Vec::new(),
                         };
-                        self.expect_unparametric_token(Token::RightParen);
-                        ASTExpression::FuncCall { funcname: name, args: args}
+                        ASTExpressionKind::StructLiteral {
+                            typ: GenericType::NewType(variant_id, bindings),
+                            fields: args.into_iter().enumerate().map(|(i, arg)| (i.to_string(), arg)).collect(),
+                        }
                     }
-                    &Token::LeftBrace => {                                                  
+                    TokenKind::LeftParen => {                                                      // FuncCall
+                        let args = self.parse_call_args()?;
+                        ASTExpressionKind::FuncCall { funcname: name, type_args: bindings, args}
+                    }
+                    TokenKind::LeftBrace => {
                         if self.new_types.contains_key(&NewtypeId(name.clone())) {
-                            let fields = self.parse_struct_literal_internals();
-                            self.expect_unparametric_token(Token::RightBrace);
-                            ASTExpression::StructLiteral {
-                                typ: ConcreteType::NewType(NewtypeId(name), bindings),
+                            let fields = self.parse_struct_literal_internals()?;
+                            self.expect_unparametric_token(TokenKind::RightBrace)?;
+                            ASTExpressionKind::StructLiteral {
+                                typ: GenericType::NewType(NewtypeId(name), bindings),
                                 fields
                             }
                         } else {
-                            ASTExpression::Variable(name)
+                            ASTExpressionKind::Variable(name)
                         }
                     }
-                    _ => ASTExpression::Variable(name)
+                    _ => ASTExpressionKind::Variable(name)
                 }
             },
-            Token::LeftParen => {
-                let paren_expr = self.parse_expression();
-                self.expect_unparametric_token(Token::RightParen);
-                paren_expr
+            TokenKind::LeftParen => {
+                self.advance();
+                let paren_expr = self.parse_expression()?;
+                self.expect_unparametric_token(TokenKind::RightParen)?;
+                paren_expr.expr
             },
-            Token::True => ASTExpression::BoolTrue,
-            Token::False => ASTExpression::BoolFalse,
+            TokenKind::True => {
+                self.advance();
+                ASTExpressionKind::BoolTrue
+            }
+            TokenKind::False => {
+                self.advance();
+                ASTExpressionKind::BoolFalse
+            }
             _ => {
-                panic!("Unexpected token {:?} during expression parsing", token);
+                return Err(self.unexpected("an expression").into());
             },
+        };
+        Ok(ASTExpression { expr, span: self.span_from(start) })
+    }
+
+    fn parse_call_args(&mut self) -> CompileResult<Vec<ASTExpression>> {
+        self.expect_unparametric_token(TokenKind::LeftParen)?;
+        let args: Vec<ASTExpression> = match self.peek() {
+            TokenKind::RightParen => Vec::new(),
+            _ => {
+                let mut collected_args: Vec<ASTExpression> = Vec::new();
+                collected_args.push(self.parse_expression()?);
+                while self.peek() == &TokenKind::Comma {
+                    self.advance();
+                    collected_args.push(self.parse_expression()?);
+                }
+                collected_args
+            }
+        };
+        self.expect_unparametric_token(TokenKind::RightParen)?;
+        Ok(args)
+    }
+
+    fn parse_struct_literal_internals(&mut self) -> CompileResult<Vec<(String, ASTExpression)>>{
+        self.expect_unparametric_token(TokenKind::LeftBrace)?;
+        let mut fields = Vec::new();
+        while !matches!(self.peek(), TokenKind::RightBrace | TokenKind::Eof) { // TODO: change (trail comma issue)
+            let field_name = self.expect_identifier()?;
+            self.expect_unparametric_token(TokenKind::Colon)?;
+            let field_value = self.parse_expression()?;
+            self.expect_unparametric_token(TokenKind::Comma)?;
+            fields.push((field_name, field_value));
         }
+        Ok(fields)
     }
-    
-    fn parse_struct_literal_internals(&mut self) -> HashMap<String, ASTExpression>{
-        self.expect_unparametric_token(Token::LeftBrace);
-        let mut fields = HashMap::new();
-        while self.tokens.peek() != Some(&Token::RightBrace) { // TODO: change (trail comma issue)
-            let field_name = self.expect_identifier();
-            self.expect_unparametric_token(Token::Colon);
-            let field_value = self.parse_expression();
-            self.expect_unparametric_token(Token::Comma);
-            fields.insert(field_name, field_value);
+
+
+    fn expect_unparametric_token(&mut self, expected_token: TokenKind) -> CompileResult<()> {
+        if self.peek() != &expected_token {
+            return Err(self.unexpected(&expected_token.to_string()).into());
         }
-        fields 
+        self.advance();
+        Ok(())
     }
 
-    
-    fn expect_unparametric_token(&mut self, expected_token: Token) {
-        let token = self.tokens.peek().unwrap();
-        if token != &expected_token {
-            panic!("Expected token {:?}, got token {:?}.", expected_token, token);
+    // A statement missing only its `;` is kept, so the error doesn't cascade into later ones. That's
+    // when what follows looks like the next statement; anything else most likely cut the statement
+    // short, which then goes as a whole.
+    fn expect_semicolon(&mut self) -> CompileResult<()> {
+        if self.peek() == &TokenKind::Semicolon {
+            self.advance();
+            return Ok(());
         }
-        self.tokens.next();
+        let found = self.peek().clone();
+        let found_span = self.peek_span();
+        let prev = self.prev_span;
+        let after_prev = Span::new(prev.end, prev.end, prev.line, prev.col + (prev.end - prev.start));
+        let diag = Diagnostic::error(ErrorCode::UnexpectedToken, format!("expected `;`, found {}", found), after_prev)
+            .with_primary_label("expected `;`")
+            .with_label(found_span, "unexpected token");
+        let next_statement = match found {
+            TokenKind::Let | TokenKind::If | TokenKind::While | TokenKind::Match | TokenKind::Break
+                | TokenKind::Continue | TokenKind::Return | TokenKind::Print | TokenKind::RightBrace | TokenKind::Eof => true,
+            // These start statements too, but also carry on expressions, so only on a line of their own
+            TokenKind::Identifier(_) | TokenKind::Deref => found_span.line > prev.line,
+            _ => false,
+        };
+        if !next_statement {
+            return Err(diag.into());
+        }
+        self.errors.push(diag);
+        Ok(())
     }
-    
-    fn expect_identifier(&mut self) -> String {
-        let token = self.tokens.next();
-        let Some(Token::Identifier(name)) = token else {
-                panic!("Expected identifier token, got token: {:?}", token);
+
+    fn expect_identifier(&mut self) -> CompileResult<String> {
+        let TokenKind::Identifier(name) = self.peek().clone() else {
+            return Err(self.unexpected("an identifier").into());
         };
-        name
+        self.advance();
+        Ok(name)
     }
 
-    fn expect_generic_type_annotation(&mut self, scope_typevars: &Vec<String>) -> GenericType {
-        match self.tokens.next().unwrap() {
-            Token::Int => {
+    // Fields of namespaced variants are named by position
+    fn expect_field_name(&mut self) -> CompileResult<String> {
+        if let TokenKind::IntLiteral(pos) = self.peek().clone() {
+            self.advance();
+            return Ok(pos.to_string());
+        }
+        self.expect_identifier()
+    }
+
+    fn expect_generic_type_annotation(&mut self, scope_typevars: &Vec<String>) -> CompileResult<GenericType> {
+        let typ = match self.peek().clone() {
+            TokenKind::Int => {
+                self.advance();
                 GenericType::Prim(PrimType::Integer)
             }
-            Token::Bool => {
+            TokenKind::Bool => {
+                self.advance();
                 GenericType::Prim(PrimType::Bool)
             }
-            Token::Identifier(type_id) => {
-                if scope_typevars.contains(&type_id) {
-                    GenericType::TypeVar(type_id)
-                }
-                else {
-                    let bindings = self.expect_generic_bindings(scope_typevars);
-                    GenericType::NewType(NewtypeId(type_id), bindings)
-                }
+            TokenKind::Identifier(type_id) => {
+                self.advance();
+                self.expect_named_type(type_id, scope_typevars)?
             }
-            Token::Ref => {
-                let refd_type = self.expect_generic_type_annotation(scope_typevars);
+            TokenKind::Ref => {
+                self.advance();
+                let refd_type = self.expect_generic_type_annotation(scope_typevars)?;
                 GenericType::Reference(Box::new(refd_type))
             }
             _ => {
-                panic!("Unexpected token while parsing type annotation");
+                return Err(self.unexpected("a type").into());
             }
+        };
+        Ok(typ)
+    }
+
+    // The rest of a type starting with a name: its type args, and the variant if it's a namespaced one
+    fn expect_named_type(&mut self, type_id: String, scope_typevars: &Vec<String>) -> CompileResult<GenericType> {
+        if scope_typevars.contains(&type_id) {
+            return Ok(GenericType::TypeVar(type_id));
         }
+        let bindings = self.expect_generic_bindings(scope_typevars)?;
+        let mut id = NewtypeId(type_id);
+        if self.peek() == &TokenKind::DoubleColon {
+            self.advance();
+            id = NewtypeId::variant(&id, &self.expect_identifier()?);
+        }
+        Ok(GenericType::NewType(id, bindings))
     }
-    
-    fn expect_generic_bindings(&mut self, scope_typevars: &Vec<String>) -> Vec<GenericType> {
-        if self.tokens.peek().unwrap() != &Token::LeftSqBracket {
-            return vec![]
+
+    fn expect_generic_bindings(&mut self, scope_typevars: &Vec<String>) -> CompileResult<Vec<GenericType>> {
+        if self.peek() != &TokenKind::LeftSqBracket {
+            return Ok(vec![])
         } else {
-            self.tokens.next();
+            self.advance();
         }
         let mut bindings: Vec<GenericType> = Vec::new();
-        bindings.push(self.expect_generic_type_annotation(scope_typevars));
-        while self.tokens.peek().unwrap() == &Token::Comma {
-            self.tokens.next();
-            bindings.push(self.expect_generic_type_annotation(scope_typevars));
+        bindings.push(self.expect_generic_type_annotation(scope_typevars)?);
+        while self.peek() == &TokenKind::Comma {
+            self.advance();
+            bindings.push(self.expect_generic_type_annotation(scope_typevars)?);
         }
-        self.expect_unparametric_token(Token::RightSqBracket);
-        bindings
+        self.expect_unparametric_token(TokenKind::RightSqBracket)?;
+        Ok(bindings)
     }
-    
-    fn expect_concrete_type_annotation(&mut self) -> ConcreteType {
-        match self.tokens.next().unwrap() {
-            Token::Int => {
-                ConcreteType::Prim(PrimType::Integer)
-            }
-            Token::Bool => {
-                ConcreteType::Prim(PrimType::Bool)
-            }
-            Token::Identifier(type_id) => {
-                let bindings = self.expect_concrete_bindings();
-                ConcreteType::NewType(NewtypeId(type_id), bindings)
-            }
-            Token::Ref => {
-                let refd_type = self.expect_concrete_type_annotation();
-                ConcreteType::Reference(Box::new(refd_type))
-            }
-            _ => {
-                panic!("Unexpected token while parsing type annotation");
-            }
-        }
+
+    // Types inside a function, where its type variables are in scope
+    fn expect_type_annotation(&mut self) -> CompileResult<GenericType> {
+        let type_vars = self.fun_type_vars.clone();
+        self.expect_generic_type_annotation(&type_vars)
     }
 
+    fn expect_type_bindings(&mut self) -> CompileResult<Vec<GenericType>> {
+        let type_vars = self.fun_type_vars.clone();
+        self.expect_generic_bindings(&type_vars)
+    }
 
-    fn expect_concrete_bindings(&mut self) -> Vec<ConcreteType> {
-        if self.tokens.peek().unwrap() != &Token::LeftSqBracket {
-            return vec![]
-        } else {
-            self.tokens.next();
+    // Skips past the rest of a broken statement: up to and including its `;`, or up to the `}` closing
+    // the enclosing block. Nested blocks are skipped whole.
+    fn synchronize_statement(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                TokenKind::Eof | TokenKind::Function | TokenKind::Struct | TokenKind::Enum => return,
+                TokenKind::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                TokenKind::RightBrace if depth == 0 => return,
+                TokenKind::RightBrace => depth -= 1,
+                TokenKind::LeftBrace => depth += 1,
+                _ => {}
+            }
+            self.advance();
         }
-        let mut bindings: Vec<ConcreteType> = Vec::new();
-        bindings.push(self.expect_concrete_type_annotation());
-        while self.tokens.peek().unwrap() == &Token::Comma {
-            self.tokens.next();
-            bindings.push(self.expect_concrete_type_annotation());
+    }
+
+    // Skips to the start of the next top-level item
+    fn synchronize_item(&mut self) {
+        while !matches!(self.peek(), TokenKind::Eof | TokenKind::Function | TokenKind::Struct | TokenKind::Enum) {
+            self.advance();
         }
-        self.expect_unparametric_token(Token::RightSqBracket);
-        bindings
     }
-}
 
+    fn unexpected(&mut self, expected: &str) -> Diagnostic {
+        let span = self.peek_span();
+        let found = self.peek().clone();
+        let code = match found {
+            TokenKind::Eof => ErrorCode::UnexpectedEof,
+            _ => ErrorCode::UnexpectedToken,
+        };
+        Diagnostic::error(code, format!("expected {}, found {}", expected, found), span)
+            .with_primary_label(format!("expected {}", expected))
+    }
 
+    // The lexer always ends the stream with an Eof token, which is never consumed
+    fn peek(&mut self) -> &TokenKind {
+        &self.tokens.peek().expect("Token stream is missing its Eof token").kind
+    }
+
+    fn peek_span(&mut self) -> Span {
+        self.tokens.peek().map(|token| token.span).unwrap_or(self.prev_span)
+    }
+
+    fn advance(&mut self) -> Token {
+        if self.peek() == &TokenKind::Eof {
+            return self.tokens.peek().unwrap().clone();
+        }
+        let token = self.tokens.next().unwrap();
+        self.prev_span = token.span;
+        token
+    }
 
-     
+    // Span from the given start up to the end of the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.prev_span)
+    }
+}
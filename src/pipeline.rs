@@ -0,0 +1,193 @@
+use crate::shared::tokens::Token;
+use crate::shared::diagnostics::{Diagnostic, ErrorCode};
+use crate::shared::target::TargetInfo;
+use crate::stages::ast::ASTProgram;
+use crate::stages::hir::HIRProgram;
+use crate::stages::mir::MIRProgram;
+use crate::stages::lir::LIRProgram;
+use crate::lex::lex;
+use crate::parse::Parser;
+use crate::make_hir::HIRBuilder;
+use crate::hir_to_mir::MIRBuilder;
+use crate::mir_to_lir::LIRBuilder;
+use crate::lir_codegen::{Backend, LIRCompiler};
+use crate::lir_codegen::arm::Arm32Backend;
+use crate::lir_codegen::x86::X86Backend;
+use crate::c_codegen::CCompiler;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    Tokens,
+    Ast,
+    Hir,
+    Mir,
+    Lir,
+    Asm,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    Arm32,
+    X86_64,
+    C,
+}
+
+impl Target {
+
+    pub fn from_name(name: &str) -> Option<Target> {
+        match name {
+            "arm" | "arm32" => Some(Target::Arm32),
+            "x86_64" | "x86-64" => Some(Target::X86_64),
+            "c" => Some(Target::C),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::Arm32 => "arm32",
+            Target::X86_64 => "x86_64",
+            Target::C => "c",
+        }
+    }
+
+    // Extension of the generated code, which is what tells cc how to build it
+    pub fn source_ext(&self) -> &'static str {
+        match self {
+            Target::Arm32 | Target::X86_64 => "s",
+            Target::C => "c",
+        }
+    }
+
+    // What the compiler itself runs on, so that `run` works out of the box; ARM elsewhere
+    pub fn host() -> Target {
+        if cfg!(target_arch = "x86_64") { Target::X86_64 } else { Target::Arm32 }
+    }
+
+    // None for C, which is generated from the MIR rather than the LIR
+    pub fn backend(&self) -> Option<&'static dyn Backend> {
+        match self {
+            Target::Arm32 => Some(&Arm32Backend),
+            Target::X86_64 => Some(&X86Backend),
+            Target::C => None,
+        }
+    }
+
+    // C has no layout of its own to speak of; its LIR is laid out as if for the host
+    pub fn info(&self) -> TargetInfo {
+        match self {
+            Target::Arm32 => TargetInfo::ARM32,
+            Target::X86_64 => TargetInfo::X86_64,
+            Target::C => TargetInfo::host(),
+        }
+    }
+
+    pub fn runs_on_host(&self) -> bool {
+        match self {
+            Target::Arm32 => cfg!(target_arch = "arm"),
+            Target::X86_64 => cfg!(target_arch = "x86_64"),
+            Target::C => true,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    pub stop_after: Stage,      // Last stage to run; errors from later stages won't be reported
+    pub keep: Vec<Stage>,       // Stage outputs to hand back in the Artifacts
+    pub target: Target,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            stop_after: Stage::Asm,
+            keep: vec![Stage::Asm],
+            target: Target::host(),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct Artifacts {
+    pub tokens: Option<Vec<Token>>,
+    pub ast: Option<ASTProgram>,
+    pub hir: Option<HIRProgram>,
+    pub mir: Option<MIRProgram>,
+    pub lir: Option<LIRProgram>,
+    pub asm: Option<String>,
+}
+
+impl Artifacts {
+
+    // Textual form of a kept stage output, as printed by `dump`
+    pub fn dump(&self, stage: Stage) -> Option<String> {
+        match stage {
+            Stage::Tokens => self.tokens.as_ref().map(|x| format!("{:#?}", x)),
+            Stage::Ast => self.ast.as_ref().map(|x| format!("{:#?}", x)),
+            Stage::Hir => self.hir.as_ref().map(|x| format!("{:#?}", x)),
+            Stage::Mir => self.mir.as_ref().map(|x| format!("{:#?}", x)),
+            Stage::Lir => self.lir.as_ref().map(|x| format!("{:#?}", x)),
+            Stage::Asm => self.asm.clone(),
+        }
+    }
+}
+
+
+pub fn compile_str(source: &str, options: &CompileOptions) -> Result<Artifacts, Vec<Diagnostic>> {
+    let mut artifacts = Artifacts::default();
+    let keep = |stage: Stage| options.keep.contains(&stage);
+
+    let tokens = lex(source).map_err(|diag| vec![*diag])?;
+    if keep(Stage::Tokens) { artifacts.tokens = Some(tokens.clone()); }
+    if options.stop_after == Stage::Tokens { return Ok(artifacts); }
+
+    let (ast, mut diags) = Parser::parse_program(tokens);
+    if !diags.is_empty() {
+        // Still check what did parse, so one run surfaces as much as possible. What recovery
+        // skipped over is missing from the AST though, so complaints about missing names are
+        // more likely than not about that.
+        if let Err(diag) = HIRBuilder::lower_ast(ast)
+            && !matches!(
+                diag.code,
+                ErrorCode::MissingEntryPoint | ErrorCode::UnknownVariable | ErrorCode::UnknownFunction | ErrorCode::UnknownType
+            ) {
+            diags.push(*diag);
+        }
+        return Err(diags);
+    }
+    if keep(Stage::Ast) { artifacts.ast = Some(ast.clone()); }
+    if options.stop_after == Stage::Ast { return Ok(artifacts); }
+
+    let hir = HIRBuilder::lower_ast(ast).map_err(|diag| vec![*diag])?;
+    if keep(Stage::Hir) { artifacts.hir = Some(hir.clone()); }
+    if options.stop_after == Stage::Hir { return Ok(artifacts); }
+
+    let mir = MIRBuilder::lower_hir(hir);
+    if keep(Stage::Mir) { artifacts.mir = Some(mir.clone()); }
+    if options.stop_after == Stage::Mir { return Ok(artifacts); }
+
+    let Some(backend) = options.target.backend() else {
+        if options.stop_after == Stage::Asm && keep(Stage::Asm) {
+            artifacts.asm = Some(CCompiler::compile(&mir));
+        }
+        // Nothing further depends on the LIR, but it's still there to look at, and lowered when
+        // stopping at it so that `check` goes through the same stages whatever the target
+        if keep(Stage::Lir) || options.stop_after == Stage::Lir {
+            let lir = LIRBuilder::lower_mir(mir, options.target.info()).map_err(|diag| vec![*diag])?;
+            if keep(Stage::Lir) { artifacts.lir = Some(lir); }
+        }
+        return Ok(artifacts);
+    };
+
+    let lir = LIRBuilder::lower_mir(mir, options.target.info()).map_err(|diag| vec![*diag])?;
+    if keep(Stage::Lir) { artifacts.lir = Some(lir.clone()); }
+    if options.stop_after == Stage::Lir { return Ok(artifacts); }
+
+    if keep(Stage::Asm) { artifacts.asm = Some(LIRCompiler::compile(lir, backend)); }
+    Ok(artifacts)
+}
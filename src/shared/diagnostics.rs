@@ -0,0 +1,210 @@
+use std::fmt;
+
+use crate::shared::span::Span;
+
+
+// Boxed, since a diagnostic with its labels and notes dwarfs most of what comes back on success
+pub type CompileResult<T> = Result<T, Box<Diagnostic>>;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Bug,            // Internal compiler error: an invariant some earlier stage should have upheld
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    // Lexing
+    UnexpectedCharacter,
+    IntLiteralOverflow,
+
+    // Parsing
+    UnexpectedToken,
+    UnexpectedEof,
+    CyclicTypeDefinition,
+
+    // Name resolution
+    UnknownVariable,
+    UnknownFunction,
+    UnknownType,
+    MissingEntryPoint,
+    WrongTypeArgCount,
+    DuplicateDefinition,
+
+    // Type checking
+    TypeMismatch,
+    NonBoolCondition,
+    InvalidOperands,
+    NotAStruct,
+    NoSuchField,
+    MissingField,
+    DerefNonReference,
+    InvalidReference,
+    CannotInferTypeArgs,
+    InvalidVariant,
+    NotAnEnum,
+    NonExhaustiveMatch,
+    UnreachableArm,
+    InfiniteInstantiation,
+
+    // Control flow
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => "E0001",
+            ErrorCode::IntLiteralOverflow => "E0002",
+            ErrorCode::UnexpectedToken => "E0100",
+            ErrorCode::UnexpectedEof => "E0101",
+            ErrorCode::CyclicTypeDefinition => "E0102",
+            ErrorCode::UnknownVariable => "E0200",
+            ErrorCode::UnknownFunction => "E0201",
+            ErrorCode::UnknownType => "E0202",
+            ErrorCode::MissingEntryPoint => "E0203",
+            ErrorCode::WrongTypeArgCount => "E0204",
+            ErrorCode::DuplicateDefinition => "E0205",
+            ErrorCode::TypeMismatch => "E0300",
+            ErrorCode::NonBoolCondition => "E0301",
+            ErrorCode::InvalidOperands => "E0302",
+            ErrorCode::NotAStruct => "E0303",
+            ErrorCode::NoSuchField => "E0304",
+            ErrorCode::MissingField => "E0305",
+            ErrorCode::DerefNonReference => "E0306",
+            ErrorCode::InvalidReference => "E0307",
+            ErrorCode::CannotInferTypeArgs => "E0308",
+            ErrorCode::InvalidVariant => "E0309",
+            ErrorCode::NotAnEnum => "E0310",
+            ErrorCode::NonExhaustiveMatch => "E0311",
+            ErrorCode::UnreachableArm => "E0312",
+            ErrorCode::InfiniteInstantiation => "E0313",
+            ErrorCode::BreakOutsideLoop => "E0400",
+            ErrorCode::ContinueOutsideLoop => "E0401",
+            ErrorCode::Internal => "E9999",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: ErrorCode,
+    pub message: String,
+    pub primary: Label,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+
+    pub fn error(code: ErrorCode, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            primary: Label { span, message: String::new() },
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn bug(message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Bug,
+            ..Diagnostic::error(ErrorCode::Internal, message, span)
+        }
+    }
+
+    // Text printed under the carets of the primary span
+    pub fn with_primary_label(mut self, message: impl Into<String>) -> Self {
+        self.primary.message = message.into();
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into() });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity != Severity::Warning
+    }
+
+    pub fn render(&self, source: &str, filename: &str) -> String {
+        let header = match self.severity {
+            Severity::Error => format!("error[{}]", self.code),
+            Severity::Warning => format!("warning[{}]", self.code),
+            Severity::Bug => "internal compiler error".to_string(),
+        };
+        let gutter = [&self.primary].into_iter()
+            .chain(self.labels.iter())
+            .map(|label| label.span.line.to_string().len())
+            .max()
+            .unwrap();
+        let pad = " ".repeat(gutter);
+
+        let mut out = format!("{}: {}\n", header, self.message);
+        out.push_str(&format!("{}--> {}:{}:{}\n", pad, filename, self.primary.span.line, self.primary.span.col));
+        out.push_str(&format!("{} |\n", pad));
+        render_label(&mut out, source, &self.primary, '^', gutter);
+        for label in &self.labels {
+            render_label(&mut out, source, label, '-', gutter);
+        }
+        if !self.notes.is_empty() {
+            out.push_str(&format!("{} |\n", pad));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", pad, note));
+        }
+        out
+    }
+}
+
+
+// Prints the source line a span starts on, underlined up to the span end (or the end of that line)
+fn render_label(out: &mut String, source: &str, label: &Label, marker: char, gutter: usize) {
+    let start = label.span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = source[line_start..].find('\n').map(|i| line_start + i).unwrap_or(source.len());
+    let line = source[line_start..line_end].trim_end_matches('\r');
+
+    let indent: String = source[line_start..start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let end = label.span.end.clamp(start, line_start + line.len());
+    let width = source[start..end].chars().count().max(1);
+
+    out.push_str(&format!("{:>gutter$} | {}\n", label.span.line, line));
+    let mut underline = format!("{} | {}{}", " ".repeat(gutter), indent, marker.to_string().repeat(width));
+    if !label.message.is_empty() {
+        underline.push(' ');
+        underline.push_str(&label.message);
+    }
+    out.push_str(&underline);
+    out.push('\n');
+}
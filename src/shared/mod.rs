@@ -3,4 +3,6 @@ pub mod tokens;
 pub mod binops;
 pub mod tables;
 pub mod utils;
-
+pub mod span;
+pub mod diagnostics;
+pub mod target;
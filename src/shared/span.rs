@@ -0,0 +1,28 @@
+
+
+// Byte range into the source text, plus the line and column (both 1-based) where it starts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+
+    pub fn new(start: usize, end: usize, line: usize, col: usize) -> Self {
+        Span { start, end, line, col }
+    }
+
+    // Smallest span covering both, keeping the position of whichever starts first
+    pub fn to(self, other: Span) -> Span {
+        let (first, _) = if self.start <= other.start { (self, other) } else { (other, self) };
+        Span {
+            start: first.start,
+            end: self.end.max(other.end),
+            line: first.line,
+            col: first.col,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::{BTreeMap, HashMap, VecDeque}, hash::Hash};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use crate::shared::typing::*;
 
 
@@ -6,34 +6,64 @@ use crate::shared::typing::*;
 #[derive(Debug, Clone)]
 pub struct GenericTypetable {
     topo_order: Vec<NewtypeId>,
-    monomorphizations: HashMap<NewtypeId, HashMap<Vec<ConcreteType>,ConcreteShape>>, // Change for a HashMap to avoid duplicates
-    pub defs: HashMap<NewtypeId, GenericTypeDef>,
+    monomorphizations: BTreeMap<NewtypeId, BTreeMap<Vec<ConcreteType>,ConcreteShape>>,
+    pub defs: BTreeMap<NewtypeId, GenericTypeDef>,
 }
 
 impl GenericTypetable {
 
-    pub fn new(defs: HashMap<NewtypeId, GenericTypeDef>) -> Self {
-        Self { 
-            topo_order: toposort_depgraph(extract_newtype_dependencies(&defs)), 
-            monomorphizations: defs.iter().map(|(id, _)| (id.clone(), HashMap::new())).collect(),
+    // Fails with the types involved in a definition cycle, if there is one
+    pub fn new(defs: BTreeMap<NewtypeId, GenericTypeDef>) -> Result<Self, Vec<NewtypeId>> {
+        Ok(Self { 
+            topo_order: toposort_depgraph(extract_newtype_dependencies(&defs))?, 
+            monomorphizations: defs.keys().map(|id| (id.clone(), BTreeMap::new())).collect(),
             defs,
-        }
+        })
     }
 
     pub fn get_mono(&self, id: NewtypeId, tvars: Vec<ConcreteType>) -> ConcreteShape {
         self.monomorphizations[&id][&tvars].clone()
     }
 
+    pub fn is_monomorphized(&self, id: &NewtypeId, tvars: &Vec<ConcreteType>) -> bool {
+        self.monomorphizations[id].contains_key(tvars)
+    }
+
+    // Every instance comes after the ones its fields are. The definition order alone doesn't see
+    // through type params: `Tagged[Pair[int]]` needs `Pair[int]` first, whichever of the two
+    // definitions does.
     pub fn topo_mono_iter(&self) -> impl Iterator<Item = (NewtypeId, Vec<ConcreteType>, ConcreteShape)> {
-        let mut monomorphizations = self.monomorphizations.clone();
-        self.topo_order
-            .iter()
-            .flat_map(move |id| {
-                monomorphizations.remove(&id)
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(move |(tvars, shape)| (id.clone(), tvars, shape))
-            })
+        let mut done: BTreeSet<(NewtypeId, Vec<ConcreteType>)> = BTreeSet::new();
+        let mut order = Vec::new();
+        for id in &self.topo_order {
+            for tvars in self.monomorphizations[id].keys() {
+                self.visit_mono(id, tvars, &mut done, &mut order);
+            }
+        }
+        order.into_iter()
+    }
+
+    fn visit_mono(
+        &self,
+        id: &NewtypeId,
+        tvars: &Vec<ConcreteType>,
+        done: &mut BTreeSet<(NewtypeId, Vec<ConcreteType>)>,
+        order: &mut Vec<(NewtypeId, Vec<ConcreteType>, ConcreteShape)>,
+    ) {
+        if !done.insert((id.clone(), tvars.clone())) {
+            return;
+        }
+        let shape = &self.monomorphizations[id][tvars];
+        let parts: Vec<&ConcreteType> = match shape {
+            ConcreteShape::Struct { fields } => fields.values().collect(),
+            ConcreteShape::Enum { variants } => variants.iter().collect(),
+        };
+        for part in parts {
+            if let ConcreteType::NewType(pid, ptvars) = part {
+                self.visit_mono(pid, ptvars, done, order);
+            }
+        }
+        order.push((id.clone(), tvars.clone(), shape.clone()));
     }
 
     pub fn monomorphize(
@@ -56,30 +86,52 @@ impl GenericTypetable {
                         .collect()
                 }
             }
-            NewtypeShape::Enum { variants } => unimplemented!()
+            NewtypeShape::Enum { variants } => {
+                NewtypeShape::Enum {
+                    variants: variants
+                        .iter()
+                        .map(|typ| typ.monomorphize(&bindings))
+                        .collect()
+                }
+            }
         };
         self.monomorphizations.get_mut(&id).unwrap().insert(typ_var_vals,monomorph.clone());
         monomorph
     }
+
+    // Which of an enum's variants a type is, if it's one. Doesn't need the enum monomorphized.
+    pub fn variant_index(&self, enum_typ: &ConcreteType, typ: &ConcreteType) -> Option<usize> {
+        let ConcreteType::NewType(id, tvars) = enum_typ else {
+            return None;
+        };
+        let def = self.defs.get(id)?;
+        let NewtypeShape::Enum { variants } = &def.defn else {
+            return None;
+        };
+        let bindings: BTreeMap<String, ConcreteType> = def.type_params
+            .iter()
+            .cloned()
+            .zip(tvars.iter().cloned())
+            .collect();
+        variants.iter().position(|variant| variant.monomorphize(&bindings) == *typ)
+    }
 }
 
 
 
 
 
-fn extract_newtype_dependencies(newtype_defs: &HashMap<NewtypeId, GenericTypeDef>) -> HashMap<NewtypeId, Vec<NewtypeId>> {
-    let mut dep_graph: HashMap<NewtypeId, Vec<NewtypeId>> = HashMap::new();
+fn extract_newtype_dependencies(newtype_defs: &BTreeMap<NewtypeId, GenericTypeDef>) -> BTreeMap<NewtypeId, Vec<NewtypeId>> {
+    let mut dep_graph: BTreeMap<NewtypeId, Vec<NewtypeId>> = BTreeMap::new();
     for (type_id, newtype) in newtype_defs {
         let deps: Vec<NewtypeId> = match &newtype.defn {
             NewtypeShape::Struct {fields} => fields
                 .values()
-                .map(|ftyp| extract_type_id(&ftyp))
-                .flatten()
+                .flat_map(extract_type_id)
                 .collect(),
             NewtypeShape::Enum { variants } => variants
                 .iter()
-                .map(|vtyp| extract_type_id(&vtyp))
-                .flatten()
+                .flat_map(extract_type_id)
                 .collect(),
         };
         dep_graph.insert(type_id.clone(), deps);
@@ -92,19 +144,19 @@ fn extract_type_id(t: &GenericType) -> Vec<NewtypeId> {
     match t {
         GenericType::Prim(..) => vec![],
         GenericType::NewType(id, t_params) => {
-            let mut deps: Vec<NewtypeId> = t_params.iter().map(|p| extract_type_id(p)).flatten().collect::<Vec<_>>();
+            let mut deps: Vec<NewtypeId> = t_params.iter().flat_map(extract_type_id).collect::<Vec<_>>();
             deps.push(id.clone());
             deps
         }
-        GenericType::Reference(typ) => extract_type_id(&typ),
+        GenericType::Reference(..) => vec![],       // Pointee layout doesn't matter, so self-reference is fine
         GenericType::TypeVar(..) => vec![] 
     }
 }
 
 
-fn toposort_depgraph<T: Clone + Eq + PartialEq + Hash>(depgraph: HashMap<T, Vec<T>>) -> Vec<T> {
+fn toposort_depgraph<T: Clone + Ord>(depgraph: BTreeMap<T, Vec<T>>) -> Result<Vec<T>, Vec<T>> {
 
-    let mut indegrees: HashMap<T, usize> = depgraph
+    let mut indegrees: BTreeMap<T, usize> = depgraph
         .keys()
         .map(|k| (k.clone(),0))
         .collect();
@@ -137,8 +189,12 @@ fn toposort_depgraph<T: Clone + Eq + PartialEq + Hash>(depgraph: HashMap<T, Vec<
         }
     }
     if result.len() != indegrees.len() {
-        panic!("Cycle detected in type definitions");
+        return Err(indegrees
+            .into_iter()
+            .filter(|(_, deg)| *deg > 0)
+            .map(|(node, _)| node)
+            .collect());
     }
     result.reverse();
-    result
+    Ok(result)
 }
@@ -0,0 +1,88 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+
+// What layout needs to know about the machine. Sizes and alignments are in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TargetInfo {
+    pub pointer_size: usize,
+    pub pointer_align: usize,
+    pub int_size: usize,
+    pub int_align: usize,
+    pub bool_size: usize,
+    pub bool_align: usize,
+    pub word_size: usize,           // Width of a general purpose register
+    pub stack_align: usize,         // What the ABI wants sp aligned to at calls
+    pub ret_regs_size: usize,       // How big a value can come back in registers
+    pub endianness: Endianness,
+}
+
+impl TargetInfo {
+
+    pub const ARM32: TargetInfo = TargetInfo {
+        pointer_size: 4,
+        pointer_align: 4,
+        int_size: 4,
+        int_align: 4,
+        bool_size: 1,
+        bool_align: 1,
+        word_size: 4,
+        stack_align: 8,
+        ret_regs_size: 4,
+        endianness: Endianness::Little,
+    };
+
+    pub const X86_64: TargetInfo = TargetInfo {
+        pointer_size: 8,
+        pointer_align: 8,
+        int_size: 4,
+        int_align: 4,
+        bool_size: 1,
+        bool_align: 1,
+        word_size: 8,
+        stack_align: 16,
+        ret_regs_size: 16,
+        endianness: Endianness::Little,
+    };
+
+    pub fn host() -> TargetInfo {
+        if cfg!(target_arch = "x86_64") { TargetInfo::X86_64 } else { TargetInfo::ARM32 }
+    }
+
+    // Whether a value of this size moves through a register in one go: one of the scalar sizes,
+    // or a struct exactly that big. Anything else gets copied memory to memory.
+    pub fn fits_register(&self, size: usize) -> bool {
+        [0, self.bool_size, self.int_size, self.pointer_size, self.word_size].contains(&size)
+    }
+
+    // Whether a return value this big comes back through a slot the caller passes in, rather
+    // than in the return registers
+    pub fn returns_in_slot(&self, size: usize) -> bool {
+        size > self.ret_regs_size
+    }
+
+    // Little-endian or big-endian bytes of num, sign-extended or truncated to size
+    pub fn int_to_bytes(&self, num: i64, size: usize) -> Vec<u8> {
+        let fill = if num < 0 { 0xff } else { 0 };
+        let mut bytes = num.to_le_bytes().to_vec();
+        bytes.resize(size, fill);
+        if self.endianness == Endianness::Big {
+            bytes.reverse();
+        }
+        bytes
+    }
+
+    pub fn bytes_to_int(&self, bytes: &[u8]) -> i64 {
+        let mut bytes = bytes.to_vec();
+        if self.endianness == Endianness::Big {
+            bytes.reverse();
+        }
+        bytes.truncate(8);
+        let fill = if bytes.last().is_some_and(|b| b & 0x80 != 0) { 0xff } else { 0 };
+        bytes.resize(8, fill);
+        i64::from_le_bytes(bytes.try_into().unwrap())
+    }
+}
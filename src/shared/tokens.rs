@@ -1,8 +1,16 @@
 use crate::shared::binops::*;
+use crate::shared::span::Span;
+use std::fmt;
 
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Token {
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
     // Operators
     Assign,
     Plus,
@@ -22,7 +30,10 @@ pub enum Token {
     RightSqBracket,
     Comma,
     Colon,
+    DoubleColon,
     Dot,
+    FatArrow,
+    Underscore,
 
     // Values 
     Identifier(String),
@@ -44,6 +55,8 @@ pub enum Token {
     Return,
     Let,
     Struct,
+    Enum,
+    Match,
     
     // Type stuff
     Int, 
@@ -52,29 +65,80 @@ pub enum Token {
 
     Ref,
     Deref,
+
+    Eof,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            TokenKind::Assign => "=",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Multiply => "*",
+            TokenKind::Equals => "==",
+            TokenKind::Less => "<",
+            TokenKind::Modulo => "%",
+            TokenKind::Semicolon => ";",
+            TokenKind::LeftParen => "(",
+            TokenKind::RightParen => ")",
+            TokenKind::LeftBrace => "{",
+            TokenKind::RightBrace => "}",
+            TokenKind::LeftSqBracket => "[",
+            TokenKind::RightSqBracket => "]",
+            TokenKind::Comma => ",",
+            TokenKind::Colon => ":",
+            TokenKind::DoubleColon => "::",
+            TokenKind::Dot => ".",
+            TokenKind::FatArrow => "=>",
+            TokenKind::Underscore => "_",
+            TokenKind::Identifier(name) => return write!(f, "identifier `{}`", name),
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::IntLiteral(num) => return write!(f, "literal `{}`", num),
+            TokenKind::Print => "print",
+            TokenKind::If => "if",
+            TokenKind::Else => "else",
+            TokenKind::While => "while",
+            TokenKind::Break => "break",
+            TokenKind::Continue => "continue",
+            TokenKind::Function => "fun",
+            TokenKind::Return => "return",
+            TokenKind::Let => "let",
+            TokenKind::Struct => "struct",
+            TokenKind::Enum => "enum",
+            TokenKind::Match => "match",
+            TokenKind::Int => "int",
+            TokenKind::Bool => "bool",
+            TokenKind::RightArrow => "->",
+            TokenKind::Ref => "&",
+            TokenKind::Deref => "?",
+            TokenKind::Eof => return write!(f, "end of file"),
+        };
+        write!(f, "`{}`", text)
+    }
 }
 
 
 
 
-pub fn get_connector_precedence(op_token: &Token) -> usize {
+pub fn get_connector_precedence(op_token: &TokenKind) -> usize {
     match *op_token {
-        Token::Dot => 3, 
-        Token::Multiply | Token::Modulo => 2,
-        Token::Plus| Token::Minus => 1,
-        Token::Equals | Token::Less => 0,
+        TokenKind::Multiply | TokenKind::Modulo => 2,
+        TokenKind::Plus| TokenKind::Minus => 1,
+        TokenKind::Equals | TokenKind::Less => 0,
         _ => panic!("Expected binary operator token, found: {:?}", op_token), 
     }
 }
 
-pub fn map_binop_token(op_token: &Token) -> BinaryOperator {
+pub fn map_binop_token(op_token: &TokenKind) -> BinaryOperator {
     match *op_token {
-        Token::Plus => BinaryOperator::Add,
-        Token::Minus => BinaryOperator::Sub,
-        Token::Multiply => BinaryOperator::Mul,
-        Token::Equals => BinaryOperator::Equals,
-        Token::Less => BinaryOperator::Less,
-        Token::Modulo => BinaryOperator::Modulo,
+        TokenKind::Plus => BinaryOperator::Add,
+        TokenKind::Minus => BinaryOperator::Sub,
+        TokenKind::Multiply => BinaryOperator::Mul,
+        TokenKind::Equals => BinaryOperator::Equals,
+        TokenKind::Less => BinaryOperator::Less,
+        TokenKind::Modulo => BinaryOperator::Modulo,
         _ => panic!("Expected binary operator token, found: {:?}", op_token), 
 
     }
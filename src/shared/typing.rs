@@ -1,13 +1,24 @@
 use std::collections::{BTreeMap};
+use std::fmt;
 
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ConcreteType {
     Prim(PrimType),
     NewType(NewtypeId, Vec<ConcreteType>),
     Reference(Box<ConcreteType>),
 }
 
+impl fmt::Display for ConcreteType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prim(prim_typ) => write!(f, "{}", prim_typ),
+            Self::NewType(id, params) => write!(f, "{}", format_newtype(id, params)),
+            Self::Reference(typ) => write!(f, "&{}", typ),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum GenericType {                      
     // This represent basically what we put in type annots and stuff, NOT the typedefs
@@ -33,6 +44,55 @@ impl GenericType {
             }
         }
     }
+
+    // Matches this against a concrete type, binding the type vars on the way. False if they
+    // don't fit, including when a var would have to be two different types.
+    pub fn infer_bindings(&self, concrete: &ConcreteType, bindings: &mut BTreeMap<String, ConcreteType>) -> bool {
+        match (self, concrete) {
+            (Self::Prim(prim_typ), ConcreteType::Prim(conc_prim)) => prim_typ == conc_prim,
+            (Self::NewType(id, gen_params), ConcreteType::NewType(conc_id, conc_params)) => {
+                id == conc_id
+                    && gen_params.len() == conc_params.len()
+                    && gen_params.iter().zip(conc_params).all(|(p, c)| p.infer_bindings(c, bindings))
+            }
+            (Self::Reference(typ), ConcreteType::Reference(conc_typ)) => typ.infer_bindings(conc_typ, bindings),
+            (Self::TypeVar(id), _) => match bindings.get(id) {
+                Some(bound) => bound == concrete,
+                None => {
+                    bindings.insert(id.clone(), concrete.clone());
+                    true
+                }
+            },
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for GenericType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prim(prim_typ) => write!(f, "{}", prim_typ),
+            Self::NewType(id, params) => write!(f, "{}", format_newtype(id, params)),
+            Self::Reference(typ) => write!(f, "&{}", typ),
+            Self::TypeVar(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+// A namespaced variant shares its enum's params, so they go where the enum's name is
+fn format_newtype<T: fmt::Display>(id: &NewtypeId, params: &[T]) -> String {
+    match id.0.split_once("::") {
+        Some((enum_name, variant)) => format!("{}{}::{}", enum_name, format_type_params(params), variant),
+        None => format!("{}{}", id.0, format_type_params(params)),
+    }
+}
+
+fn format_type_params<T: fmt::Display>(params: &[T]) -> String {
+    if params.is_empty() {
+        return String::new();
+    }
+    let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+    format!("[{}]", params.join(", "))
 }
 
 
@@ -61,13 +121,23 @@ pub enum NewtypeShape<T>{
 }
 
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy, PartialOrd, Ord)]
 pub enum PrimType {
     Integer,
     Bool,
     None,
 }
 
+impl fmt::Display for PrimType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrimType::Integer => write!(f, "int"),
+            PrimType::Bool => write!(f, "bool"),
+            PrimType::None => write!(f, "()"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Binding(pub BTreeMap<String, ConcreteType>);
 
@@ -77,8 +147,22 @@ impl Binding {
     }
 }
 
-#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, PartialOrd, Ord)]
 pub struct NewtypeId(pub String); 
 
+impl NewtypeId {
+
+    // The payload type of a namespaced variant, `R::D`. User types can't clash with it, `:` being no
+    // part of an identifier.
+    pub fn variant(enum_id: &NewtypeId, name: &str) -> NewtypeId {
+        NewtypeId(format!("{}::{}", enum_id.0, name))
+    }
+
+    // The enum a namespaced variant belongs to
+    pub fn enum_of(&self) -> Option<NewtypeId> {
+        self.0.split_once("::").map(|(enum_name, _)| NewtypeId(enum_name.to_string()))
+    }
+}
+
 
 
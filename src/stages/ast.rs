@@ -2,33 +2,34 @@ use crate::shared::typing::*;
 use crate::shared::binops::*;
 use crate::shared::tables::*;
 use crate::shared::utils::*;
-
-use std::collections::HashMap;
+use crate::shared::span::Span;
 
 
 #[derive(Debug, Clone)]
 pub struct ASTProgram {
     pub typetable: GenericTypetable,
-    pub functions: HashMap<ConcreteFuncSignature, ASTFunction>,
+    pub functions: Vec<ASTFunction>,
 }
 
 
 #[derive(Debug, Clone)]
 pub struct ASTFunction {
     pub name: String,
-    pub args: HashMap<String, ConcreteType>,    // Does this lose argument order?
+    pub type_params: Vec<String>,
+    pub args: Vec<(String, GenericType)>,
     pub body: Vec<ASTStatement>,
-    pub ret_type: ConcreteType,
+    pub ret_type: GenericType,
+    pub span: Span,
 }
 
 impl ASTFunction {
    
-    pub fn get_signature(&self) -> ConcreteFuncSignature {
+    pub fn get_signature(&self) -> GenericFuncSignature {
         FuncSignature { 
             name: self.name.clone(), 
             argtypes: self.args
                 .iter()
-                .map(|(_, argt)| argt.clone())
+                .map(|(_, typ)| typ.clone())
                 .collect()
         }
     }
@@ -37,9 +38,15 @@ impl ASTFunction {
 
 
 #[derive(Debug, Clone)]
-pub enum ASTStatement {
+pub struct ASTStatement {
+    pub stmt: ASTStatementKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ASTStatementKind {
     Let {
-        var: ConcreteVariable,
+        var: GenTypeVariable,
         value: ASTExpression,
     },
     Assign {
@@ -55,10 +62,45 @@ pub enum ASTStatement {
         condition: ASTExpression,
         body: Vec<ASTStatement>,
     },
+    Match {
+        scrutinee: ASTExpression,
+        arms: Vec<ASTMatchArm>,
+    },
     Break,
     Continue,
     Return(ASTExpression),
     Print(ASTExpression),
+    Call(ASTExpression),        // Whatever it returns is dropped
+}
+
+#[derive(Debug, Clone)]
+pub struct ASTMatchArm {
+    pub pattern: ASTPattern,
+    pub body: Vec<ASTStatement>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ASTPattern {
+    pub pat: ASTPatternKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ASTPatternKind {
+    Wildcard,
+    Name(String),           // A type if there's one called that, otherwise a binding
+    IntLiteral(i32),
+    BoolTrue,
+    BoolFalse,
+    Type(GenericType),      // Values of this type, or of this variant
+    Struct {
+        typ: GenericType,
+        fields: Vec<(String, ASTPattern)>,      // Fields left out match anything
+    },
+    Positional {            // Of a namespaced variant, `R::D(a, b)`
+        typ: GenericType,
+        fields: Vec<ASTPattern>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -72,7 +114,13 @@ pub enum ASTLValue {        // Rename to ASTPlace
 }
 
 #[derive(Debug, Clone)]
-pub enum ASTExpression {
+pub struct ASTExpression {
+    pub expr: ASTExpressionKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ASTExpressionKind {
     IntLiteral(i32),
     Variable(String),
     BinOp {
@@ -82,6 +130,7 @@ pub enum ASTExpression {
     },
     FuncCall {
         funcname: String,
+        type_args: Vec<GenericType>,
         args: Vec<ASTExpression>,
     },
     BoolTrue,
@@ -93,8 +142,8 @@ pub enum ASTExpression {
     },
 
     StructLiteral {
-        typ: ConcreteType,
-        fields: HashMap<String, ASTExpression>,
+        typ: GenericType,
+        fields: Vec<(String, ASTExpression)>,
     },
 
     Reference(Box<ASTExpression>),
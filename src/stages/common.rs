@@ -1,9 +1,17 @@
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, PartialOrd, Ord)]
 pub struct FuncId(pub usize);
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Copy)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Copy, PartialOrd, Ord)]
 pub struct BlockId(pub usize);
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy, PartialOrd, Ord)]
 pub struct CellId(pub usize);
+
+// One step from a value into a part of it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Projection {
+    Field(String),
+    Tag,                // Which variant an enum value is, as an int
+    Payload(usize),     // An enum value as the variant it is, which has to be this one
+}
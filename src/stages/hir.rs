@@ -3,13 +3,14 @@ use crate::shared::binops::*;
 use crate::stages::common::FuncId;
 use crate::shared::tables::*;
 use crate::shared::utils::*;
+use crate::shared::span::Span;
 
-use std::{collections::HashMap};
+use std::collections::BTreeMap;
 
 #[derive(Clone, Debug)]
 pub struct HIRProgram {
     pub typetable: GenericTypetable, 
-    pub functions: HashMap<FuncId, HIRFunction>,
+    pub functions: BTreeMap<FuncId, HIRFunction>,
     pub entry: FuncId,
 }
 
@@ -18,14 +19,20 @@ pub struct HIRProgram {
 pub struct HIRFunction {
     pub name: String,
     pub args: Vec<VarId>,
-    pub variables: HashMap<VarId, ConcreteVariable>,
+    pub variables: BTreeMap<VarId, ConcreteVariable>,
     pub body: Vec<HIRStatement>,
     pub ret_type: ConcreteType,
 }
 
 
 #[derive(Clone, Debug)]
-pub enum HIRStatement {
+pub struct HIRStatement {
+    pub stmt: HIRStatementKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum HIRStatementKind {
     Let {
         var: VarId,
         value: HIRExpression,
@@ -47,12 +54,46 @@ pub enum HIRStatement {
     Continue,
     Return(Option<HIRExpression>),
     Print(HIRExpression),
+    Call(HIRExpression),
+    Match {
+        scrutinee: HIRExpression,
+        arms: Vec<HIRMatchArm>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct HIRMatchArm {
+    pub pattern: HIRPattern,
+    pub body: Vec<HIRStatement>,
+}
+
+#[derive(Clone, Debug)]
+pub struct HIRPattern {
+    pub typ: ConcreteType,      // Of the values it's matched against
+    pub pat: HIRPatternKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum HIRPatternKind {
+    Wildcard,
+    Binding(VarId),
+    IntLiteral(i32),
+    BoolTrue,
+    BoolFalse,
+    Variant {
+        variant: usize,         // Index into the enum's variants
+        payload: Box<HIRPattern>,
+    },
+    Struct {
+        fields: Vec<(String, HIRPattern)>,      // All of them, in the type's order
+    },
 }
 
 #[derive(Debug, Clone)]
 pub struct HIRExpression {
     pub typ: ConcreteType,
     pub expr: HIRExpressionKind,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -77,7 +118,16 @@ pub enum HIRExpressionKind {
     },
 
     StructLiteral {
-        fields: HashMap<String, HIRExpression>,
+        fields: Vec<(String, HIRExpression)>,      // In source order, which is also evaluation order
+    },
+    EnumLiteral {
+        variant: usize,             // Index into the enum's variants
+        payload: Box<HIRExpression>,
+    },
+    // An enum value known to be of this variant, as that variant
+    VariantPayload {
+        expr: Box<HIRExpression>,
+        variant: usize,
     },
     Reference(Box<HIRExpression>),
     Dereference(Box<HIRExpression>),
@@ -96,9 +146,13 @@ pub enum PlaceKind {
         of: Box<Place>,
         field: String,
     },
+    VariantPayload {
+        of: Box<Place>,
+        variant: usize,
+    },
     Deref(HIRExpression),
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, PartialOrd, Ord)]
 pub struct VarId(pub usize);
 
@@ -1,21 +1,26 @@
 use crate::shared::binops::*;
+use crate::shared::target::TargetInfo;
 use crate::stages::common::*;
 
-use std::{collections::HashMap};
+use std::collections::BTreeMap;
 
 
 #[derive(Clone, Debug)]
 pub struct LIRProgram {
-    pub functions: HashMap<FuncId, LIRFunction>,
-    pub entry: FuncId
+    pub functions: BTreeMap<FuncId, LIRFunction>,
+    pub entry: FuncId,
+    pub target: TargetInfo,         // What the sizes and offsets below were computed for
+    pub exit_code: bool,            // Whether the entry function's result is the exit code, as ints and bools are
 }
 
 #[derive(Clone, Debug)]
 pub struct LIRFunction {
-    pub blocks: HashMap<BlockId, LIRBlock>,
+    pub blocks: BTreeMap<BlockId, LIRBlock>,
     pub entry: BlockId,
-    pub chunks: HashMap<ChunkId, Chunk>,
-    pub args: Vec<ChunkId>
+    pub chunks: BTreeMap<ChunkId, Chunk>,
+    pub vregs: BTreeMap<VRegId, Chunk>,     // What each would take up if it ends up on the stack
+    pub params: Vec<LIRPlace>,              // Where the prologue puts each arg it's called with
+    pub ret_size: usize,                    // Of the declared return type, slot or not
 }
 
 #[derive(Clone, Debug)]
@@ -38,10 +43,15 @@ pub enum LIRStatement {
         left: LIRValue,
         right: LIRValue,
     },
+    // Args are passed by value, wherever the platform's calling convention puts them, and end up
+    // in the callee's params. Results too big for the return registers come back through a return
+    // slot instead: a pointer to it goes in as an extra first arg, and there's no dest.
+    // Calls move args, params, dests and return values a whole word at a time, so any of those
+    // that aren't scalars live in chunks padded out to whole words.
     Call {
-        dest: LIRPlace,
+        dest: Option<LIRPlace>,
         func: FuncId,
-        args: Vec<LIRPlace>,
+        args: Vec<LIRValue>,
     },
     Print(LIRValue),
 }
@@ -92,17 +102,19 @@ pub enum LIRPlaceKind {
     Deref {
         pointer: ChunkId,
         offset: usize,
-    }
+    },
+    // A scalar nothing takes the address of, so it can live in a register. Always used whole.
+    VReg(VRegId),
 }
 
 #[derive(Clone, Debug)]
 pub struct Chunk {
     pub size: usize,
-    // TODO: align or whatever
+    pub align: usize,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy, PartialOrd, Ord)]
 pub struct ChunkId(pub usize);
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy, PartialOrd, Ord)]
 pub struct VRegId(pub usize);
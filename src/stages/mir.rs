@@ -1,14 +1,15 @@
 use crate::shared::{binops::BinaryOperator, tables::GenericTypetable};
 use crate::shared::typing::*;
 use crate::stages::common::*;
+use crate::shared::span::Span;
 
-use std::{collections::HashMap};
+use std::collections::BTreeMap;
 
 
 #[derive(Clone, Debug)]
 pub struct MIRProgram {
     pub typetable: GenericTypetable,
-    pub functions: HashMap<FuncId, MIRFunction>,
+    pub functions: BTreeMap<FuncId, MIRFunction>,
     pub entry: FuncId,
 }
 
@@ -16,8 +17,8 @@ pub struct MIRProgram {
 pub struct MIRFunction {
     pub name: String,
     pub args: Vec<CellId>,
-    pub cells: HashMap<CellId, Cell>,
-    pub blocks: HashMap<BlockId, MIRBlock>,
+    pub cells: BTreeMap<CellId, Cell>,
+    pub blocks: BTreeMap<BlockId, MIRBlock>,
     pub entry: BlockId,
     pub ret_type: ConcreteType,
 }
@@ -29,7 +30,13 @@ pub struct MIRBlock {
 }
 
 #[derive(Clone, Debug)]
-pub enum MIRStatement {
+pub struct MIRStatement {
+    pub stmt: MIRStatementKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum MIRStatementKind {
     Assign {
         target: MIRPlace,
         value: MIRValue, 
@@ -48,6 +55,12 @@ pub enum MIRStatement {
     Print(MIRValue),
 }
 
+impl MIRStatementKind {
+    pub fn at(self, span: Span) -> MIRStatement {
+        MIRStatement { stmt: self, span }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum MIRTerminator {
     Goto(BlockId),
@@ -73,7 +86,12 @@ pub enum MIRValueKind {
     BoolFalse,
     StructLiteral {
         typ: ConcreteType,
-        fields: HashMap<String, MIRValue>,
+        fields: Vec<(String, MIRValue)>,
+    },
+    EnumLiteral {
+        typ: ConcreteType,
+        variant: usize,
+        payload: Box<MIRValue>,
     },
     Reference(MIRPlace),
 }
@@ -83,7 +101,7 @@ pub enum MIRValueKind {
 pub struct MIRPlace {
     pub typ: ConcreteType,
     pub base: MIRPlaceBase,
-    pub fieldchain: Vec<String>
+    pub fieldchain: Vec<Projection>
 }
 
 #[derive(Clone, Debug)]
@@ -0,0 +1,185 @@
+.global main
+.extern printf
+.align 8
+.data
+fmt: .asciz "%d\n"
+.text
+func_0:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #40
+    str r4, [fp, #-20]
+    str r5, [fp, #-24]
+    str r6, [fp, #-28]
+    str r7, [fp, #-32]
+    str r8, [fp, #-36]
+    str r9, [fp, #-40]
+    str r0, [fp, #-4]
+    str r1, [fp, #-8]
+    str r2, [fp, #-12]
+    str r3, [fp, #-16]
+    b block_7
+block_1:
+    mov r0, r5
+    mov r1, r0
+    mov r0, r7
+    add r0, r1, r0
+    mov r8, r0
+    mov r0, r8
+    mov r8, r0
+    mov r0, r4
+    cmp r0, #1
+    beq block_3
+    b block_2
+block_2:
+    mov r0, r6
+    cmp r0, #1
+    beq block_5
+    b block_4
+block_3:
+    mov r0, r8
+    mov r1, r0
+    ldr r0, =1
+    add r0, r1, r0
+    mov r9, r0
+    mov r0, r9
+    mov r8, r0
+    b block_2
+block_4:
+    mov r0, r8
+    b ret_0
+block_5:
+    mov r0, r8
+    mov r1, r0
+    ldr r0, =1
+    add r0, r1, r0
+    mov r9, r0
+    mov r0, r9
+    mov r8, r0
+    b block_4
+block_7:
+    ldrb r0, [fp, #-4]
+    mov r4, r0
+    ldr r0, [fp, #-8]
+    mov r5, r0
+    ldrb r0, [fp, #-12]
+    mov r6, r0
+    ldr r0, [fp, #-16]
+    mov r7, r0
+    b block_1
+ret_0:
+    ldr r4, [fp, #-20]
+    ldr r5, [fp, #-24]
+    ldr r6, [fp, #-28]
+    ldr r7, [fp, #-32]
+    ldr r8, [fp, #-36]
+    ldr r9, [fp, #-40]
+    add sp, sp, #40
+    pop {fp, lr}
+    bx lr
+func_1:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #48
+    str r4, [fp, #-40]
+    str r5, [fp, #-44]
+    b block_8
+block_6:
+    ldr r0, =1
+    mov r4, r0
+    ldr r0, =5
+    str r0, [fp, #-4]
+    ldr r0, =0
+    mov r5, r0
+    mov r0, r5
+    strb r0, [fp, #-28]
+    mov r0, r4
+    strb r0, [fp, #-24]
+    ldr r0, [fp, #-4]
+    str r0, [fp, #-20]
+    mov r0, r5
+    strb r0, [fp, #-16]
+    sub r0, fp, #4
+    str r0, [fp, #-12]
+    mov r0, r4
+    strb r0, [fp, #-8]
+    ldrb r0, [fp, #-8]
+    mov r1, r0
+    ldrb r0, [fp, #-24]
+    cmp r1, r0
+    mov r0, #0
+    moveq r0, #1
+    mov r5, r0
+    mov r0, r5
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, [fp, #-12]
+    mov r5, r0
+    mov r0, r5
+    str r0, [fp, #-32]
+    ldr r0, [fp, #-32]
+    ldr r0, [r0, #0]
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    mov r0, r5
+    str r0, [fp, #-36]
+    ldr r0, =9
+    ldr r1, [fp, #-36]
+    str r0, [r1, #0]
+    ldrb r0, [fp, #-28]
+    mov r1, r0
+    ldr r0, =1
+    cmp r1, r0
+    mov r0, #0
+    moveq r0, #1
+    mov r5, r0
+    mov r0, r5
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =1
+    mov r3, r0
+    ldrb r0, [fp, #-16]
+    mov r2, r0
+    ldr r0, [fp, #-4]
+    mov r1, r0
+    ldrb r0, [fp, #-24]
+    bl func_0
+    mov r5, r0
+    mov r0, r5
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, [fp, #-20]
+    mov r1, r0
+    ldr r0, [fp, #-4]
+    add r0, r1, r0
+    mov r5, r0
+    ldr r0, =4
+    mov r3, r0
+    ldr r0, =1
+    mov r2, r0
+    mov r0, r5
+    mov r1, r0
+    mov r0, r4
+    bl func_0
+    mov r4, r0
+    mov r0, r4
+    b ret_1
+block_8:
+    b block_6
+ret_1:
+    ldr r4, [fp, #-40]
+    ldr r5, [fp, #-44]
+    add sp, sp, #48
+    pop {fp, lr}
+    bx lr
+main:
+    push {fp, lr}
+    mov fp, sp
+    bl func_1
+    mov sp, fp
+    pop {fp, lr}
+    bx lr
@@ -0,0 +1,649 @@
+.global main
+.extern printf
+.align 8
+.data
+fmt: .asciz "%d\n"
+.text
+func_0:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #40
+    str r4, [fp, #-28]
+    str r5, [fp, #-32]
+    str r6, [fp, #-36]
+    str r7, [fp, #-40]
+    str r0, [fp, #-16]
+    str r1, [fp, #-20]
+    str r2, [fp, #-12]
+    str r3, [fp, #-8]
+    add r1, fp, #8
+    sub r0, fp, #4
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    add r1, fp, #12
+    sub r0, fp, #24
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    b block_10
+block_1:
+    mov r0, r4
+    mov r1, r0
+    mov r0, r5
+    add r0, r1, r0
+    mov r7, r0
+    mov r0, r7
+    mov r1, r0
+    ldr r0, [fp, #-12]
+    add r0, r1, r0
+    mov r7, r0
+    mov r0, r7
+    mov r1, r0
+    ldr r0, [fp, #-8]
+    add r0, r1, r0
+    mov r7, r0
+    mov r0, r7
+    mov r1, r0
+    ldr r0, [fp, #-4]
+    add r0, r1, r0
+    mov r7, r0
+    mov r0, r7
+    mov r1, r0
+    mov r0, r6
+    add r0, r1, r0
+    mov r7, r0
+    mov r0, r7
+    b ret_0
+block_10:
+    ldr r0, [fp, #-16]
+    mov r4, r0
+    ldr r0, [fp, #-20]
+    mov r5, r0
+    ldr r0, [fp, #-24]
+    mov r6, r0
+    b block_1
+ret_0:
+    ldr r4, [fp, #-28]
+    ldr r5, [fp, #-32]
+    ldr r6, [fp, #-36]
+    ldr r7, [fp, #-40]
+    add sp, sp, #40
+    pop {fp, lr}
+    bx lr
+func_1:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #72
+    str r4, [fp, #-48]
+    str r5, [fp, #-52]
+    str r6, [fp, #-56]
+    str r7, [fp, #-60]
+    str r8, [fp, #-64]
+    str r9, [fp, #-68]
+    str r0, [fp, #-4]
+    str r1, [fp, #-32]
+    str r2, [fp, #-36]
+    str r3, [fp, #-40]
+    add r1, fp, #8
+    sub r0, fp, #16
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    add r1, fp, #20
+    sub r0, fp, #28
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    add r1, fp, #32
+    sub r0, fp, #44
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    b block_11
+block_2:
+    mov r0, r4
+    mov r1, r0
+    mov r0, r5
+    add r0, r1, r0
+    mov r8, r0
+    mov r0, r8
+    mov r1, r0
+    mov r0, r6
+    add r0, r1, r0
+    mov r8, r0
+    ldr r0, [fp, #-12]
+    mov r1, r0
+    ldr r0, [fp, #-24]
+    add r0, r1, r0
+    mov r9, r0
+    mov r0, r8
+    ldr r1, [fp, #-4]
+    str r0, [r1, #0]
+    mov r0, r9
+    ldr r1, [fp, #-4]
+    str r0, [r1, #4]
+    ldr r0, [fp, #-20]
+    ldr r1, [fp, #-4]
+    str r0, [r1, #8]
+    mov r0, r7
+    ldr r1, [fp, #-4]
+    str r0, [r1, #12]
+    ldr r0, [fp, #-4]
+    b ret_1
+block_11:
+    ldr r0, [fp, #-32]
+    mov r4, r0
+    ldr r0, [fp, #-36]
+    mov r5, r0
+    ldr r0, [fp, #-40]
+    mov r6, r0
+    ldr r0, [fp, #-44]
+    mov r7, r0
+    b block_2
+ret_1:
+    ldr r4, [fp, #-48]
+    ldr r5, [fp, #-52]
+    ldr r6, [fp, #-56]
+    ldr r7, [fp, #-60]
+    ldr r8, [fp, #-64]
+    ldr r9, [fp, #-68]
+    add sp, sp, #72
+    pop {fp, lr}
+    bx lr
+func_2:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #24
+    str r4, [fp, #-20]
+    str r0, [fp, #-4]
+    str r1, [fp, #-8]
+    b block_12
+block_3:
+    mov r0, r4
+    cmp r0, #1
+    beq block_5
+    b block_4
+block_4:
+    sub r0, fp, #4
+    mov r1, r0
+    sub r0, fp, #12
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldr r0, [fp, #-12]
+    b ret_2
+block_5:
+    ldrb r0, [fp, #-2]
+    strb r0, [fp, #-16]
+    ldrb r0, [fp, #-3]
+    strb r0, [fp, #-15]
+    ldrb r0, [fp, #-4]
+    strb r0, [fp, #-14]
+    ldr r0, [fp, #-16]
+    b ret_2
+block_12:
+    ldrb r0, [fp, #-8]
+    mov r4, r0
+    b block_3
+ret_2:
+    ldr r4, [fp, #-20]
+    add sp, sp, #24
+    pop {fp, lr}
+    bx lr
+func_3:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #80
+    str r4, [fp, #-68]
+    str r5, [fp, #-72]
+    str r6, [fp, #-76]
+    str r7, [fp, #-80]
+    str r0, [fp, #-32]
+    str r1, [fp, #-12]
+    str r2, [fp, #-8]
+    str r3, [fp, #-4]
+    add r1, fp, #8
+    sub r0, fp, #28
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    add r1, fp, #24
+    sub r0, fp, #36
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    b block_13
+block_6:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =0
+    cmp r1, r0
+    mov r0, #0
+    moveq r0, #1
+    mov r6, r0
+    mov r0, r6
+    cmp r0, #1
+    beq block_8
+    b block_7
+block_7:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =1
+    sub r0, r1, r0
+    mov r6, r0
+    mov r0, r5
+    mov r1, r0
+    ldr r0, [fp, #-12]
+    add r0, r1, r0
+    mov r7, r0
+    mov r0, r7
+    mov r1, r0
+    ldr r0, [fp, #-16]
+    add r0, r1, r0
+    mov r7, r0
+    sub r0, fp, #12
+    mov r1, r0
+    sub r0, fp, #48
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #28
+    mov r1, r0
+    sub r0, fp, #64
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    sub sp, sp, #24
+    sub r0, fp, #64
+    add r1, r0, #0
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    mov r0, r7
+    str r0, [sp, #16]
+    sub r0, fp, #48
+    ldr r3, [r0, #8]
+    ldr r2, [r0, #4]
+    ldr r1, [r0, #0]
+    mov r0, r6
+    bl func_3
+    add sp, sp, #24
+    mov r6, r0
+    mov r0, r6
+    b ret_3
+block_8:
+    mov r0, r5
+    b ret_3
+block_13:
+    ldr r0, [fp, #-32]
+    mov r4, r0
+    ldr r0, [fp, #-36]
+    mov r5, r0
+    b block_6
+ret_3:
+    ldr r4, [fp, #-68]
+    ldr r5, [fp, #-72]
+    ldr r6, [fp, #-76]
+    ldr r7, [fp, #-80]
+    add sp, sp, #80
+    pop {fp, lr}
+    bx lr
+func_4:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #200
+    str r4, [fp, #-196]
+    b block_14
+block_9:
+    ldr r0, =10
+    str r0, [fp, #-12]
+    ldr r0, =20
+    str r0, [fp, #-8]
+    ldr r0, =30
+    str r0, [fp, #-4]
+    sub r0, fp, #12
+    mov r1, r0
+    sub r0, fp, #68
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub sp, sp, #8
+    sub r0, fp, #68
+    add r1, r0, #8
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r0, =48
+    str r0, [sp, #4]
+    sub r0, fp, #68
+    ldr r3, [r0, #4]
+    ldr r2, [r0, #0]
+    ldr r0, =2
+    mov r1, r0
+    ldr r0, =1
+    bl func_0
+    add sp, sp, #8
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    sub r0, fp, #12
+    mov r1, r0
+    sub r0, fp, #96
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r0, =0
+    str r0, [fp, #-108]
+    ldr r0, =1000
+    str r0, [fp, #-104]
+    ldr r0, =2000
+    str r0, [fp, #-100]
+    sub sp, sp, #32
+    sub r0, fp, #96
+    add r1, r0, #0
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #108
+    add r1, r0, #0
+    add r0, sp, #12
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r0, =15
+    str r0, [sp, #24]
+    ldr r0, =3
+    mov r3, r0
+    ldr r0, =2
+    mov r2, r0
+    ldr r0, =1
+    mov r1, r0
+    sub r0, fp, #84
+    bl func_1
+    add sp, sp, #32
+    sub r0, fp, #84
+    mov r1, r0
+    sub r0, fp, #48
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    sub r0, fp, #48
+    mov r1, r0
+    sub r0, fp, #28
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r0, [fp, #-24]
+    mov r1, r0
+    ldr r0, [fp, #-20]
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, [fp, #-28]
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, [fp, #-16]
+    sub r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =15
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =0
+    mov r1, r0
+    ldr r0, =996
+    sub r0, r1, r0
+    mov r4, r0
+    ldr r0, [fp, #-16]
+    str r0, [fp, #-120]
+    ldr r0, =0
+    str r0, [fp, #-116]
+    ldr r0, =0
+    str r0, [fp, #-112]
+    sub sp, sp, #8
+    sub r0, fp, #120
+    add r1, r0, #8
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    mov r0, r4
+    str r0, [sp, #4]
+    sub r0, fp, #120
+    ldr r3, [r0, #4]
+    ldr r2, [r0, #0]
+    ldr r0, [fp, #-24]
+    mov r1, r0
+    ldr r0, [fp, #-28]
+    bl func_0
+    add sp, sp, #8
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =1
+    strb r0, [fp, #-124]
+    ldr r0, =0
+    strb r0, [fp, #-123]
+    ldr r0, =0
+    strb r0, [fp, #-122]
+    ldr r0, =1
+    mov r1, r0
+    ldr r0, [fp, #-124]
+    bl func_2
+    str r0, [fp, #-128]
+    sub r0, fp, #128
+    mov r1, r0
+    sub r0, fp, #51
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    sub r0, fp, #51
+    mov r1, r0
+    sub r0, fp, #132
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldr r0, =0
+    mov r1, r0
+    ldr r0, [fp, #-132]
+    bl func_2
+    str r0, [fp, #-136]
+    sub r0, fp, #136
+    mov r1, r0
+    sub r0, fp, #54
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    sub r0, fp, #54
+    mov r1, r0
+    sub r0, fp, #31
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldrb r0, [fp, #-29]
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =1
+    str r0, [fp, #-148]
+    ldr r0, =0
+    str r0, [fp, #-144]
+    ldr r0, =0
+    str r0, [fp, #-140]
+    sub r0, fp, #28
+    mov r1, r0
+    sub r0, fp, #164
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    sub sp, sp, #24
+    sub r0, fp, #164
+    add r1, r0, #0
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r0, =0
+    str r0, [sp, #16]
+    sub r0, fp, #148
+    ldr r3, [r0, #8]
+    ldr r2, [r0, #4]
+    ldr r1, [r0, #0]
+    ldr r0, =5
+    bl func_3
+    add sp, sp, #24
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =70
+    sub r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    sub r0, fp, #12
+    mov r1, r0
+    sub r0, fp, #176
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r0, =0
+    str r0, [fp, #-192]
+    ldr r0, =0
+    str r0, [fp, #-188]
+    ldr r0, =0
+    str r0, [fp, #-184]
+    ldr r0, =4
+    str r0, [fp, #-180]
+    sub sp, sp, #24
+    sub r0, fp, #192
+    add r1, r0, #0
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r0, =0
+    str r0, [sp, #16]
+    sub r0, fp, #176
+    ldr r3, [r0, #8]
+    ldr r2, [r0, #4]
+    ldr r1, [r0, #0]
+    ldr r0, =2
+    bl func_3
+    add sp, sp, #24
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, [fp, #-28]
+    mov r1, r0
+    ldr r0, =1
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    b ret_4
+block_14:
+    b block_9
+ret_4:
+    ldr r4, [fp, #-196]
+    add sp, sp, #200
+    pop {fp, lr}
+    bx lr
+main:
+    push {fp, lr}
+    mov fp, sp
+    bl func_4
+    mov sp, fp
+    pop {fp, lr}
+    bx lr
@@ -0,0 +1,281 @@
+.global main
+.extern printf
+.align 8
+.data
+fmt: .asciz "%d\n"
+.text
+func_0:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #16
+    str r4, [fp, #-12]
+    str r0, [fp, #-4]
+    str r1, [fp, #-8]
+    b block_8
+block_1:
+    mov r0, r4
+    cmp r0, #1
+    beq block_3
+    b block_2
+block_2:
+    ldr r0, =0
+    ldr r1, [fp, #-4]
+    str r0, [r1, #0]
+    ldr r0, =1
+    ldr r1, [fp, #-4]
+    str r0, [r1, #4]
+    ldr r0, [fp, #-4]
+    b ret_0
+block_3:
+    ldr r0, =1
+    ldr r1, [fp, #-4]
+    str r0, [r1, #0]
+    ldr r0, =10
+    ldr r1, [fp, #-4]
+    str r0, [r1, #8]
+    ldr r0, =20
+    ldr r1, [fp, #-4]
+    str r0, [r1, #4]
+    ldr r0, [fp, #-4]
+    b ret_0
+block_8:
+    ldrb r0, [fp, #-8]
+    mov r4, r0
+    b block_1
+ret_0:
+    ldr r4, [fp, #-12]
+    add sp, sp, #16
+    pop {fp, lr}
+    bx lr
+func_1:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #24
+    str r4, [fp, #-20]
+    str r0, [fp, #-12]
+    str r1, [fp, #-8]
+    str r2, [fp, #-4]
+    str r3, [fp, #-16]
+    b block_9
+block_4:
+    mov r0, r4
+    b ret_1
+block_9:
+    ldr r0, [fp, #-16]
+    mov r4, r0
+    b block_4
+ret_1:
+    ldr r4, [fp, #-20]
+    add sp, sp, #24
+    pop {fp, lr}
+    bx lr
+func_2:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #16
+    str r4, [fp, #-12]
+    str r0, [fp, #-4]
+    str r1, [fp, #-8]
+    b block_10
+block_5:
+    ldr r0, [fp, #-4]
+    b ret_2
+block_10:
+    ldr r0, [fp, #-8]
+    mov r4, r0
+    b block_5
+ret_2:
+    ldr r4, [fp, #-12]
+    add sp, sp, #16
+    pop {fp, lr}
+    bx lr
+func_3:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #144
+    str r4, [fp, #-140]
+    b block_11
+block_6:
+    ldr r0, =0
+    str r0, [fp, #-12]
+    ldr r0, =3
+    str r0, [fp, #-8]
+    ldr r0, =1
+    str r0, [fp, #-12]
+    ldr r0, =2
+    str r0, [fp, #-4]
+    ldr r0, =5
+    str r0, [fp, #-8]
+    ldr r0, =1
+    mov r1, r0
+    sub r0, fp, #100
+    bl func_0
+    sub r0, fp, #100
+    mov r1, r0
+    sub r0, fp, #76
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #76
+    mov r1, r0
+    sub r0, fp, #12
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r0, =1
+    str r0, [fp, #-16]
+    ldr r0, [fp, #-16]
+    str r0, [fp, #-104]
+    ldr r0, =0
+    mov r1, r0
+    ldr r0, [fp, #-104]
+    bl func_2
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =1
+    str r0, [fp, #-116]
+    ldr r0, =1
+    str r0, [fp, #-108]
+    ldr r0, =1
+    str r0, [fp, #-112]
+    ldr r0, =2
+    mov r3, r0
+    sub r0, fp, #116
+    ldr r2, [r0, #8]
+    ldr r1, [r0, #4]
+    ldr r0, [r0, #0]
+    bl func_1
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =2
+    str r0, [fp, #-28]
+    ldr r0, =42
+    str r0, [fp, #-32]
+    ldr r0, [fp, #-32]
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =0
+    str r0, [fp, #-28]
+    ldr r0, [fp, #-16]
+    str r0, [fp, #-24]
+    sub r0, fp, #28
+    mov r4, r0
+    mov r0, r4
+    str r0, [fp, #-80]
+    ldr r0, =1
+    ldr r1, [fp, #-80]
+    str r0, [r1, #0]
+    ldr r0, =3
+    ldr r1, [fp, #-80]
+    str r0, [r1, #8]
+    ldr r0, =4
+    ldr r1, [fp, #-80]
+    str r0, [r1, #4]
+    ldr r0, [fp, #-32]
+    mov r1, r0
+    ldr r0, =37
+    sub r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =7
+    mov r1, r0
+    sub r0, fp, #124
+    bl func_4
+    sub r0, fp, #124
+    mov r1, r0
+    sub r0, fp, #88
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #88
+    mov r1, r0
+    sub r0, fp, #40
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r0, =1
+    str r0, [fp, #-48]
+    ldr r0, =0
+    str r0, [fp, #-64]
+    ldr r0, =0
+    str r0, [fp, #-60]
+    ldr r0, =2
+    str r0, [fp, #-56]
+    ldr r0, =7
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =1
+    str r0, [fp, #-136]
+    ldr r0, =1
+    str r0, [fp, #-128]
+    ldr r0, =1
+    str r0, [fp, #-132]
+    ldr r0, =6
+    mov r3, r0
+    sub r0, fp, #136
+    ldr r2, [r0, #8]
+    ldr r1, [r0, #4]
+    ldr r0, [r0, #0]
+    bl func_1
+    mov r4, r0
+    mov r0, r4
+    b ret_3
+block_11:
+    b block_6
+ret_3:
+    ldr r4, [fp, #-140]
+    add sp, sp, #144
+    pop {fp, lr}
+    bx lr
+func_4:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #16
+    str r4, [fp, #-12]
+    str r0, [fp, #-4]
+    str r1, [fp, #-8]
+    b block_12
+block_7:
+    ldr r0, =0
+    ldr r1, [fp, #-4]
+    str r0, [r1, #0]
+    mov r0, r4
+    ldr r1, [fp, #-4]
+    str r0, [r1, #4]
+    ldr r0, [fp, #-4]
+    b ret_4
+block_12:
+    ldr r0, [fp, #-8]
+    mov r4, r0
+    b block_7
+ret_4:
+    ldr r4, [fp, #-12]
+    add sp, sp, #16
+    pop {fp, lr}
+    bx lr
+main:
+    push {fp, lr}
+    mov fp, sp
+    bl func_3
+    mov sp, fp
+    pop {fp, lr}
+    bx lr
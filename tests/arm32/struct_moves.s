@@ -0,0 +1,524 @@
+.global main
+.extern printf
+.align 8
+.data
+fmt: .asciz "%d\n"
+.text
+func_0:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #80
+    str r4, [fp, #-80]
+    str r0, [fp, #-76]
+    str r1, [fp, #-72]
+    str r2, [fp, #-68]
+    str r3, [fp, #-64]
+    add r1, fp, #8
+    sub r0, fp, #60
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r2, [r1, #16]
+    str r2, [r0, #16]
+    ldr r2, [r1, #20]
+    str r2, [r0, #20]
+    ldr r2, [r1, #24]
+    str r2, [r0, #24]
+    ldr r2, [r1, #28]
+    str r2, [r0, #28]
+    ldr r2, [r1, #32]
+    str r2, [r0, #32]
+    ldr r2, [r1, #36]
+    str r2, [r0, #36]
+    ldr r2, [r1, #40]
+    str r2, [r0, #40]
+    ldr r2, [r1, #44]
+    str r2, [r0, #44]
+    ldr r2, [r1, #48]
+    str r2, [r0, #48]
+    ldr r2, [r1, #52]
+    str r2, [r0, #52]
+    ldr r2, [r1, #56]
+    str r2, [r0, #56]
+    b block_10
+block_1:
+    ldr r0, =0
+    str r0, [fp, #-76]
+    ldr r0, [fp, #-68]
+    mov r1, r0
+    ldr r0, [fp, #-52]
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, [fp, #-8]
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, [fp, #-76]
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    b ret_0
+block_10:
+    b block_1
+ret_0:
+    ldr r4, [fp, #-80]
+    add sp, sp, #80
+    pop {fp, lr}
+    bx lr
+func_1:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #16
+    str r4, [fp, #-8]
+    str r5, [fp, #-12]
+    str r0, [fp, #-4]
+    b block_11
+block_2:
+    ldr r0, =0
+    mov r4, r0
+    ldrb r0, [fp, #-4]
+    cmp r0, #1
+    beq block_4
+    b block_3
+block_3:
+    ldrb r0, [fp, #-3]
+    cmp r0, #1
+    beq block_6
+    b block_5
+block_4:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =1
+    add r0, r1, r0
+    mov r5, r0
+    mov r0, r5
+    mov r4, r0
+    b block_3
+block_5:
+    ldrb r0, [fp, #-2]
+    cmp r0, #1
+    beq block_8
+    b block_7
+block_6:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =1
+    add r0, r1, r0
+    mov r5, r0
+    mov r0, r5
+    mov r4, r0
+    b block_5
+block_7:
+    mov r0, r4
+    b ret_1
+block_8:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =1
+    add r0, r1, r0
+    mov r5, r0
+    mov r0, r5
+    mov r4, r0
+    b block_7
+block_11:
+    b block_2
+ret_1:
+    ldr r4, [fp, #-8]
+    ldr r5, [fp, #-12]
+    add sp, sp, #16
+    pop {fp, lr}
+    bx lr
+func_2:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #432
+    str r4, [fp, #-432]
+    b block_12
+block_9:
+    ldr r0, =1
+    strb r0, [fp, #-3]
+    ldr r0, =0
+    strb r0, [fp, #-2]
+    ldr r0, =1
+    strb r0, [fp, #-1]
+    sub r0, fp, #3
+    mov r1, r0
+    sub r0, fp, #6
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldr r0, =1
+    strb r0, [fp, #-5]
+    sub r0, fp, #6
+    mov r1, r0
+    sub r0, fp, #272
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldr r0, [fp, #-272]
+    bl func_1
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =10
+    str r0, [fp, #-16]
+    ldr r0, =20
+    str r0, [fp, #-12]
+    sub r0, fp, #3
+    mov r1, r0
+    sub r0, fp, #20
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    sub r0, fp, #20
+    mov r1, r0
+    sub r0, fp, #32
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r0, =0
+    str r0, [fp, #-28]
+    ldr r0, [fp, #-16]
+    mov r1, r0
+    ldr r0, [fp, #-24]
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    sub r0, fp, #20
+    mov r1, r0
+    sub r0, fp, #104
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #32
+    mov r1, r0
+    sub r0, fp, #92
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #20
+    mov r1, r0
+    sub r0, fp, #80
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #32
+    mov r1, r0
+    sub r0, fp, #68
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #20
+    mov r1, r0
+    sub r0, fp, #56
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r0, =13
+    str r0, [fp, #-40]
+    ldr r0, =0
+    str r0, [fp, #-36]
+    sub r0, fp, #6
+    mov r1, r0
+    sub r0, fp, #44
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldr r0, =7
+    str r0, [fp, #-108]
+    sub r0, fp, #108
+    mov r1, r0
+    sub r0, fp, #184
+    ldr r3, =19
+copy_1:
+    ldr r2, [r1], #4
+    str r2, [r0], #4
+    subs r3, r3, #1
+    bne copy_1
+    sub r0, fp, #184
+    mov r4, r0
+    mov r0, r4
+    str r0, [fp, #-264]
+    ldr r0, [fp, #-264]
+    add r0, r0, #0
+    mov r1, r0
+    sub r0, fp, #260
+    ldr r3, =19
+copy_2:
+    ldr r2, [r1], #4
+    str r2, [r0], #4
+    subs r3, r3, #1
+    bne copy_2
+    mov r0, r4
+    str r0, [fp, #-268]
+    sub r0, fp, #32
+    mov r1, r0
+    ldr r0, [fp, #-268]
+    add r0, r0, #4
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #32
+    mov r1, r0
+    ldr r0, [fp, #-268]
+    add r0, r0, #16
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #32
+    mov r1, r0
+    ldr r0, [fp, #-268]
+    add r0, r0, #28
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #32
+    mov r1, r0
+    ldr r0, [fp, #-268]
+    add r0, r0, #40
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #32
+    mov r1, r0
+    ldr r0, [fp, #-268]
+    add r0, r0, #52
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #32
+    mov r1, r0
+    ldr r0, [fp, #-268]
+    add r0, r0, #64
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r0, =1
+    ldr r1, [fp, #-268]
+    str r0, [r1, #0]
+    ldrb r0, [fp, #-195]
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    sub r0, fp, #260
+    mov r1, r0
+    sub r0, fp, #348
+    ldr r3, =19
+copy_3:
+    ldr r2, [r1], #4
+    str r2, [r0], #4
+    subs r3, r3, #1
+    bne copy_3
+    sub sp, sp, #64
+    sub r0, fp, #348
+    add r1, r0, #16
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r2, [r1, #16]
+    str r2, [r0, #16]
+    ldr r2, [r1, #20]
+    str r2, [r0, #20]
+    ldr r2, [r1, #24]
+    str r2, [r0, #24]
+    ldr r2, [r1, #28]
+    str r2, [r0, #28]
+    ldr r2, [r1, #32]
+    str r2, [r0, #32]
+    ldr r2, [r1, #36]
+    str r2, [r0, #36]
+    ldr r2, [r1, #40]
+    str r2, [r0, #40]
+    ldr r2, [r1, #44]
+    str r2, [r0, #44]
+    ldr r2, [r1, #48]
+    str r2, [r0, #48]
+    ldr r2, [r1, #52]
+    str r2, [r0, #52]
+    ldr r2, [r1, #56]
+    str r2, [r0, #56]
+    sub r0, fp, #348
+    ldr r3, [r0, #12]
+    ldr r2, [r0, #8]
+    ldr r1, [r0, #4]
+    ldr r0, [r0, #0]
+    bl func_0
+    add sp, sp, #64
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, [fp, #-260]
+    mov r1, r0
+    ldr r0, =7
+    cmp r1, r0
+    mov r0, #0
+    moveq r0, #1
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    sub r0, fp, #184
+    mov r1, r0
+    sub r0, fp, #424
+    ldr r3, =19
+copy_4:
+    ldr r2, [r1], #4
+    str r2, [r0], #4
+    subs r3, r3, #1
+    bne copy_4
+    sub sp, sp, #64
+    sub r0, fp, #424
+    add r1, r0, #16
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r2, [r1, #16]
+    str r2, [r0, #16]
+    ldr r2, [r1, #20]
+    str r2, [r0, #20]
+    ldr r2, [r1, #24]
+    str r2, [r0, #24]
+    ldr r2, [r1, #28]
+    str r2, [r0, #28]
+    ldr r2, [r1, #32]
+    str r2, [r0, #32]
+    ldr r2, [r1, #36]
+    str r2, [r0, #36]
+    ldr r2, [r1, #40]
+    str r2, [r0, #40]
+    ldr r2, [r1, #44]
+    str r2, [r0, #44]
+    ldr r2, [r1, #48]
+    str r2, [r0, #48]
+    ldr r2, [r1, #52]
+    str r2, [r0, #52]
+    ldr r2, [r1, #56]
+    str r2, [r0, #56]
+    sub r0, fp, #424
+    ldr r3, [r0, #12]
+    ldr r2, [r0, #8]
+    ldr r1, [r0, #4]
+    ldr r0, [r0, #0]
+    bl func_0
+    add sp, sp, #64
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, [fp, #-184]
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    sub r0, fp, #44
+    mov r1, r0
+    sub r0, fp, #428
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldr r0, [fp, #-428]
+    bl func_1
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =3
+    cmp r1, r0
+    mov r0, #0
+    moveq r0, #1
+    mov r4, r0
+    mov r0, r4
+    b ret_2
+block_12:
+    b block_9
+ret_2:
+    ldr r4, [fp, #-432]
+    add sp, sp, #432
+    pop {fp, lr}
+    bx lr
+main:
+    push {fp, lr}
+    mov fp, sp
+    bl func_2
+    mov sp, fp
+    pop {fp, lr}
+    bx lr
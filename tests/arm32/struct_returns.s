@@ -0,0 +1,773 @@
+.global main
+.extern printf
+.align 8
+.data
+fmt: .asciz "%d\n"
+.text
+func_0:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #24
+    str r4, [fp, #-16]
+    str r5, [fp, #-20]
+    str r0, [fp, #-4]
+    str r1, [fp, #-8]
+    str r2, [fp, #-12]
+    b block_18
+block_1:
+    mov r0, r4
+    ldr r1, [fp, #-4]
+    str r0, [r1, #0]
+    mov r0, r5
+    ldr r1, [fp, #-4]
+    str r0, [r1, #4]
+    ldr r0, [fp, #-4]
+    b ret_0
+block_18:
+    ldr r0, [fp, #-8]
+    mov r4, r0
+    ldr r0, [fp, #-12]
+    mov r5, r0
+    b block_1
+ret_0:
+    ldr r4, [fp, #-16]
+    ldr r5, [fp, #-20]
+    add sp, sp, #24
+    pop {fp, lr}
+    bx lr
+func_1:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #16
+    str r0, [fp, #-4]
+    str r1, [fp, #-12]
+    str r2, [fp, #-8]
+    b block_19
+block_2:
+    ldr r0, [fp, #-8]
+    ldr r1, [fp, #-4]
+    str r0, [r1, #0]
+    ldr r0, [fp, #-12]
+    ldr r1, [fp, #-4]
+    str r0, [r1, #4]
+    ldr r0, [fp, #-4]
+    b ret_1
+block_19:
+    b block_2
+ret_1:
+    add sp, sp, #16
+    pop {fp, lr}
+    bx lr
+func_2:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #24
+    str r4, [fp, #-20]
+    str r0, [fp, #-4]
+    str r1, [fp, #-16]
+    b block_20
+block_3:
+    mov r0, r4
+    str r0, [fp, #-8]
+    mov r0, r4
+    str r0, [fp, #-12]
+    ldr r0, [fp, #-8]
+    ldr r0, [r0, #4]
+    ldr r1, [fp, #-4]
+    str r0, [r1, #0]
+    ldr r0, [fp, #-12]
+    ldr r0, [r0, #0]
+    ldr r1, [fp, #-4]
+    str r0, [r1, #4]
+    ldr r0, [fp, #-4]
+    b ret_2
+block_20:
+    ldr r0, [fp, #-16]
+    mov r4, r0
+    b block_3
+ret_2:
+    ldr r4, [fp, #-20]
+    add sp, sp, #24
+    pop {fp, lr}
+    bx lr
+func_3:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #48
+    str r4, [fp, #-48]
+    str r0, [fp, #-4]
+    str r1, [fp, #-12]
+    str r2, [fp, #-8]
+    str r3, [fp, #-20]
+    add r1, fp, #8
+    sub r0, fp, #16
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    b block_21
+block_4:
+    ldr r0, =1
+    strb r0, [fp, #-40]
+    sub r0, fp, #12
+    mov r1, r0
+    sub r0, fp, #36
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #20
+    mov r1, r0
+    sub r0, fp, #28
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r0, =0
+    str r0, [fp, #-44]
+    ldr r0, [fp, #-12]
+    mov r1, r0
+    ldr r0, [fp, #-16]
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    str r0, [fp, #-44]
+    sub r0, fp, #44
+    mov r1, r0
+    ldr r0, [fp, #-4]
+    add r0, r0, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r2, [r1, #16]
+    str r2, [r0, #16]
+    ldr r2, [r1, #20]
+    str r2, [r0, #20]
+    ldr r0, [fp, #-4]
+    b ret_3
+block_21:
+    b block_4
+ret_3:
+    ldr r4, [fp, #-48]
+    add sp, sp, #48
+    pop {fp, lr}
+    bx lr
+func_4:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #32
+    str r0, [fp, #-4]
+    str r1, [fp, #-28]
+    str r2, [fp, #-24]
+    str r3, [fp, #-20]
+    add r1, fp, #8
+    sub r0, fp, #16
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    b block_22
+block_5:
+    sub r0, fp, #20
+    mov r1, r0
+    ldr r0, [fp, #-4]
+    add r0, r0, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r0, [fp, #-4]
+    b ret_4
+block_22:
+    b block_5
+ret_4:
+    add sp, sp, #32
+    pop {fp, lr}
+    bx lr
+func_5:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #8
+    str r0, [fp, #-4]
+    b block_23
+block_6:
+    ldrb r0, [fp, #-2]
+    strb r0, [fp, #-8]
+    ldrb r0, [fp, #-3]
+    strb r0, [fp, #-7]
+    ldrb r0, [fp, #-4]
+    strb r0, [fp, #-6]
+    ldr r0, [fp, #-8]
+    b ret_5
+block_23:
+    b block_6
+ret_5:
+    add sp, sp, #8
+    pop {fp, lr}
+    bx lr
+func_6:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #56
+    str r4, [fp, #-52]
+    str r5, [fp, #-56]
+    str r0, [fp, #-4]
+    str r1, [fp, #-32]
+    b block_24
+block_7:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =0
+    cmp r1, r0
+    mov r0, #0
+    moveq r0, #1
+    mov r5, r0
+    mov r0, r5
+    cmp r0, #1
+    beq block_9
+    b block_8
+block_8:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =1
+    sub r0, r1, r0
+    mov r5, r0
+    mov r0, r5
+    mov r1, r0
+    sub r0, fp, #40
+    bl func_6
+    sub r0, fp, #40
+    mov r1, r0
+    sub r0, fp, #28
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #28
+    mov r1, r0
+    sub r0, fp, #12
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r0, [fp, #-12]
+    mov r1, r0
+    ldr r0, [fp, #-8]
+    add r0, r1, r0
+    mov r5, r0
+    ldr r0, [fp, #-8]
+    ldr r1, [fp, #-4]
+    str r0, [r1, #0]
+    mov r0, r5
+    ldr r1, [fp, #-4]
+    str r0, [r1, #4]
+    ldr r0, [fp, #-4]
+    b ret_6
+block_9:
+    ldr r0, =1
+    mov r2, r0
+    ldr r0, =0
+    mov r1, r0
+    sub r0, fp, #48
+    bl func_0
+    sub r0, fp, #48
+    mov r1, r0
+    sub r0, fp, #20
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #20
+    mov r1, r0
+    ldr r0, [fp, #-4]
+    add r0, r0, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r0, [fp, #-4]
+    b ret_6
+block_24:
+    ldr r0, [fp, #-32]
+    mov r4, r0
+    b block_7
+ret_6:
+    ldr r4, [fp, #-52]
+    ldr r5, [fp, #-56]
+    add sp, sp, #56
+    pop {fp, lr}
+    bx lr
+func_7:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #16
+    str r4, [fp, #-12]
+    str r5, [fp, #-16]
+    str r0, [fp, #-4]
+    b block_25
+block_10:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =0
+    cmp r1, r0
+    mov r0, #0
+    moveq r0, #1
+    mov r5, r0
+    mov r0, r4
+    strb r0, [fp, #-8]
+    mov r0, r5
+    strb r0, [fp, #-7]
+    ldr r0, [fp, #-8]
+    b ret_7
+block_25:
+    ldrb r0, [fp, #-4]
+    mov r4, r0
+    b block_10
+ret_7:
+    ldr r4, [fp, #-12]
+    ldr r5, [fp, #-16]
+    add sp, sp, #16
+    pop {fp, lr}
+    bx lr
+func_8:
+    push {fp, lr}
+    mov fp, sp
+    sub sp, sp, #344
+    str r4, [fp, #-340]
+    str r5, [fp, #-344]
+    b block_26
+block_11:
+    ldr r0, =4
+    mov r2, r0
+    ldr r0, =3
+    mov r1, r0
+    sub r0, fp, #172
+    bl func_0
+    sub r0, fp, #172
+    mov r1, r0
+    sub r0, fp, #72
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #72
+    mov r1, r0
+    sub r0, fp, #8
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r0, [fp, #-8]
+    mov r1, r0
+    ldr r0, [fp, #-4]
+    add r0, r1, r0
+    mov r4, r0
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    sub r0, fp, #8
+    mov r1, r0
+    sub r0, fp, #188
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #188
+    ldr r2, [r0, #4]
+    ldr r1, [r0, #0]
+    sub r0, fp, #180
+    bl func_1
+    sub r0, fp, #180
+    mov r1, r0
+    sub r0, fp, #80
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #80
+    mov r1, r0
+    sub r0, fp, #16
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #16
+    mov r1, r0
+    sub r0, fp, #196
+    bl func_2
+    sub r0, fp, #196
+    mov r1, r0
+    sub r0, fp, #88
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #88
+    mov r1, r0
+    sub r0, fp, #16
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r0, [fp, #-16]
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, [fp, #-12]
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    sub r0, fp, #8
+    mov r1, r0
+    sub r0, fp, #212
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #212
+    ldr r2, [r0, #4]
+    ldr r1, [r0, #0]
+    sub r0, fp, #204
+    bl func_1
+    sub r0, fp, #204
+    mov r1, r0
+    sub r0, fp, #96
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #8
+    mov r1, r0
+    sub r0, fp, #244
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub r0, fp, #96
+    mov r1, r0
+    sub r0, fp, #252
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    sub sp, sp, #8
+    sub r0, fp, #252
+    add r1, r0, #4
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    sub r0, fp, #252
+    ldr r3, [r0, #0]
+    sub r0, fp, #244
+    ldr r2, [r0, #4]
+    ldr r1, [r0, #0]
+    sub r0, fp, #236
+    bl func_3
+    add sp, sp, #8
+    sub r0, fp, #236
+    mov r1, r0
+    sub r0, fp, #120
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r2, [r1, #16]
+    str r2, [r0, #16]
+    ldr r2, [r1, #20]
+    str r2, [r0, #20]
+    sub r0, fp, #120
+    mov r1, r0
+    sub r0, fp, #40
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r2, [r1, #16]
+    str r2, [r0, #16]
+    ldr r2, [r1, #20]
+    str r2, [r0, #20]
+    sub r0, fp, #40
+    mov r1, r0
+    sub r0, fp, #292
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r2, [r1, #16]
+    str r2, [r0, #16]
+    ldr r2, [r1, #20]
+    str r2, [r0, #20]
+    sub sp, sp, #16
+    sub r0, fp, #292
+    add r1, r0, #12
+    add r0, sp, #0
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    sub r0, fp, #292
+    ldr r3, [r0, #8]
+    ldr r2, [r0, #4]
+    ldr r1, [r0, #0]
+    sub r0, fp, #268
+    bl func_4
+    add sp, sp, #16
+    sub r0, fp, #268
+    mov r1, r0
+    sub r0, fp, #136
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    sub r0, fp, #136
+    mov r1, r0
+    sub r0, fp, #56
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r2, [r1, #8]
+    str r2, [r0, #8]
+    ldr r2, [r1, #12]
+    str r2, [r0, #12]
+    ldr r0, [fp, #-44]
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldrb r0, [fp, #-36]
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =10
+    mov r1, r0
+    sub r0, fp, #300
+    bl func_6
+    sub r0, fp, #300
+    mov r1, r0
+    sub r0, fp, #144
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    ldr r0, [fp, #-144]
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =1
+    strb r0, [fp, #-304]
+    ldr r0, =0
+    strb r0, [fp, #-303]
+    ldr r0, =0
+    strb r0, [fp, #-302]
+    ldr r0, [fp, #-304]
+    bl func_5
+    str r0, [fp, #-308]
+    sub r0, fp, #308
+    mov r1, r0
+    sub r0, fp, #147
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    sub r0, fp, #147
+    mov r1, r0
+    sub r0, fp, #59
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    sub r0, fp, #59
+    mov r1, r0
+    sub r0, fp, #312
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldr r0, [fp, #-312]
+    bl func_5
+    str r0, [fp, #-316]
+    sub r0, fp, #316
+    mov r1, r0
+    sub r0, fp, #150
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    sub r0, fp, #150
+    mov r1, r0
+    sub r0, fp, #320
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldr r0, [fp, #-320]
+    bl func_5
+    str r0, [fp, #-324]
+    sub r0, fp, #324
+    mov r1, r0
+    sub r0, fp, #153
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    sub r0, fp, #153
+    mov r1, r0
+    sub r0, fp, #62
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldrb r2, [r1, #2]
+    strb r2, [r0, #2]
+    ldr r0, =0
+    mov r4, r0
+    ldrb r0, [fp, #-60]
+    cmp r0, #1
+    beq block_13
+    b block_12
+block_12:
+    ldrb r0, [fp, #-61]
+    cmp r0, #1
+    beq block_15
+    b block_14
+block_13:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =1
+    add r0, r1, r0
+    mov r5, r0
+    mov r0, r5
+    mov r4, r0
+    b block_12
+block_14:
+    ldrb r0, [fp, #-57]
+    cmp r0, #1
+    beq block_17
+    b block_16
+block_15:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =10
+    add r0, r1, r0
+    mov r5, r0
+    mov r0, r5
+    mov r4, r0
+    b block_14
+block_16:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldr r0, =0
+    bl func_7
+    str r0, [fp, #-328]
+    sub r0, fp, #328
+    mov r1, r0
+    sub r0, fp, #155
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    sub r0, fp, #155
+    mov r1, r0
+    sub r0, fp, #64
+    ldrb r2, [r1, #0]
+    strb r2, [r0, #0]
+    ldrb r2, [r1, #1]
+    strb r2, [r0, #1]
+    ldr r0, [fp, #-40]
+    mov r1, r0
+    ldr r0, [fp, #-52]
+    sub r0, r1, r0
+    mov r5, r0
+    mov r0, r5
+    mov r1, r0
+    ldr r0, =1
+    sub r0, r1, r0
+    mov r5, r0
+    ldr r0, =1
+    mov r1, r0
+    sub r0, fp, #336
+    bl func_6
+    sub r0, fp, #336
+    mov r1, r0
+    sub r0, fp, #164
+    ldr r2, [r1, #0]
+    str r2, [r0, #0]
+    ldr r2, [r1, #4]
+    str r2, [r0, #4]
+    mov r0, r5
+    mov r1, r0
+    ldr r0, [fp, #-160]
+    add r0, r1, r0
+    mov r5, r0
+    mov r0, r5
+    mov r1, r0
+    ldr r0, =fmt
+    bl printf
+    ldrb r0, [fp, #-63]
+    b ret_8
+block_17:
+    mov r0, r4
+    mov r1, r0
+    ldr r0, =20
+    add r0, r1, r0
+    mov r5, r0
+    mov r0, r5
+    mov r4, r0
+    b block_16
+block_26:
+    b block_11
+ret_8:
+    ldr r4, [fp, #-340]
+    ldr r5, [fp, #-344]
+    add sp, sp, #344
+    pop {fp, lr}
+    bx lr
+main:
+    push {fp, lr}
+    mov fp, sp
+    bl func_8
+    mov sp, fp
+    pop {fp, lr}
+    bx lr
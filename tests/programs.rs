@@ -0,0 +1,354 @@
+// End-to-end tests: every program under tests/src is compiled and checked against the
+// expectations in its header comments:
+//
+//     // exit: 1                      exit code of the program
+//     // stdout: 42                   one line of expected output (repeatable, in order)
+//     // error: E0301 must be a       compilation fails with this code, message containing the rest
+//                                      (repeatable: one per error reported, in order)
+//
+// A program without any header only has to compile. Run expectations are checked against the
+// HIR, MIR and LIR interpreters (the LIR one with both 32 and 64-bit layouts), the C backend, and
+// the program compiled for the host where we have a backend for it. ARM32 code also gets run off
+// ARM hosts when there's a cross compiler and qemu to run it with.
+// Since each stage has to match the same expectations, a mismatch points at the lowering to blame.
+//
+// On top of that, the ARM32 assembly of the programs under tests/arm32 has to match the copy kept
+// there, so that the ARM backend is looked at even where nothing can run it. Running the tests with
+// YUM_BLESS=1 rewrites those copies with what's generated now.
+
+use std::fs;
+use std::env;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use minicompiler::{compile_str, CCompiler, CompileOptions, Stage, Target};
+use minicompiler::interp::hir::HIRInterpreter;
+use minicompiler::interp::mir::MIRInterpreter;
+use minicompiler::interp::lir::LIRInterpreter;
+use minicompiler::stages::hir::HIRProgram;
+use minicompiler::stages::mir::MIRProgram;
+use minicompiler::stages::lir::LIRProgram;
+
+
+#[derive(Debug, Default)]
+struct Expectation {
+    exit: Option<i32>,
+    stdout: Option<Vec<String>>,
+    errors: Vec<(String, String)>,
+}
+
+impl Expectation {
+
+    fn parse(source: &str) -> Result<Expectation, String> {
+        let mut expect = Expectation::default();
+        for line in source.lines() {
+            let Some(comment) = line.trim().strip_prefix("//") else { break };
+            let Some((key, value)) = comment.split_once(':') else { continue };
+            let value = value.trim();
+            match key.trim() {
+                "exit" => {
+                    let code = value.parse().map_err(|_| format!("bad exit code `{}`", value))?;
+                    expect.exit = Some(code);
+                }
+                "stdout" => expect.stdout.get_or_insert_with(Vec::new).push(value.to_string()),
+                "error" => {
+                    let (code, message) = value.split_once(' ').unwrap_or((value, ""));
+                    expect.errors.push((code.to_string(), message.trim().to_string()));
+                }
+                _ => {}
+            }
+        }
+        if !expect.errors.is_empty() && expect.runs() {
+            return Err("a program can't both fail to compile and run".to_string());
+        }
+        Ok(expect)
+    }
+
+    fn runs(&self) -> bool {
+        self.exit.is_some() || self.stdout.is_some()
+    }
+}
+
+
+struct Outcome {
+    exit: i32,
+    stdout: String,
+}
+
+impl Outcome {
+
+    fn check(&self, expect: &Expectation, backend: &str) -> Result<(), String> {
+        if let Some(code) = expect.exit
+            && self.exit != code {
+            return Err(format!("{}: expected exit code {}, got {}", backend, code, self.exit));
+        }
+        if let Some(lines) = &expect.stdout {
+            let got: Vec<&str> = self.stdout.lines().collect();
+            if got != *lines {
+                return Err(format!("{}: expected stdout {:?}, got {:?}", backend, lines, got));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Builds and runs the program on the host through cc, so only possible where the host understands
+// what we generated for the target
+fn run_native(name: &str, target: Target, code: &str) -> Result<Outcome, String> {
+    build_and_run(name, target, code, &["cc"], &[])
+}
+
+// Statically linked, since qemu wouldn't know where to find an ARM libc
+fn run_arm32_qemu(name: &str, code: &str) -> Result<Outcome, String> {
+    build_and_run(name, Target::Arm32, code, &[ARM32_CC, "-static"], &[ARM32_QEMU])
+}
+
+// Runs the executable under `runner` when there is one
+fn build_and_run(name: &str, target: Target, code: &str, compiler: &[&str], runner: &[&str]) -> Result<Outcome, String> {
+    let dir = env::temp_dir();
+    let stem = format!("yumtest-{}-{}-{}", std::process::id(), name, target.name());
+    let asm_path = dir.join(format!("{}.{}", stem, target.source_ext()));
+    let exe_path = dir.join(stem);
+    fs::write(&asm_path, code).map_err(|e| e.to_string())?;
+    let link = Command::new(compiler[0])
+        .args(&compiler[1..])
+        .arg("-o")
+        .arg(&exe_path)
+        .arg(&asm_path)
+        .output()
+        .map_err(|e| format!("couldn't invoke {}: {}", compiler[0], e))?;
+    let _ = fs::remove_file(&asm_path);
+    if !link.status.success() {
+        return Err(format!("linking failed:\n{}", String::from_utf8_lossy(&link.stderr)));
+    }
+    let output = match runner.split_first() {
+        Some((program, args)) => Command::new(program).args(args).arg(&exe_path).output(),
+        None => Command::new(&exe_path).output(),
+    };
+    let output = output.map_err(|e| e.to_string());
+    let _ = fs::remove_file(&exe_path);
+    let output = output?;
+    Ok(Outcome {
+        exit: output.status.code().unwrap_or(-1),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+    })
+}
+
+fn run_hir_interp(hir: &HIRProgram) -> Result<Outcome, String> {
+    let mut stdout: Vec<u8> = Vec::new();
+    let exit = HIRInterpreter::run(hir, &mut stdout).map_err(|e| e.to_string())?;
+    Ok(Outcome { exit, stdout: String::from_utf8(stdout).unwrap() })
+}
+
+fn run_mir_interp(mir: &MIRProgram) -> Result<Outcome, String> {
+    let mut stdout: Vec<u8> = Vec::new();
+    let exit = MIRInterpreter::run(mir, &mut stdout).map_err(|e| e.to_string())?;
+    Ok(Outcome { exit, stdout: String::from_utf8(stdout).unwrap() })
+}
+
+fn run_lir_interp(lir: &LIRProgram) -> Result<Outcome, String> {
+    let mut stdout: Vec<u8> = Vec::new();
+    let exit = LIRInterpreter::run(lir, &mut stdout).map_err(|e| e.to_string())?;
+    Ok(Outcome { exit, stdout: String::from_utf8(stdout).unwrap() })
+}
+
+fn lower_for(source: &str, target: Target) -> Result<LIRProgram, String> {
+    let options = CompileOptions { stop_after: Stage::Lir, keep: vec![Stage::Lir], target };
+    let artifacts = compile_str(source, &options).map_err(|_| format!("failed to compile for {}", target.name()))?;
+    Ok(artifacts.lir.unwrap())
+}
+
+fn native_backend_available() -> bool {
+    Target::host().runs_on_host()
+}
+
+const ARM32_CC: &str = "arm-linux-gnueabihf-gcc";
+const ARM32_QEMU: &str = "qemu-arm";
+
+// Only worth it where ARM32 isn't already what runs natively
+fn arm32_qemu_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let works = |program: &str| Command::new(program).arg("--version").output().is_ok_and(|out| out.status.success());
+        Target::host() != Target::Arm32 && works(ARM32_CC) && works(ARM32_QEMU)
+    })
+}
+
+
+fn check_program(path: &Path) -> Result<(), String> {
+    let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let expect = Expectation::parse(&source)?;
+
+    let options = CompileOptions {
+        stop_after: if expect.runs() { Stage::Asm } else { Stage::Lir },
+        keep: vec![Stage::Hir, Stage::Mir, Stage::Lir, Stage::Asm],
+        target: Target::host(),
+    };
+    let artifacts = match compile_str(&source, &options) {
+        Ok(_) if !expect.errors.is_empty() => {
+            return Err(format!("expected error {}, but it compiled", expect.errors[0].0));
+        }
+        Err(diags) if !expect.errors.is_empty() => {
+            let matching = diags.len() == expect.errors.len()
+                && diags
+                    .iter()
+                    .zip(&expect.errors)
+                    .all(|(d, (code, message))| d.code.as_str() == code && d.message.contains(message.as_str()));
+            if !matching {
+                let expected: Vec<String> = expect.errors.iter().map(|(code, message)| format!("{}: {}", code, message)).collect();
+                let got: Vec<String> = diags.iter().map(|d| format!("{}: {}", d.code, d.message)).collect();
+                return Err(format!("expected errors {:?}, got: {:?}", expected, got));
+            }
+            return Ok(());
+        }
+        Err(diags) => {
+            let rendered: Vec<String> = diags.iter().map(|d| d.render(&source, &path.display().to_string())).collect();
+            return Err(format!("failed to compile:\n{}", rendered.concat()));
+        }
+        Ok(artifacts) => artifacts,
+    };
+
+    if !expect.runs() {
+        return Ok(());
+    }
+    run_hir_interp(artifacts.hir.as_ref().unwrap())?.check(&expect, "hir interpreter")?;
+    run_mir_interp(artifacts.mir.as_ref().unwrap())?.check(&expect, "mir interpreter")?;
+    run_lir_interp(artifacts.lir.as_ref().unwrap())?.check(&expect, "lir interpreter")?;
+    // The same LIR laid out for the other word size, since only one of them gets run natively
+    let other = if Target::host() == Target::Arm32 { Target::X86_64 } else { Target::Arm32 };
+    let other_lir = lower_for(&source, other)?;
+    run_lir_interp(&other_lir)?.check(&expect, &format!("lir interpreter ({})", other.name()))?;
+    let c_source = CCompiler::compile(artifacts.mir.as_ref().unwrap());
+    run_native(&name, Target::C, &c_source)?.check(&expect, "c")?;
+    if native_backend_available() {
+        run_native(&name, Target::host(), artifacts.asm.as_deref().unwrap())?.check(&expect, "native")?;
+    }
+    if arm32_qemu_available() {
+        let options = CompileOptions { stop_after: Stage::Asm, keep: vec![Stage::Asm], target: Target::Arm32 };
+        let artifacts = compile_str(&source, &options).map_err(|_| "failed to compile for arm32".to_string())?;
+        run_arm32_qemu(&name, artifacts.asm.as_deref().unwrap())?.check(&expect, "arm32 (qemu)")?;
+    }
+    Ok(())
+}
+
+// In a thread of its own, so that a compiler that panics or never finishes only fails that program
+fn check_isolated(path: &Path) -> Result<(), String> {
+    let (sender, receiver) = mpsc::channel();
+    let path = path.to_path_buf();
+    thread::spawn(move || {
+        let result = panic::catch_unwind(|| check_program(&path)).unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|msg| msg.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            Err(format!("panicked: {}", message))
+        });
+        let _ = sender.send(result);
+    });
+    match receiver.recv_timeout(PROGRAM_TIMEOUT) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(format!("didn't finish in {}s", PROGRAM_TIMEOUT.as_secs())),
+        Err(RecvTimeoutError::Disconnected) => Err("the checking thread died".to_string()),
+    }
+}
+
+const PROGRAM_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn files_with_ext(dir: &Path, ext: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == ext))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn tests_dir(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join(name)
+}
+
+fn check_arm32_golden(golden: &Path) -> Result<(), String> {
+    let source_path = tests_dir("src").join(golden.file_name().unwrap()).with_extension("yum");
+    let source = fs::read_to_string(&source_path).map_err(|e| format!("{}: {}", source_path.display(), e))?;
+    let options = CompileOptions { stop_after: Stage::Asm, keep: vec![Stage::Asm], target: Target::Arm32 };
+    let asm = compile_str(&source, &options)
+        .map_err(|_| "failed to compile".to_string())?
+        .asm
+        .unwrap();
+    if env::var_os("YUM_BLESS").is_some() {
+        return fs::write(golden, asm).map_err(|e| e.to_string());
+    }
+    let expected = fs::read_to_string(golden).map_err(|e| e.to_string())?;
+    match expected.lines().zip(asm.lines()).position(|(exp, got)| exp != got) {
+        Some(i) => Err(format!(
+            "differs at line {}: expected `{}`, got `{}`",
+            i + 1,
+            expected.lines().nth(i).unwrap(),
+            asm.lines().nth(i).unwrap(),
+        )),
+        None if expected.lines().count() != asm.lines().count() => Err(format!(
+            "expected {} lines, got {}",
+            expected.lines().count(),
+            asm.lines().count(),
+        )),
+        None => Ok(()),
+    }
+}
+
+
+#[test]
+fn yum_programs() {
+    let dir = tests_dir("src");
+    let paths = files_with_ext(&dir, "yum");
+    assert!(!paths.is_empty(), "no programs found in {}", dir.display());
+
+    let mut failures: Vec<String> = Vec::new();
+    for path in &paths {
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        match check_isolated(path) {
+            Ok(()) => eprintln!("{} ... ok", file_name),
+            Err(msg) => {
+                eprintln!("{} ... FAILED", file_name);
+                failures.push(format!("{}: {}", file_name, msg));
+            }
+        }
+    }
+    if !native_backend_available() {
+        eprintln!("note: no native backend on this host; only the interpreters were checked");
+    }
+    if !arm32_qemu_available() && Target::host() != Target::Arm32 {
+        eprintln!("note: no {} and {} on this host, so ARM32 code wasn't run", ARM32_CC, ARM32_QEMU);
+    }
+    assert!(failures.is_empty(), "{} of {} programs failed:\n{}", failures.len(), paths.len(), failures.join("\n"));
+}
+
+#[test]
+fn arm32_assembly() {
+    let dir = tests_dir("arm32");
+    let goldens = files_with_ext(&dir, "s");
+    assert!(!goldens.is_empty(), "no assembly found in {}", dir.display());
+
+    let failures: Vec<String> = goldens
+        .iter()
+        .filter_map(|golden| {
+            check_arm32_golden(golden)
+                .err()
+                .map(|msg| format!("{}: {}", golden.file_name().unwrap().to_string_lossy(), msg))
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} ARM32 programs changed (rerun with YUM_BLESS=1 if that's intended):\n{}",
+        failures.len(),
+        goldens.len(),
+        failures.join("\n"),
+    );
+}